@@ -16,29 +16,59 @@ const UTAH_TEAPOT: &[u8] = include_bytes!("../data/stl/utah_teapot.stl");
 const RESOLUTION: usize = 100;
 
 fn cast_teapot_generic<'a, C: Castable<'a, ff32>>(b: &mut Bencher, castable: &'a C) {
-    let pov = ff32_3::new(ff32(0.0), ff32(0.0), ff32(26.0));
-    let screen_00 = ff32_3::new(ff32(-0.5), ff32(0.5), ff32(25.0));
+    // 1x1 screen at distance 1 from the camera
+    let camera = Camera::perspective(
+        ff32_3::new(ff32(0.0), ff32(0.0), ff32(26.0)),
+        ff32_3::ZERO,
+        ff32_3::EY,
+        ff32(2.0 * 0.5f32.atan()),
+        ff32(1.0),
+    );
     let screen_step = ff32(1.0) / ff32::from_usize(RESOLUTION);
 
     b.iter(|| {
         for pixel_x in 0..RESOLUTION {
             for pixel_y in 0..RESOLUTION {
-                let screen_rel = ff32_3::new(
+                let ray = camera.ray(
                     screen_step * ff32::from_usize(pixel_x),
-                    -screen_step * ff32::from_usize(pixel_y),
-                    ff32(0.0),
+                    screen_step * ff32::from_usize(pixel_y),
                 );
 
-                let screen_p = screen_00 + screen_rel;
+                let isec = castable.cast_ray(ray, ff32(0.0), ff32(2000.0));
 
-                let ray = Ray {
-                    src: pov,
-                    dir1: (screen_p - pov).norm(),
-                };
+                black_box(isec);
+            }
+        }
+    })
+}
 
-                let isec = castable.cast_ray(ray, ff32(2000.0));
+/// Casts packets of `L / 2` rows by 2 columns of pixels.
+fn cast_teapot_packet_generic<'a, C: Castable<'a, ff32>, const L: usize>(
+    b: &mut Bencher,
+    castable: &'a C,
+) {
+    let camera = Camera::perspective(
+        ff32_3::new(ff32(0.0), ff32(0.0), ff32(26.0)),
+        ff32_3::ZERO,
+        ff32_3::EY,
+        ff32(2.0 * 0.5f32.atan()),
+        ff32(1.0),
+    );
+    let screen_step = ff32(1.0) / ff32::from_usize(RESOLUTION);
 
-                black_box(isec);
+    b.iter(|| {
+        for pixel_x in (0..RESOLUTION).step_by(2) {
+            for pixel_y in (0..RESOLUTION).step_by(L / 2) {
+                let rays: [Ray<ff32>; L] = std::array::from_fn(|i| {
+                    camera.ray(
+                        screen_step * ff32::from_usize(pixel_x + i % 2),
+                        screen_step * ff32::from_usize(pixel_y + i / 2),
+                    )
+                });
+
+                let isecs = castable.cast_packet(&rays, ff32(0.0), ff32(2000.0));
+
+                black_box(isecs);
             }
         }
     })
@@ -52,6 +82,16 @@ fn cast_teapot_triangle_list(b: &mut Bencher) {
     cast_teapot_generic(b, &triangles)
 }
 
+#[bench]
+fn cast_teapot_bvh(b: &mut Bencher) {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    let bvh = Bvh::build(&triangles.triangles);
+
+    cast_teapot_generic(b, &bvh.with_triangles(&triangles.triangles))
+}
+
 #[bench]
 fn cast_teapot_kd_tree(b: &mut Bencher) {
     let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
@@ -59,7 +99,17 @@ fn cast_teapot_kd_tree(b: &mut Bencher) {
 
     let tree = BspTree::build_kd(&triangles.triangles);
 
-    cast_teapot_generic(b, &tree)
+    cast_teapot_generic(b, &tree.with_triangles(&triangles.triangles))
+}
+
+#[bench]
+fn cast_teapot_kd_sah_tree(b: &mut Bencher) {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    let tree = BspTree::build_kd_sah(&triangles.triangles, &KdSahOptions::default());
+
+    cast_teapot_generic(b, &tree.with_triangles(&triangles.triangles))
 }
 
 #[bench]
@@ -70,5 +120,52 @@ fn cast_teapot_bsp_tree(b: &mut Bencher) {
     let mut rng = SmallRng::seed_from_u64(117);
     let tree = BspTree::build_tri_randomized(&triangles.triangles, &mut rng, 1);
 
-    cast_teapot_generic(b, &tree)
+    cast_teapot_generic(b, &tree.with_triangles(&triangles.triangles))
+}
+
+#[bench]
+fn cast_teapot_triangle_list_packet_4(b: &mut Bencher) {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    cast_teapot_packet_generic::<_, 4>(b, &triangles)
+}
+
+#[bench]
+fn cast_teapot_triangle_list_packet_8(b: &mut Bencher) {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    cast_teapot_packet_generic::<_, 8>(b, &triangles)
+}
+
+#[bench]
+fn cast_teapot_kd_sah_tree_packet_4(b: &mut Bencher) {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    let tree = BspTree::build_kd_sah(&triangles.triangles, &KdSahOptions::default());
+
+    cast_teapot_packet_generic::<_, 4>(b, &tree.with_triangles(&triangles.triangles))
+}
+
+#[bench]
+fn cast_teapot_kd_sah_tree_packet_8(b: &mut Bencher) {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    let tree = BspTree::build_kd_sah(&triangles.triangles, &KdSahOptions::default());
+
+    cast_teapot_packet_generic::<_, 8>(b, &tree.with_triangles(&triangles.triangles))
+}
+
+#[bench]
+fn cast_teapot_bsp_tree_packet_8(b: &mut Bencher) {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    let mut rng = SmallRng::seed_from_u64(117);
+    let tree = BspTree::build_tri_randomized(&triangles.triangles, &mut rng, 1);
+
+    cast_teapot_packet_generic::<_, 8>(b, &tree.with_triangles(&triangles.triangles))
 }
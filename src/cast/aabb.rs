@@ -0,0 +1,140 @@
+use crate::math::*;
+
+use super::*;
+
+/// Axis-aligned bounding box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb<N: Num> {
+    pub min_coords: Vector3<N>,
+    pub max_coords: Vector3<N>,
+}
+
+impl<N: Num> Aabb<N> {
+    pub fn from_point(p: Vector3<N>) -> Self {
+        Self {
+            min_coords: p,
+            max_coords: p,
+        }
+    }
+
+    pub fn from_triangle(tri: &Triangle<N>) -> Self {
+        Self::from_point(tri.meta.a)
+            .extend(tri.meta.b)
+            .extend(tri.meta.c)
+    }
+
+    #[inline(always)]
+    pub fn extend(self, p: Vector3<N>) -> Self {
+        Self {
+            min_coords: Vector3::min_coords(self.min_coords, p),
+            max_coords: Vector3::max_coords(self.max_coords, p),
+        }
+    }
+
+    #[inline(always)]
+    pub fn union(a: Self, b: Self) -> Self {
+        Self {
+            min_coords: Vector3::min_coords(a.min_coords, b.min_coords),
+            max_coords: Vector3::max_coords(a.max_coords, b.max_coords),
+        }
+    }
+
+    pub fn center(&self) -> Vector3<N> {
+        (self.min_coords + self.max_coords) / (N::ONE + N::ONE)
+    }
+
+    pub fn size(&self) -> Vector3<N> {
+        self.max_coords - self.min_coords
+    }
+
+    pub fn surface_area(&self) -> N {
+        let size = self.size();
+        let two = N::ONE + N::ONE;
+
+        two * (size.x() * size.y() + size.y() * size.z() + size.z() * size.x())
+    }
+
+    /// Axis of the largest extent.
+    pub fn longest_axis(&self) -> usize {
+        let size = self.size();
+
+        if size.x() >= size.y() && size.x() >= size.z() {
+            0
+        } else if size.y() >= size.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Slab test; returns the distances along the ray where it enters and leaves the box,
+    /// clipped to `[min_d; max_d]`.
+    #[inline(always)]
+    pub fn cast_slab_ray(&self, ray: &SlabRay<N>, min_d: N, max_d: N) -> Option<(N, N)> {
+        let mut near_d = min_d;
+        let mut far_d = max_d;
+
+        for axis in 0..3 {
+            let src = ray.src.axis(axis);
+            let min = self.min_coords.axis(axis);
+            let max = self.max_coords.axis(axis);
+
+            if ray.is_parallel[axis] {
+                if src < min || src > max {
+                    return None;
+                }
+
+                continue;
+            }
+
+            let inv_dir = ray.inv_dir.axis(axis);
+            let mut d0 = (min - src) * inv_dir;
+            let mut d1 = (max - src) * inv_dir;
+
+            if d0 > d1 {
+                std::mem::swap(&mut d0, &mut d1);
+            }
+
+            if d0 > near_d {
+                near_d = d0;
+            }
+
+            if d1 < far_d {
+                far_d = d1;
+            }
+
+            if near_d > far_d {
+                return None;
+            }
+        }
+
+        Some((near_d, far_d))
+    }
+}
+
+/// Ray with a precomputed reciprocal direction, for repeated slab tests.
+#[derive(Debug, Clone, Copy)]
+pub struct SlabRay<N: Num> {
+    pub src: Vector3<N>,
+
+    /// zero along the axes the ray is parallel to
+    pub inv_dir: Vector3<N>,
+
+    /// axes with a zero direction component
+    pub is_parallel: [bool; 3],
+}
+
+impl<N: Num> From<Ray<N>> for SlabRay<N> {
+    fn from(ray: Ray<N>) -> Self {
+        let dir = ray.dir1;
+        let is_parallel = [dir.x(), dir.y(), dir.z()].map(|x| x == N::ZERO);
+
+        let inv = |x: N| if x == N::ZERO { N::ZERO } else { N::ONE / x };
+
+        Self {
+            src: ray.src,
+            inv_dir: Vector3::new(inv(dir.x()), inv(dir.y()), inv(dir.z())),
+            is_parallel,
+        }
+    }
+}
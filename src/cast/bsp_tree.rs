@@ -1,33 +1,117 @@
-use std::intrinsics::likely;
-
+use crate::formats::FormatError;
 use crate::math::*;
 
 use itertools::partition;
 use rand::prelude::*;
+use std::io::{Read, Write};
+use std::simd::prelude::*;
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 
 use super::*;
 
-pub struct BspTree<'a, N: Num> {
-    root: Option<Box<Node<'a, N>>>,
+/// Binary space partitioning tree over a triangle array it does not own.
+/// Nodes refer to triangles by index, so the tree can be moved, stored next to the triangles,
+/// or saved to a file; rays are cast through `with_triangles`.
+pub struct BspTree<N: Num> {
+    /// depth-first, starting with the root; children always come after their parent
+    nodes: Vec<Node<N>>,
+
+    /// triangle indices of all nodes; every node references a contiguous range
+    tri_indices: Vec<u32>,
+
+    /// length of the triangle array the tree was built for
+    n_triangles: usize,
 }
 
-struct Node<'a, N: Num> {
-    tris: Vec<&'a Triangle<N>>,
-    neg: Option<Box<Node<'a, N>>>,
-    pos: Option<Box<Node<'a, N>>>,
+struct Node<N: Num> {
+    /// own triangles are `tri_indices[tris_offset..tris_offset + n_tris]`
+    tris_offset: u32,
+    n_tris: u32,
+
+    /// indices into `nodes`
+    neg: Option<u32>,
+    pos: Option<u32>,
+
     origin: Vector3<N>,
     mat: Matrix3<N>,
 }
 
-impl<'a, N: Num> Node<'a, N> {
+impl<N: Num> BspTree<N> {
+    fn empty(n_triangles: usize) -> Self {
+        assert!(
+            u32::try_from(n_triangles).is_ok(),
+            "too many triangles for a tree: {n_triangles}"
+        );
+
+        Self {
+            nodes: Vec::new(),
+            tri_indices: Vec::new(),
+            n_triangles,
+        }
+    }
+
+    /// Appends a node without children; returns its index.
+    fn push_node(&mut self, origin: Vector3<N>, mat: Matrix3<N>, tris: &[u32]) -> u32 {
+        let i_node = u32::try_from(self.nodes.len()).expect("too many tree nodes");
+        let tris_offset =
+            u32::try_from(self.tri_indices.len()).expect("too many triangle references");
+
+        self.nodes.push(Node {
+            tris_offset,
+            n_tris: tris.len() as u32,
+            neg: None,
+            pos: None,
+            origin,
+            mat,
+        });
+        self.tri_indices.extend_from_slice(tris);
+
+        i_node
+    }
+
+    fn set_children(&mut self, i_node: u32, neg: Option<u32>, pos: Option<u32>) {
+        let node = &mut self.nodes[i_node as usize];
+        node.neg = neg;
+        node.pos = pos;
+    }
+
+    fn node_tris(&self, node: &Node<N>) -> &[u32] {
+        let offset = node.tris_offset as usize;
+        &self.tri_indices[offset..offset + node.n_tris as usize]
+    }
+
+    /// Length of the triangle array the tree was built for.
+    pub fn n_triangles(&self) -> usize {
+        self.n_triangles
+    }
+
+    /// Pairs the tree with the triangles it was built for, to cast rays through them.
+    pub fn with_triangles<'a>(&'a self, triangles: &'a [Triangle<N>]) -> BspTreeView<'a, N> {
+        assert_eq!(
+            triangles.len(),
+            self.n_triangles,
+            "the tree was built for a different triangle array"
+        );
+
+        BspTreeView {
+            tree: self,
+            triangles,
+        }
+    }
+}
+
+impl<N: Num> Node<N> {
     fn partition(
-        mut slice: &mut [&'a Triangle<N>],
+        triangles: &[Triangle<N>],
+        mut slice: &mut [u32],
         origin: Vector3<N>,
         mat: Matrix3<N>,
     ) -> (usize, usize) {
         // TODO: optimize
 
-        let i_neg = partition(&mut slice[..], |tri| {
+        let i_neg = partition(&mut slice[..], |&i| {
+            let tri = &triangles[i as usize];
             let a = mat * (tri.meta.a - origin);
             let b = mat * (tri.meta.b - origin);
             let c = mat * (tri.meta.c - origin);
@@ -38,7 +122,8 @@ impl<'a, N: Num> Node<'a, N> {
         slice = &mut slice[i_neg..];
 
         let i_pos = i_neg
-            + partition(slice, |tri| {
+            + partition(slice, |&i| {
+                let tri = &triangles[i as usize];
                 let a = mat * (tri.meta.a - origin);
                 let b = mat * (tri.meta.b - origin);
                 let c = mat * (tri.meta.c - origin);
@@ -50,73 +135,56 @@ impl<'a, N: Num> Node<'a, N> {
     }
 }
 
-impl<'a, N: Num> Node<'a, N> {
-    fn build_tri(slice: &mut [&'a Triangle<N>]) -> (usize, Option<Box<Self>>) {
+impl<N: Num> BspTree<N> {
+    /// Returns the height of the subtree and the index of its root.
+    fn build_tri(&mut self, triangles: &[Triangle<N>], slice: &mut [u32]) -> (usize, Option<u32>) {
         if slice.is_empty() {
             return (0, None);
         }
 
+        let tri = &triangles[slice[0] as usize];
+
         if slice.len() == 1 {
-            let tri = slice[0];
-
-            return (
-                1,
-                Some(Box::new(Self {
-                    tris: Vec::from(slice),
-                    neg: None,
-                    pos: None,
-                    origin: tri.a,
-                    mat: tri.m_abc,
-                })),
-            );
+            return (1, Some(self.push_node(tri.a, tri.m_abc, slice)));
         }
 
-        let tri = slice[0];
-
-        let (i_neg, i_pos) = Self::partition(slice, tri.a, tri.m_abc);
+        let (i_neg, i_pos) = Node::partition(triangles, slice, tri.a, tri.m_abc);
+        let i_node = self.push_node(tri.a, tri.m_abc, &slice[i_neg..i_pos]);
 
-        let (h_neg, neg) = Self::build_tri(&mut slice[..i_neg]);
-        let (h_pos, pos) = Self::build_tri(&mut slice[i_pos..]);
+        let (h_neg, neg) = self.build_tri(triangles, &mut slice[..i_neg]);
+        let (h_pos, pos) = self.build_tri(triangles, &mut slice[i_pos..]);
+        self.set_children(i_node, neg, pos);
 
-        (
-            1 + usize::max(h_neg, h_pos),
-            Some(Box::new(Self {
-                tris: Vec::from(&slice[i_neg..i_pos]),
-                neg,
-                pos,
-                origin: tri.a,
-                mat: tri.m_abc,
-            })),
-        )
+        (1 + usize::max(h_neg, h_pos), Some(i_node))
     }
-}
 
-impl<'a, N: Num> BspTree<'a, N> {
     pub fn build_tri_randomized<RNG: Rng>(
-        triangles: &'a [Triangle<N>],
+        triangles: &[Triangle<N>],
         rng: &mut RNG,
         n_retries: usize,
     ) -> Self {
-        let mut triangles: Vec<&'a Triangle<N>> = triangles.iter().collect();
+        let mut best_tree = Self::empty(triangles.len());
+        let mut indices: Vec<u32> = (0..triangles.len() as u32).collect();
 
         let mut min_height = triangles.len() + 1;
-        let mut best_root: Option<Box<Node<'a, N>>> = None;
 
         for _i in 0..n_retries {
-            triangles.shuffle(rng);
-            let root = Node::build_tri(&mut triangles);
+            indices.shuffle(rng);
+
+            let mut tree = Self::empty(triangles.len());
+            let root = tree.build_tri(triangles, &mut indices);
 
-            if let (height, Some(root)) = root && height < min_height {
+            if let (height, Some(_)) = root && height < min_height {
                 min_height = height;
-                best_root = Some(root);
+                best_tree = tree;
             }
         }
 
-        Self { root: best_root }
+        best_tree
     }
 }
 
-impl<'a, N: Num> Node<'a, N> {
+impl<N: Num> Node<N> {
     fn get_kd_mat(axis: usize) -> Matrix3<N> {
         let _0 = N::ZERO;
         let _1 = N::ONE;
@@ -130,11 +198,13 @@ impl<'a, N: Num> Node<'a, N> {
         }
     }
 
-    fn get_bounds(triangles: &[&'a Triangle<N>]) -> (Vector3<N>, Vector3<N>) {
-        let mut min_coords = triangles[0].a;
-        let mut max_coords = triangles[0].a;
+    fn get_bounds(triangles: &[Triangle<N>], slice: &[u32]) -> (Vector3<N>, Vector3<N>) {
+        let mut min_coords = triangles[slice[0] as usize].a;
+        let mut max_coords = triangles[slice[0] as usize].a;
+
+        for &i in slice {
+            let tri = &triangles[i as usize];
 
-        for tri in triangles {
             min_coords = Vector3::min_coords(min_coords, tri.meta.a);
             min_coords = Vector3::min_coords(min_coords, tri.meta.b);
             min_coords = Vector3::min_coords(min_coords, tri.meta.c);
@@ -146,145 +216,806 @@ impl<'a, N: Num> Node<'a, N> {
 
         (min_coords, max_coords)
     }
+}
 
-    fn build_kd(slice: &mut [&'a Triangle<N>], axis: usize) -> Option<Box<Self>> {
+impl<N: Num> BspTree<N> {
+    fn build_kd_node(
+        &mut self,
+        triangles: &[Triangle<N>],
+        slice: &mut [u32],
+        axis: usize,
+    ) -> Option<u32> {
         if slice.is_empty() {
             return None;
         }
 
-        let (min_coords, max_coords) = Self::get_bounds(slice);
+        let (min_coords, max_coords) = Node::get_bounds(triangles, slice);
         let origin = (min_coords + max_coords) / (N::ONE + N::ONE);
-        let mat = Self::get_kd_mat(axis);
+        let mat = Node::get_kd_mat(axis);
 
-        let (i_neg, i_pos) = Self::partition(slice, origin, mat);
+        let (i_neg, i_pos) = Node::partition(triangles, slice, origin, mat);
+        let i_node = self.push_node(origin, mat, &slice[i_neg..i_pos]);
 
-        let neg = Self::build_kd(&mut slice[..i_neg], axis + 1);
-        let pos = Self::build_kd(&mut slice[i_pos..], axis + 1);
+        let neg = self.build_kd_node(triangles, &mut slice[..i_neg], axis + 1);
+        let pos = self.build_kd_node(triangles, &mut slice[i_pos..], axis + 1);
+        self.set_children(i_node, neg, pos);
 
-        Some(Box::new(Self {
-            tris: Vec::from(&slice[i_neg..i_pos]),
-            mat,
-            origin,
-            neg,
-            pos,
-        }))
+        Some(i_node)
+    }
+
+    pub fn build_kd(triangles: &[Triangle<N>]) -> Self {
+        let mut tree = Self::empty(triangles.len());
+        let mut indices: Vec<u32> = (0..triangles.len() as u32).collect();
+
+        tree.build_kd_node(triangles, &mut indices, 0);
+
+        tree
     }
 }
 
-impl<'a, N: Num> BspTree<'a, N> {
-    pub fn build_kd(triangles: &'a [Triangle<N>]) -> Self {
-        let mut triangles: Vec<&'a Triangle<N>> = triangles.iter().collect();
+/// Parameters of `BspTree::build_kd_sah`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KdSahOptions {
+    /// nodes with this many triangles or fewer are not split
+    pub leaf_size: usize,
 
+    /// nodes this deep are not split; `None` picks it from the triangle count
+    pub max_depth: Option<usize>,
+}
+
+impl Default for KdSahOptions {
+    fn default() -> Self {
         Self {
-            root: Node::build_kd(&mut triangles, 0),
+            leaf_size: 4,
+            max_depth: None,
         }
     }
 }
 
-impl<'a, N: Num> Node<'a, N> {
-    fn cast_through_own(&'a self, ray: Ray<N>, max_d: N) -> Option<RayIntersection<'a, N>> {
-        let mut cur_d = max_d;
-        let mut cur_tri: Option<&Triangle<N>> = None;
-        let mut cur_p_abc = Vector3::ZERO;
+/// Relative cost of visiting a node against a ray-triangle test.
+const KD_TRAVERSAL_COST: usize = 1;
+
+impl<N: Num> BspTree<N> {
+    fn push_kd_leaf(&mut self, tris: Vec<(u32, Aabb<N>)>, bounds: &Aabb<N>) -> u32 {
+        let indices: Vec<u32> = tris.into_iter().map(|(i, _)| i).collect();
+
+        self.push_node(bounds.center(), Node::get_kd_mat(2), &indices)
+    }
+
+    /// Cheapest plane by the surface area heuristic, with its cost.
+    fn find_kd_sah_split(tris: &[(u32, Aabb<N>)], bounds: &Aabb<N>) -> Option<(usize, N, N)> {
+        let area = bounds.surface_area();
+        let mut best: Option<(usize, N, N)> = None;
 
-        for tri in self.tris.iter() {
-            let src_abc = tri.m_abc * (ray.src - tri.a);
-            let dir_abc = tri.m_abc * ray.dir1;
+        let sorted = |mut coords: Vec<N>| {
+            coords.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            coords
+        };
 
-            if src_abc.z() < N::EPS || dir_abc.z() > N::EPS {
-                continue;
+        for axis in 0..3 {
+            let min = bounds.min_coords.axis(axis);
+            let max = bounds.max_coords.axis(axis);
+
+            let tri_mins = sorted(tris.iter().map(|(_, b)| b.min_coords.axis(axis)).collect());
+            let tri_maxs = sorted(tris.iter().map(|(_, b)| b.max_coords.axis(axis)).collect());
+
+            for &plane in tri_mins.iter().chain(tri_maxs.iter()) {
+                if plane <= min || plane >= max {
+                    continue;
+                }
+
+                // triangles touching the plane go to both sides
+                let n_neg = tri_mins.partition_point(|&x| x <= plane);
+                let n_pos = tri_maxs.len() - tri_maxs.partition_point(|&x| x < plane);
+
+                let neg_bounds = Aabb {
+                    max_coords: bounds.max_coords.with_axis(axis, plane),
+                    ..*bounds
+                };
+                let pos_bounds = Aabb {
+                    min_coords: bounds.min_coords.with_axis(axis, plane),
+                    ..*bounds
+                };
+
+                let cost = N::from_usize(KD_TRAVERSAL_COST)
+                    + (neg_bounds.surface_area() * N::from_usize(n_neg)
+                        + pos_bounds.surface_area() * N::from_usize(n_pos))
+                        / area;
+
+                if best.is_none_or(|(_, _, best_cost)| cost < best_cost) {
+                    best = Some((axis, plane, cost));
+                }
             }
+        }
 
-            let d = -src_abc.z() / dir_abc.z();
+        best
+    }
 
-            if d >= cur_d {
-                continue;
+    fn build_kd_sah_node(
+        &mut self,
+        tris: Vec<(u32, Aabb<N>)>,
+        bounds: Aabb<N>,
+        depth: usize,
+        max_depth: usize,
+        options: &KdSahOptions,
+    ) -> Option<u32> {
+        if tris.is_empty() {
+            return None;
+        }
+
+        if tris.len() <= options.leaf_size || depth >= max_depth || bounds.surface_area() <= N::ZERO
+        {
+            return Some(self.push_kd_leaf(tris, &bounds));
+        }
+
+        let Some((axis, plane, cost)) = Self::find_kd_sah_split(&tris, &bounds) else {
+            return Some(self.push_kd_leaf(tris, &bounds));
+        };
+
+        if cost >= N::from_usize(tris.len()) {
+            return Some(self.push_kd_leaf(tris, &bounds));
+        }
+
+        let neg_tris = tris
+            .iter()
+            .copied()
+            .filter(|(_, b)| b.min_coords.axis(axis) <= plane)
+            .collect();
+        let pos_tris = tris
+            .iter()
+            .copied()
+            .filter(|(_, b)| b.max_coords.axis(axis) >= plane)
+            .collect();
+
+        let neg_bounds = Aabb {
+            max_coords: bounds.max_coords.with_axis(axis, plane),
+            ..bounds
+        };
+        let pos_bounds = Aabb {
+            min_coords: bounds.min_coords.with_axis(axis, plane),
+            ..bounds
+        };
+
+        let origin = bounds.center().with_axis(axis, plane);
+        let i_node = self.push_node(origin, Node::get_kd_mat(axis), &[]);
+
+        let neg = self.build_kd_sah_node(neg_tris, neg_bounds, depth + 1, max_depth, options);
+        let pos = self.build_kd_sah_node(pos_tris, pos_bounds, depth + 1, max_depth, options);
+        self.set_children(i_node, neg, pos);
+
+        Some(i_node)
+    }
+
+    /// Axis-aligned splits chosen by the surface area heuristic.
+    /// Triangles crossing a plane are referenced from both sides, so all of them end up in leaves.
+    pub fn build_kd_sah(triangles: &[Triangle<N>], options: &KdSahOptions) -> Self {
+        let mut tree = Self::empty(triangles.len());
+
+        let tris: Vec<(u32, Aabb<N>)> = triangles
+            .iter()
+            .enumerate()
+            .map(|(i, tri)| (i as u32, Aabb::from_triangle(tri)))
+            .collect();
+
+        let Some(bounds) = tris.iter().map(|&(_, b)| b).reduce(Aabb::union) else {
+            return tree;
+        };
+
+        let max_depth = options
+            .max_depth
+            .unwrap_or_else(|| 8 + 13 * tris.len().ilog2() as usize / 10);
+
+        tree.build_kd_sah_node(tris, bounds, 0, max_depth, options);
+
+        tree
+    }
+}
+
+/// Shape of a built tree, for tuning the builders.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BspTreeStats {
+    /// number of nodes on the longest path from the root
+    pub depth: usize,
+
+    pub n_nodes: usize,
+
+    /// nodes without children
+    pub n_leaves: usize,
+
+    /// triangle references in all nodes; a triangle can be referenced more than once
+    pub n_tri_refs: usize,
+
+    /// triangle references in leaves only
+    pub n_leaf_tris: usize,
+
+    pub max_leaf_tris: usize,
+}
+
+impl BspTreeStats {
+    pub fn mean_leaf_tris(&self) -> f64 {
+        if self.n_leaves == 0 {
+            0.0
+        } else {
+            self.n_leaf_tris as f64 / self.n_leaves as f64
+        }
+    }
+}
+
+impl<N: Num> BspTree<N> {
+    fn collect_stats(&self, i_node: u32, depth: usize, stats: &mut BspTreeStats) {
+        let node = &self.nodes[i_node as usize];
+        let n_tris = node.n_tris as usize;
+
+        stats.depth = stats.depth.max(depth + 1);
+        stats.n_nodes += 1;
+        stats.n_tri_refs += n_tris;
+
+        if node.neg.is_none() && node.pos.is_none() {
+            stats.n_leaves += 1;
+            stats.n_leaf_tris += n_tris;
+            stats.max_leaf_tris = stats.max_leaf_tris.max(n_tris);
+        }
+
+        for child in [node.neg, node.pos].into_iter().flatten() {
+            self.collect_stats(child, depth + 1, stats);
+        }
+    }
+
+    pub fn stats(&self) -> BspTreeStats {
+        let mut stats = BspTreeStats::default();
+
+        if !self.nodes.is_empty() {
+            self.collect_stats(0, 0, &mut stats);
+        }
+
+        stats
+    }
+}
+
+/// File signature, followed by the format version and the `BuildKey`.
+const FILE_MAGIC: &[u8; 8] = b"deer2bsp";
+const FILE_VERSION: u32 = 2;
+
+/// Marks a missing child in files.
+const FILE_NO_CHILD: u32 = u32::MAX;
+
+/// Deepest tree that is saved or loaded, as traversals recurse once per level.
+const FILE_MAX_DEPTH: usize = 1024;
+
+impl<N: Num> BspTree<N> {
+    /// Number of nodes on the longest path from the root, without recursion;
+    /// children always come after their parents, so one pass is enough.
+    fn depth(&self) -> usize {
+        let mut depths = vec![0; self.nodes.len()];
+
+        if let Some(root_depth) = depths.first_mut() {
+            *root_depth = 1;
+        }
+
+        for (i_node, node) in self.nodes.iter().enumerate() {
+            for child in [node.neg, node.pos].into_iter().flatten() {
+                depths[child as usize] = depths[child as usize].max(depths[i_node] + 1);
             }
+        }
+
+        depths.into_iter().max().unwrap_or(0)
+    }
+}
 
-            let p_abc = src_abc + dir_abc * d;
+impl<N: SimdNum> BspTree<N> {
+    /// Little-endian binary dump, with all coordinates as `f32`;
+    /// `key` is stored to be checked by whoever reads the file.
+    pub fn write_to<W: Write>(&self, writer: &mut W, key: &BuildKey) -> Result<(), FormatError> {
+        let oversize = || {
+            FormatError::OversizeDimension(format!(
+                "BSP tree of {} nodes for {} triangles",
+                self.nodes.len(),
+                self.n_triangles
+            ))
+        };
+
+        let n_triangles = u32::try_from(self.n_triangles).map_err(|_| oversize())?;
+        let n_nodes = u32::try_from(self.nodes.len()).map_err(|_| oversize())?;
+        let n_tri_indices = u32::try_from(self.tri_indices.len()).map_err(|_| oversize())?;
+
+        // the reader would reject it
+        let depth = self.depth();
+        if depth > FILE_MAX_DEPTH {
+            return Err(FormatError::OversizeDimension(format!(
+                "BSP tree of {depth} levels"
+            )));
+        }
 
-            if likely(
-                p_abc.x() < -N::EPS
-                    || p_abc.y() < -N::EPS
-                    || p_abc.x() + p_abc.y() > N::ONE + N::EPS,
-            ) {
-                continue;
+        writer.write_all(FILE_MAGIC)?;
+        writer.write_u32::<LE>(FILE_VERSION)?;
+        writer.write_u64::<LE>(key.builder)?;
+        writer.write_u64::<LE>(key.triangles)?;
+
+        writer.write_u32::<LE>(n_triangles)?;
+        writer.write_u32::<LE>(n_nodes)?;
+        writer.write_u32::<LE>(n_tri_indices)?;
+
+        let write_vector = |writer: &mut W, v: Vector3<N>| -> Result<(), FormatError> {
+            writer.write_f32::<LE>(v.x().to_f32())?;
+            writer.write_f32::<LE>(v.y().to_f32())?;
+            writer.write_f32::<LE>(v.z().to_f32())?;
+            Ok(())
+        };
+
+        for node in &self.nodes {
+            writer.write_u32::<LE>(node.tris_offset)?;
+            writer.write_u32::<LE>(node.n_tris)?;
+            writer.write_u32::<LE>(node.neg.unwrap_or(FILE_NO_CHILD))?;
+            writer.write_u32::<LE>(node.pos.unwrap_or(FILE_NO_CHILD))?;
+
+            write_vector(writer, node.origin)?;
+            write_vector(writer, node.mat.0)?;
+            write_vector(writer, node.mat.1)?;
+            write_vector(writer, node.mat.2)?;
+        }
+
+        for &i in &self.tri_indices {
+            writer.write_u32::<LE>(i)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads what `write_to` wrote, with its key, checking that every index is in range
+    /// and the tree is not too deep, so that casting rays through it cannot panic or loop.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<(Self, BuildKey), FormatError> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != FILE_MAGIC {
+            return Err(FormatError::InvalidHeader(
+                "not a BSP tree file".to_string(),
+            ));
+        }
+
+        let version = reader.read_u32::<LE>()?;
+        if version != FILE_VERSION {
+            return Err(FormatError::Unsupported(format!(
+                "BSP tree file version {version}"
+            )));
+        }
+
+        let key = BuildKey {
+            builder: reader.read_u64::<LE>()?,
+            triangles: reader.read_u64::<LE>()?,
+        };
+
+        let n_triangles = reader.read_u32::<LE>()? as usize;
+        let n_nodes = reader.read_u32::<LE>()?;
+        let n_tri_indices = reader.read_u32::<LE>()?;
+
+        let read_vector = |reader: &mut R| -> Result<Vector3<N>, FormatError> {
+            Ok(Vector3::new(
+                N::from_f32(reader.read_f32::<LE>()?),
+                N::from_f32(reader.read_f32::<LE>()?),
+                N::from_f32(reader.read_f32::<LE>()?),
+            ))
+        };
+
+        // children come after their parents, so there are no cycles
+        let read_child = |reader: &mut R, i_node: u32| -> Result<Option<u32>, FormatError> {
+            match reader.read_u32::<LE>()? {
+                FILE_NO_CHILD => Ok(None),
+                i_child if i_child > i_node && i_child < n_nodes => Ok(Some(i_child)),
+                i_child => Err(FormatError::InvalidData(format!(
+                    "node {i_node} has child {i_child}"
+                ))),
+            }
+        };
+
+        // do not trust the counts with a huge allocation before any node is read
+        let mut nodes = Vec::with_capacity((n_nodes as usize).min(1 << 20));
+
+        for i_node in 0..n_nodes {
+            let tris_offset = reader.read_u32::<LE>()?;
+            let n_tris = reader.read_u32::<LE>()?;
+
+            if tris_offset as u64 + n_tris as u64 > n_tri_indices as u64 {
+                return Err(FormatError::InvalidData(format!(
+                    "node {i_node} triangles are out of range"
+                )));
+            }
+
+            let neg = read_child(reader, i_node)?;
+            let pos = read_child(reader, i_node)?;
+
+            let origin = read_vector(reader)?;
+            let mat = Matrix3::from_rows(
+                read_vector(reader)?,
+                read_vector(reader)?,
+                read_vector(reader)?,
+            );
+
+            nodes.push(Node {
+                tris_offset,
+                n_tris,
+                neg,
+                pos,
+                origin,
+                mat,
+            });
+        }
+
+        let mut tri_indices = Vec::with_capacity((n_tri_indices as usize).min(1 << 20));
+
+        for _ in 0..n_tri_indices {
+            let i = reader.read_u32::<LE>()?;
+
+            if i as usize >= n_triangles {
+                return Err(FormatError::InvalidData(format!(
+                    "triangle index {i} of {n_triangles}"
+                )));
             }
 
-            cur_d = d;
-            cur_tri = Some(tri);
-            cur_p_abc = p_abc;
+            tri_indices.push(i);
         }
 
-        if let Some(cur_tri) = cur_tri {
-            Some(RayIntersection {
-                tri: cur_tri,
-                d: cur_d,
-                p_abc: cur_p_abc,
-            })
-        } else {
+        let tree = Self {
+            nodes,
+            tri_indices,
+            n_triangles,
+        };
+
+        if tree.depth() > FILE_MAX_DEPTH {
+            return Err(FormatError::InvalidData(format!(
+                "BSP tree deeper than {FILE_MAX_DEPTH} levels"
+            )));
+        }
+
+        Ok((tree, key))
+    }
+}
+
+/// A tree paired with the triangles it was built for; see `BspTree::with_triangles`.
+#[derive(Clone, Copy)]
+pub struct BspTreeView<'a, N: Num> {
+    tree: &'a BspTree<N>,
+    triangles: &'a [Triangle<N>],
+}
+
+impl<'a, N: Num> BspTreeView<'a, N> {
+    fn node(&self, i_node: u32) -> &'a Node<N> {
+        &self.tree.nodes[i_node as usize]
+    }
+
+    fn own_tris(&self, node: &Node<N>) -> impl Iterator<Item = &'a Triangle<N>> + 'a {
+        let triangles = self.triangles;
+
+        self.tree
+            .node_tris(node)
+            .iter()
+            .map(move |&i| &triangles[i as usize])
+    }
+
+    fn root(&self) -> Option<u32> {
+        if self.tree.nodes.is_empty() {
             None
+        } else {
+            Some(0)
         }
     }
+}
 
-    #[inline(always)]
-    fn choose_from_2(
-        isec1: Option<RayIntersection<'a, N>>,
-        isec2: Option<RayIntersection<'a, N>>,
+impl<'a, N: Num> BspTreeView<'a, N> {
+    fn cast_through_own(
+        &self,
+        node: &Node<N>,
+        ray: Ray<N>,
+        min_d: N,
+        max_d: N,
+        culling: Culling,
     ) -> Option<RayIntersection<'a, N>> {
-        match (isec1, isec2) {
-            (isec1, None) => isec1,
-            (None, isec2) => isec2,
-
-            (Some(isec1), Some(isec2)) => {
-                if isec1.d <= isec2.d {
-                    Some(isec1)
-                } else {
-                    Some(isec2)
-                }
+        let mut cur_d = max_d;
+        let mut cur_isec = None;
+
+        for tri in self.own_tris(node) {
+            if let Some(isec) = tri.cast_ray_between(ray, min_d, cur_d, culling) {
+                cur_d = isec.d;
+                cur_isec = Some(isec);
             }
         }
+
+        cur_isec
+    }
+}
+
+/// Children of a node paired with the parts of a ray interval in their half-spaces.
+struct Split<N: Num> {
+    /// the child containing the start of the interval
+    near: (Option<u32>, (N, N)),
+
+    /// the other child, if the interval crosses the plane
+    far: Option<(Option<u32>, (N, N))>,
+
+    /// hits closer than this are on the near side;
+    /// the end of the interval if it does not cross the plane
+    plane_d: N,
+}
+
+impl<'a, N: Num> BspTreeView<'a, N> {
+    /// The parts overlap a little, as triangles touching the plane
+    /// can be hit a rounding error behind it.
+    fn split(&self, node: &Node<N>, ray: Ray<N>, (min_d, max_d): (N, N)) -> Split<N> {
+        let src_z = (node.mat * (ray.src - node.origin)).z();
+        let dir_z = (node.mat * ray.dir1).z();
+
+        // signed distances from the plane at both ends of the interval
+        let min_z = src_z + dir_z * min_d;
+        let max_z = src_z + dir_z * max_d;
+
+        let is_near_neg = min_z < N::ZERO || (min_z == N::ZERO && dir_z < N::ZERO);
+
+        let (near, far, crosses) = if is_near_neg {
+            (node.neg, node.pos, max_z > N::ZERO)
+        } else {
+            (node.pos, node.neg, max_z < N::ZERO)
+        };
+
+        if !crosses {
+            return Split {
+                near: (near, (min_d, max_d)),
+                far: None,
+                plane_d: max_d,
+            };
+        }
+
+        // the sign of z changes along the interval, so `dir_z` is non-zero
+        let plane_d = -src_z / dir_z;
+
+        let near_max_d = if plane_d + N::EPS < max_d {
+            plane_d + N::EPS
+        } else {
+            max_d
+        };
+        let far_min_d = if plane_d - N::EPS > min_d {
+            plane_d - N::EPS
+        } else {
+            min_d
+        };
+
+        Split {
+            near: (near, (min_d, near_max_d)),
+            far: Some((far, (far_min_d, max_d))),
+            plane_d,
+        }
     }
+}
 
-    #[inline(always)]
-    fn choose_from_3(
-        isec1: Option<RayIntersection<'a, N>>,
-        isec2: Option<RayIntersection<'a, N>>,
-        isec3: Option<RayIntersection<'a, N>>,
+impl<'a, N: Num> BspTreeView<'a, N> {
+    /// Visits the half-space containing the start of the `[min_d; max_d]` interval first,
+    /// and the other one only if the ray crosses the plane before hitting anything.
+    /// Hits closer than `hit_min_d` are ignored.
+    fn cast_ray_between(
+        &self,
+        i_node: u32,
+        ray: Ray<N>,
+        (min_d, max_d): (N, N),
+        hit_min_d: N,
+        culling: Culling,
     ) -> Option<RayIntersection<'a, N>> {
-        Self::choose_from_2(isec1, Self::choose_from_2(isec2, isec3))
+        let node = self.node(i_node);
+
+        // own triangles straddle the plane, so they can be hit on either side
+        let own_isec = self.cast_through_own(node, ray, hit_min_d, max_d, culling);
+        let max_d = own_isec.as_ref().map_or(max_d, |isec| isec.d);
+
+        let split = self.split(node, ray, (min_d, max_d));
+
+        let (near, near_interval) = split.near;
+        let near_isec =
+            near.and_then(|n| self.cast_ray_between(n, ray, near_interval, hit_min_d, culling));
+
+        let Some((far, (far_min_d, far_max_d))) = split.far else {
+            return near_isec.or(own_isec);
+        };
+
+        let far_max_d = match &near_isec {
+            // anything on the far side is behind the plane
+            Some(isec) if isec.d < split.plane_d => return near_isec,
+
+            Some(isec) => isec.d,
+            None => far_max_d,
+        };
+
+        let far_isec = far.and_then(|n| {
+            self.cast_ray_between(n, ray, (far_min_d, far_max_d), hit_min_d, culling)
+        });
+
+        far_isec.or(near_isec).or(own_isec)
     }
 }
 
-impl<'a, N: Num> Castable<'a, N> for Node<'a, N> {
-    fn cast_ray(&'a self, ray: Ray<N>, max_d: N) -> Option<RayIntersection<'a, N>> {
-        let src_bs = self.mat * (ray.src - self.origin);
-        let dir_bs = self.mat * ray.dir1;
+impl<'a, N: Num> BspTreeView<'a, N> {
+    /// Same traversal order as `cast_ray_between`, but returns on the first hit.
+    fn is_occluded_between(
+        &self,
+        i_node: u32,
+        ray: Ray<N>,
+        interval: (N, N),
+        hit_min_d: N,
+    ) -> bool {
+        let node = self.node(i_node);
+
+        if self.own_tris(node).any(|tri| {
+            tri.cast_ray_between(ray, hit_min_d, interval.1, Culling::Back)
+                .is_some()
+        }) {
+            return true;
+        }
 
-        if src_bs.z() < N::ZERO && dir_bs.z() < N::ZERO {
-            return Self::choose_from_2(
-                self.cast_through_own(ray, max_d),
-                self.neg.as_ref().and_then(|n| n.cast_ray(ray, max_d)),
-            );
+        let split = self.split(node, ray, interval);
+
+        let (near, near_interval) = split.near;
+        let is_occluded = |child: Option<u32>, interval| {
+            child.is_some_and(|n| self.is_occluded_between(n, ray, interval, hit_min_d))
+        };
+
+        is_occluded(near, near_interval)
+            || split
+                .far
+                .is_some_and(|(far, far_interval)| is_occluded(far, far_interval))
+    }
+}
+
+impl<'a, N: Num> BspTreeView<'a, N> {
+    /// Visits every node the `[min_d; max_d]` interval passes through;
+    /// triangles are tested against the whole `[hit_min_d; hit_max_d]`.
+    fn cast_ray_all_between(
+        &self,
+        i_node: u32,
+        ray: Ray<N>,
+        interval: (N, N),
+        (hit_min_d, hit_max_d): (N, N),
+        isecs: &mut Vec<RayIntersection<'a, N>>,
+    ) {
+        let node = self.node(i_node);
+
+        isecs.extend(
+            self.own_tris(node)
+                .filter_map(|tri| tri.cast_ray_between(ray, hit_min_d, hit_max_d, Culling::None)),
+        );
+
+        let split = self.split(node, ray, interval);
+        let hit_interval = (hit_min_d, hit_max_d);
+
+        if let (Some(near), near_interval) = split.near {
+            self.cast_ray_all_between(near, ray, near_interval, hit_interval, isecs);
         }
 
-        if src_bs.z() > N::ZERO && dir_bs.z() > N::ZERO {
-            return Self::choose_from_2(
-                self.cast_through_own(ray, max_d),
-                self.pos.as_ref().and_then(|n| n.cast_ray(ray, max_d)),
-            );
+        if let Some((Some(far), far_interval)) = split.far {
+            self.cast_ray_all_between(far, ray, far_interval, hit_interval, isecs);
         }
+    }
+}
 
-        Self::choose_from_3(
-            self.cast_through_own(ray, max_d),
-            self.neg.as_ref().and_then(|n| n.cast_ray(ray, max_d)),
-            self.pos.as_ref().and_then(|n| n.cast_ray(ray, max_d)),
-        )
+impl<'a, N: SimdNum> BspTreeView<'a, N> {
+    /// Packet version of `cast_ray_between` for the `active` lanes, with per-lane intervals.
+    /// Children are visited in the order most lanes would visit them;
+    /// the other lanes then see their far side first, which only costs them the early exit.
+    fn cast_packet_between<const L: usize>(
+        &self,
+        i_node: u32,
+        packet: &RayPacket<L>,
+        active: Mask<i32, L>,
+        (min_d, max_d): (Simd<f32, L>, Simd<f32, L>),
+        hit_min_d: Simd<f32, L>,
+        hits: &mut PacketHits<'a, N, L>,
+    ) {
+        let node = self.node(i_node);
+
+        for tri in self.own_tris(node) {
+            tri.cast_packet_between(packet, active, hit_min_d, hits);
+        }
+
+        let max_d = max_d.simd_min(hits.d);
+
+        let normal = Vector3Simd::splat(node.mat.2);
+        let src_z = Vector3Simd::dot(packet.src - Vector3Simd::splat(node.origin), normal);
+        let dir_z = Vector3Simd::dot(packet.dir1, normal);
+
+        let zero = Simd::splat(0.0);
+        let eps = Simd::splat(N::EPS.to_f32());
+
+        let min_z = src_z + dir_z * min_d;
+        let max_z = src_z + dir_z * max_d;
+
+        let is_near_neg = min_z.simd_lt(zero) | (min_z.simd_eq(zero) & dir_z.simd_lt(zero));
+        let crosses = is_near_neg.select(max_z.simd_gt(zero), max_z.simd_lt(zero));
+
+        // infinite or NaN in the lanes that do not cross the plane, and never used there
+        let plane_d = -src_z / dir_z;
+        let near_max_d = crosses.select((plane_d + eps).simd_min(max_d), max_d);
+        let far_min_d = crosses.select((plane_d - eps).simd_max(min_d), min_d);
+
+        let n_neg = (active & is_near_neg).to_bitmask().count_ones();
+        let n_pos = (active & !is_near_neg).to_bitmask().count_ones();
+
+        let (first, second, is_first_near) = if n_neg >= n_pos {
+            (node.neg, node.pos, is_near_neg)
+        } else {
+            (node.pos, node.neg, !is_near_neg)
+        };
+
+        if let Some(first) = first {
+            let lanes = active & (is_first_near | crosses);
+
+            if lanes.any() {
+                let interval = (
+                    is_first_near.select(min_d, far_min_d),
+                    is_first_near.select(near_max_d, max_d),
+                );
+                self.cast_packet_between(first, packet, lanes, interval, hit_min_d, hits);
+            }
+        }
+
+        if let Some(second) = second {
+            // lanes that hit something before the plane are done
+            let is_far_needed = crosses & hits.d.simd_ge(plane_d);
+            let lanes = active & (!is_first_near | is_far_needed);
+
+            if lanes.any() {
+                let interval = (
+                    is_first_near.select(far_min_d, min_d),
+                    is_first_near.select(max_d, near_max_d),
+                );
+                self.cast_packet_between(second, packet, lanes, interval, hit_min_d, hits);
+            }
+        }
     }
 }
 
-impl<'a, N: Num> Castable<'a, N> for BspTree<'a, N> {
-    fn cast_ray(&'a self, ray: Ray<N>, max_d: N) -> Option<RayIntersection<'a, N>> {
-        self.root.as_ref().and_then(|n| n.cast_ray(ray, max_d))
+impl<'a, N: Num> Castable<'a, N> for BspTreeView<'a, N> {
+    fn cast_ray_culled(
+        &'a self,
+        ray: Ray<N>,
+        min_d: N,
+        max_d: N,
+        culling: Culling,
+    ) -> Option<RayIntersection<'a, N>> {
+        self.root()
+            .and_then(|n| self.cast_ray_between(n, ray, (min_d, max_d), min_d, culling))
+    }
+
+    fn is_occluded(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> bool {
+        self.root()
+            .is_some_and(|n| self.is_occluded_between(n, ray, (min_d, max_d), min_d))
+    }
+
+    fn cast_packet<const L: usize>(
+        &'a self,
+        rays: &[Ray<N>; L],
+        min_d: N,
+        max_d: N,
+    ) -> [Option<RayIntersection<'a, N>>; L]
+    where
+        N: SimdNum,
+    {
+        let packet = RayPacket::from_rays(rays);
+        let mut hits = PacketHits::new(max_d);
+
+        if let Some(root) = self.root() {
+            let min_d = Simd::splat(min_d.to_f32());
+            let max_d = Simd::splat(max_d.to_f32());
+
+            let interval = (min_d, max_d);
+            self.cast_packet_between(root, &packet, Mask::splat(true), interval, min_d, &mut hits);
+        }
+
+        hits.into_intersections(&packet)
+    }
+
+    fn cast_ray_all(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> Vec<RayIntersection<'a, N>> {
+        let mut isecs = Vec::new();
+
+        if let Some(root) = self.root() {
+            self.cast_ray_all_between(root, ray, (min_d, max_d), (min_d, max_d), &mut isecs);
+        }
+
+        sort_intersections(&mut isecs);
+        isecs
     }
 }
@@ -0,0 +1,44 @@
+use crate::math::*;
+
+use super::*;
+
+/// Identifies what a saved acceleration structure was built from,
+/// so that a file built for other triangles or with other options is not reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BuildKey {
+    /// hash of the builder name and options
+    pub builder: u64,
+
+    /// hash of the triangle vertices, in order
+    pub triangles: u64,
+}
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// FNV-1a, which, unlike `DefaultHasher`, is the same in every build.
+fn fnv_extend(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |hash, &b| (hash ^ b as u64).wrapping_mul(FNV_PRIME))
+}
+
+impl BuildKey {
+    /// `builder` should name the builder and every option that changes the result.
+    pub fn new<N: SimdNum>(builder: &str, triangles: &[Triangle<N>]) -> Self {
+        let mut triangles_hash = fnv_extend(FNV_OFFSET, &(triangles.len() as u64).to_le_bytes());
+
+        for tri in triangles {
+            for v in [tri.meta.a, tri.meta.b, tri.meta.c] {
+                for x in [v.x(), v.y(), v.z()] {
+                    triangles_hash = fnv_extend(triangles_hash, &x.to_f32().to_le_bytes());
+                }
+            }
+        }
+
+        Self {
+            builder: fnv_extend(FNV_OFFSET, builder.as_bytes()),
+            triangles: triangles_hash,
+        }
+    }
+}
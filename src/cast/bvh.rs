@@ -0,0 +1,599 @@
+use crate::formats::FormatError;
+use crate::math::*;
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use std::io::{Read, Write};
+
+use super::*;
+
+/// Bounding volume hierarchy over triangle bounding boxes, for a triangle array it does not own.
+/// Leaves refer to triangles by index, so the hierarchy can be stored next to the triangles
+/// or saved to a file; rays are cast through `with_triangles`.
+pub struct Bvh<N: Num> {
+    nodes: BvhNodes<N>,
+
+    /// length of the triangle array the hierarchy was built for
+    n_triangles: usize,
+}
+
+/// A hierarchy paired with the triangles it was built for; see `Bvh::with_triangles`.
+#[derive(Clone, Copy)]
+pub struct BvhView<'a, N: Num> {
+    bvh: &'a Bvh<N>,
+    triangles: &'a [Triangle<N>],
+}
+
+/// Hierarchy over bounding boxes of arbitrary items, referred to by their indices.
+///
+/// Nodes are stored depth-first in a flat array: the first child of an inner node
+/// immediately follows it, and `offset` points to the second one.
+pub(crate) struct BvhNodes<N: Num> {
+    nodes: Vec<Node<N>>,
+
+    /// item indices, grouped by leaves
+    items: Vec<usize>,
+}
+
+struct Node<N: Num> {
+    bounds: Aabb<N>,
+
+    /// position of the first item in `items` for leaves, index of the second child for inner nodes
+    offset: usize,
+
+    /// zero for inner nodes
+    n_items: usize,
+
+    /// axis the children were split along; the first child has smaller coordinates
+    axis: usize,
+}
+
+/// Relative cost of a ray-box test against a ray-triangle test.
+const TRAVERSAL_COST: usize = 1;
+
+/// Leaves with more triangles are always split, even if SAH suggests otherwise.
+const MAX_LEAF_SIZE: usize = 8;
+
+/// Deeper nodes are split at the median, so that the depth stays logarithmic.
+const MAX_SAH_DEPTH: usize = 32;
+
+/// Enough for `MAX_SAH_DEPTH` plus median splits of any slice that fits into memory.
+const STACK_SIZE: usize = MAX_SAH_DEPTH + 64;
+
+struct Prim<N: Num> {
+    index: usize,
+    bounds: Aabb<N>,
+    center: Vector3<N>,
+}
+
+struct Split {
+    axis: usize,
+
+    /// number of primitives going into the first child
+    index: usize,
+}
+
+fn sort_along<N: Num>(prims: &mut [Prim<N>], axis: usize) {
+    prims.sort_by(|a, b| {
+        a.center
+            .axis(axis)
+            .partial_cmp(&b.center.axis(axis))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+}
+
+/// Sweeps every axis, evaluating the surface area heuristic for every split position.
+fn find_sah_split<N: Num>(prims: &mut [Prim<N>], bounds: &Aabb<N>) -> Option<(Split, N)> {
+    let mut best: Option<(Split, N)> = None;
+    let mut right_areas = vec![N::ZERO; prims.len()];
+
+    for axis in 0..3 {
+        sort_along(prims, axis);
+
+        let mut right_bounds = prims[prims.len() - 1].bounds;
+        for i in (1..prims.len()).rev() {
+            right_bounds = Aabb::union(right_bounds, prims[i].bounds);
+            right_areas[i] = right_bounds.surface_area();
+        }
+
+        let mut left_bounds = prims[0].bounds;
+        for i in 1..prims.len() {
+            let left_cost = left_bounds.surface_area() * N::from_usize(i);
+            let right_cost = right_areas[i] * N::from_usize(prims.len() - i);
+            let cost = left_cost + right_cost;
+
+            if best.as_ref().is_none_or(|(_, best_cost)| cost < *best_cost) {
+                best = Some((Split { axis, index: i }, cost));
+            }
+
+            left_bounds = Aabb::union(left_bounds, prims[i].bounds);
+        }
+    }
+
+    let (split, cost) = best?;
+
+    // relative to the ray hitting the parent box at all
+    let area = bounds.surface_area();
+    let cost = if area > N::ZERO {
+        N::from_usize(TRAVERSAL_COST) + cost / area
+    } else {
+        N::from_usize(TRAVERSAL_COST + prims.len())
+    };
+
+    sort_along(prims, split.axis);
+
+    Some((split, cost))
+}
+
+impl<N: Num> BvhNodes<N> {
+    pub fn build(bounds: &[Aabb<N>]) -> Self {
+        let mut prims: Vec<Prim<N>> = bounds
+            .iter()
+            .enumerate()
+            .map(|(index, &bounds)| Prim {
+                index,
+                bounds,
+                center: bounds.center(),
+            })
+            .collect();
+
+        let mut nodes = Self {
+            nodes: Vec::with_capacity(2 * prims.len()),
+            items: Vec::with_capacity(prims.len()),
+        };
+
+        if !prims.is_empty() {
+            nodes.build_node(&mut prims, 0);
+        }
+
+        nodes
+    }
+
+    fn build_node(&mut self, prims: &mut [Prim<N>], depth: usize) {
+        let bounds = prims[1..]
+            .iter()
+            .fold(prims[0].bounds, |b, prim| Aabb::union(b, prim.bounds));
+
+        let i_node = self.nodes.len();
+        self.nodes.push(Node {
+            bounds,
+            offset: self.items.len(),
+            n_items: prims.len(),
+            axis: 0,
+        });
+
+        if prims.len() == 1 {
+            self.items.push(prims[0].index);
+            return;
+        }
+
+        let split = if depth < MAX_SAH_DEPTH {
+            let (split, cost) = find_sah_split(prims, &bounds).unwrap();
+
+            if cost >= N::from_usize(prims.len()) && prims.len() <= MAX_LEAF_SIZE {
+                self.items.extend(prims.iter().map(|prim| prim.index));
+                return;
+            }
+
+            split
+        } else {
+            let axis = bounds.longest_axis();
+            sort_along(prims, axis);
+
+            Split {
+                axis,
+                index: prims.len() / 2,
+            }
+        };
+
+        let (neg, pos) = prims.split_at_mut(split.index);
+
+        self.build_node(neg, depth + 1);
+        let i_pos = self.nodes.len();
+        self.build_node(pos, depth + 1);
+
+        self.nodes[i_node] = Node {
+            bounds,
+            offset: i_pos,
+            n_items: 0,
+            axis: split.axis,
+        };
+    }
+
+    pub fn bounds(&self) -> Option<Aabb<N>> {
+        self.nodes.first().map(|node| node.bounds)
+    }
+
+    /// Item indices; callbacks are given positions in this slice.
+    pub fn items(&self) -> &[usize] {
+        &self.items
+    }
+
+    /// Visits leaves front to back, skipping ones behind the closest hit so far.
+    /// `cast_item(i, cur_d)` casts the item at position `i` with `cur_d` as the maximum distance.
+    #[inline(always)]
+    pub fn cast_closest<H>(
+        &self,
+        ray: Ray<N>,
+        min_d: N,
+        max_d: N,
+        mut cast_item: impl FnMut(usize, N) -> Option<(N, H)>,
+    ) -> Option<H> {
+        let slab_ray = SlabRay::from(ray);
+
+        let mut cur_d = max_d;
+        let mut cur_hit = None;
+
+        let mut stack = [(0, N::ZERO); STACK_SIZE];
+        let mut stack_len = 0;
+
+        if let Some(root) = self.nodes.first() {
+            if let Some((near_d, _)) = root.bounds.cast_slab_ray(&slab_ray, min_d, cur_d) {
+                stack[0] = (0, near_d);
+                stack_len = 1;
+            }
+        }
+
+        while stack_len > 0 {
+            stack_len -= 1;
+            let (i_node, near_d) = stack[stack_len];
+
+            // a closer hit was found after the node had been pushed
+            if near_d >= cur_d {
+                continue;
+            }
+
+            let node = &self.nodes[i_node];
+
+            if node.n_items > 0 {
+                for i in node.offset..node.offset + node.n_items {
+                    if let Some((d, hit)) = cast_item(i, cur_d) {
+                        cur_d = d;
+                        cur_hit = Some(hit);
+                    }
+                }
+
+                continue;
+            }
+
+            let (mut near, mut far) = (i_node + 1, node.offset);
+            if ray.dir1.axis(node.axis) < N::ZERO {
+                std::mem::swap(&mut near, &mut far);
+            }
+
+            let near_hit = self.nodes[near]
+                .bounds
+                .cast_slab_ray(&slab_ray, min_d, cur_d);
+            let far_hit = self.nodes[far]
+                .bounds
+                .cast_slab_ray(&slab_ray, min_d, cur_d);
+
+            // pushed last to be popped first
+            for (i_child, hit) in [(far, far_hit), (near, near_hit)] {
+                if let Some((near_d, _)) = hit {
+                    stack[stack_len] = (i_child, near_d);
+                    stack_len += 1;
+                }
+            }
+        }
+
+        cur_hit
+    }
+
+    /// Calls `visit(i)` for the positions of items in every leaf the ray crosses,
+    /// until it returns `true`.
+    #[inline(always)]
+    pub fn any_crossed(
+        &self,
+        ray: Ray<N>,
+        min_d: N,
+        max_d: N,
+        mut visit: impl FnMut(usize) -> bool,
+    ) -> bool {
+        let slab_ray = SlabRay::from(ray);
+
+        // the root is at index 0
+        let mut stack = [0; STACK_SIZE];
+        let mut stack_len = if self.nodes.is_empty() { 0 } else { 1 };
+
+        while stack_len > 0 {
+            stack_len -= 1;
+            let i_node = stack[stack_len];
+            let node = &self.nodes[i_node];
+
+            if node.bounds.cast_slab_ray(&slab_ray, min_d, max_d).is_none() {
+                continue;
+            }
+
+            if node.n_items > 0 {
+                if (node.offset..node.offset + node.n_items).any(&mut visit) {
+                    return true;
+                }
+
+                continue;
+            }
+
+            stack[stack_len] = i_node + 1;
+            stack[stack_len + 1] = node.offset;
+            stack_len += 2;
+        }
+
+        false
+    }
+}
+
+impl<N: Num> Bvh<N> {
+    pub fn build(triangles: &[Triangle<N>]) -> Self {
+        let bounds: Vec<_> = triangles.iter().map(Aabb::from_triangle).collect();
+
+        Self {
+            nodes: BvhNodes::build(&bounds),
+            n_triangles: triangles.len(),
+        }
+    }
+
+    pub fn bounds(&self) -> Option<Aabb<N>> {
+        self.nodes.bounds()
+    }
+
+    /// Length of the triangle array the hierarchy was built for.
+    pub fn n_triangles(&self) -> usize {
+        self.n_triangles
+    }
+
+    /// Pairs the hierarchy with the triangles it was built for, to cast rays through them.
+    pub fn with_triangles<'a>(&'a self, triangles: &'a [Triangle<N>]) -> BvhView<'a, N> {
+        assert_eq!(
+            triangles.len(),
+            self.n_triangles,
+            "the hierarchy was built for a different triangle array"
+        );
+
+        BvhView {
+            bvh: self,
+            triangles,
+        }
+    }
+}
+
+impl<'a, N: Num> BvhView<'a, N> {
+    pub fn bounds(&self) -> Option<Aabb<N>> {
+        self.bvh.bounds()
+    }
+
+    #[inline(always)]
+    fn tri(self, i: usize) -> &'a Triangle<N> {
+        &self.triangles[self.bvh.nodes.items[i]]
+    }
+}
+
+// taking the view by value, so that hits can outlive it, as when `SceneBvh` makes one per ray
+impl<'a, N: Num> BvhView<'a, N> {
+    pub(crate) fn closest_hit(
+        self,
+        ray: Ray<N>,
+        min_d: N,
+        max_d: N,
+        culling: Culling,
+    ) -> Option<RayIntersection<'a, N>> {
+        self.bvh.nodes.cast_closest(ray, min_d, max_d, |i, cur_d| {
+            self.tri(i)
+                .cast_ray_between(ray, min_d, cur_d, culling)
+                .map(|isec| (isec.d, isec))
+        })
+    }
+
+    pub(crate) fn any_hit(self, ray: Ray<N>, min_d: N, max_d: N) -> bool {
+        self.bvh.nodes.any_crossed(ray, min_d, max_d, |i| {
+            self.tri(i)
+                .cast_ray_between(ray, min_d, max_d, Culling::Back)
+                .is_some()
+        })
+    }
+
+    pub(crate) fn all_hits(self, ray: Ray<N>, min_d: N, max_d: N) -> Vec<RayIntersection<'a, N>> {
+        let mut isecs = Vec::new();
+
+        self.bvh.nodes.any_crossed(ray, min_d, max_d, |i| {
+            isecs.extend(
+                self.tri(i)
+                    .cast_ray_between(ray, min_d, max_d, Culling::None),
+            );
+            false
+        });
+
+        sort_intersections(&mut isecs);
+        isecs
+    }
+}
+
+impl<'a, N: Num> Castable<'a, N> for BvhView<'a, N> {
+    fn cast_ray_culled(
+        &'a self,
+        ray: Ray<N>,
+        min_d: N,
+        max_d: N,
+        culling: Culling,
+    ) -> Option<RayIntersection<'a, N>> {
+        self.closest_hit(ray, min_d, max_d, culling)
+    }
+
+    fn is_occluded(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> bool {
+        self.any_hit(ray, min_d, max_d)
+    }
+
+    fn cast_ray_all(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> Vec<RayIntersection<'a, N>> {
+        self.all_hits(ray, min_d, max_d)
+    }
+}
+
+/// File signature, followed by the format version and the `BuildKey`.
+const FILE_MAGIC: &[u8; 8] = b"deer2bvh";
+const FILE_VERSION: u32 = 1;
+
+impl<N: SimdNum> Bvh<N> {
+    /// Little-endian binary dump, with all coordinates as `f32`;
+    /// `key` is stored to be checked by whoever reads the file.
+    pub fn write_to<W: Write>(&self, writer: &mut W, key: &BuildKey) -> Result<(), FormatError> {
+        // offsets and counts within nodes are bounded by these
+        if [
+            self.n_triangles,
+            self.nodes.nodes.len(),
+            self.nodes.items.len(),
+        ]
+        .iter()
+        .any(|&count| count > u32::MAX as usize)
+        {
+            return Err(FormatError::OversizeDimension(format!(
+                "hierarchy of {} nodes for {} triangles",
+                self.nodes.nodes.len(),
+                self.n_triangles
+            )));
+        }
+
+        let to_u32 = |x: usize| x as u32;
+
+        writer.write_all(FILE_MAGIC)?;
+        writer.write_u32::<LE>(FILE_VERSION)?;
+        writer.write_u64::<LE>(key.builder)?;
+        writer.write_u64::<LE>(key.triangles)?;
+
+        writer.write_u32::<LE>(to_u32(self.n_triangles))?;
+        writer.write_u32::<LE>(to_u32(self.nodes.nodes.len()))?;
+        writer.write_u32::<LE>(to_u32(self.nodes.items.len()))?;
+
+        for node in &self.nodes.nodes {
+            writer.write_u32::<LE>(to_u32(node.offset))?;
+            writer.write_u32::<LE>(to_u32(node.n_items))?;
+            writer.write_u32::<LE>(to_u32(node.axis))?;
+
+            for v in [node.bounds.min_coords, node.bounds.max_coords] {
+                writer.write_f32::<LE>(v.x().to_f32())?;
+                writer.write_f32::<LE>(v.y().to_f32())?;
+                writer.write_f32::<LE>(v.z().to_f32())?;
+            }
+        }
+
+        for &i in &self.nodes.items {
+            writer.write_u32::<LE>(to_u32(i))?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads what `write_to` wrote, with its key, checking the node layout and every index,
+    /// so that casting rays through the hierarchy cannot panic.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<(Self, BuildKey), FormatError> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != FILE_MAGIC {
+            return Err(FormatError::InvalidHeader("not a BVH file".to_string()));
+        }
+
+        let version = reader.read_u32::<LE>()?;
+        if version != FILE_VERSION {
+            return Err(FormatError::Unsupported(format!(
+                "BVH file version {version}"
+            )));
+        }
+
+        let key = BuildKey {
+            builder: reader.read_u64::<LE>()?,
+            triangles: reader.read_u64::<LE>()?,
+        };
+
+        let n_triangles = reader.read_u32::<LE>()? as usize;
+        let n_nodes = reader.read_u32::<LE>()? as usize;
+        let n_items = reader.read_u32::<LE>()? as usize;
+
+        let read_vector = |reader: &mut R| -> Result<Vector3<N>, FormatError> {
+            Ok(Vector3::new(
+                N::from_f32(reader.read_f32::<LE>()?),
+                N::from_f32(reader.read_f32::<LE>()?),
+                N::from_f32(reader.read_f32::<LE>()?),
+            ))
+        };
+
+        // do not trust the counts with a huge allocation before any node is read
+        let mut nodes = Vec::with_capacity(n_nodes.min(1 << 20));
+
+        for i_node in 0..n_nodes {
+            let offset = reader.read_u32::<LE>()? as usize;
+            let node_items = reader.read_u32::<LE>()? as usize;
+            let axis = reader.read_u32::<LE>()? as usize;
+
+            let is_valid = if node_items > 0 {
+                offset + node_items <= n_items
+            } else {
+                axis < 3
+            };
+
+            if !is_valid {
+                return Err(FormatError::InvalidData(format!(
+                    "node {i_node} is out of range"
+                )));
+            }
+
+            let min_coords = read_vector(reader)?;
+            let max_coords = read_vector(reader)?;
+
+            nodes.push(Node {
+                bounds: Aabb {
+                    min_coords,
+                    max_coords,
+                },
+                offset,
+                n_items: node_items,
+                axis,
+            });
+        }
+
+        let mut items = Vec::with_capacity(n_items.min(1 << 20));
+
+        for _ in 0..n_items {
+            let i = reader.read_u32::<LE>()? as usize;
+
+            if i >= n_triangles {
+                return Err(FormatError::InvalidData(format!(
+                    "triangle index {i} of {n_triangles}"
+                )));
+            }
+
+            items.push(i);
+        }
+
+        let nodes = BvhNodes { nodes, items };
+
+        if !nodes.nodes.is_empty() && nodes.check_subtree(0, 0) != Some(nodes.nodes.len()) {
+            return Err(FormatError::InvalidData(
+                "nodes are not a depth-first tree".to_string(),
+            ));
+        }
+
+        let bvh = Self { nodes, n_triangles };
+        Ok((bvh, key))
+    }
+}
+
+impl<N: Num> BvhNodes<N> {
+    /// Checks that the subtree at `i_node` is laid out as `build_node` lays it out,
+    /// and is shallow enough for the traversal stacks; returns the index past its last node.
+    fn check_subtree(&self, i_node: usize, depth: usize) -> Option<usize> {
+        let node = self.nodes.get(i_node)?;
+
+        if node.n_items > 0 {
+            return Some(i_node + 1);
+        }
+
+        // at most one node per level waits on the stack, plus the two children just pushed
+        if depth + 2 >= STACK_SIZE {
+            return None;
+        }
+
+        let first_end = self.check_subtree(i_node + 1, depth + 1)?;
+        if node.offset != first_end {
+            return None;
+        }
+
+        self.check_subtree(node.offset, depth + 1)
+    }
+}
@@ -0,0 +1,170 @@
+use crate::math::*;
+
+use super::*;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection<N: Num> {
+    /// vertical field of view in radians
+    Perspective { fov_y: N },
+
+    /// height of the view in world units
+    Orthographic { height: N },
+}
+
+/// Generates rays through a screen rectangle in front of the camera.
+#[derive(Debug, Clone, Copy)]
+pub struct Camera<N: Num> {
+    pub position: Vector3<N>,
+
+    /// unit-length view direction
+    pub forward1: Vector3<N>,
+
+    /// unit-length screen axes; `right1` points towards increasing pixel x,
+    /// `up1` towards decreasing pixel y
+    pub right1: Vector3<N>,
+    pub up1: Vector3<N>,
+
+    pub projection: Projection<N>,
+
+    /// screen width divided by screen height
+    pub aspect_ratio: N,
+}
+
+impl<N: Num> Camera<N> {
+    /// `up` is orthogonalized; if it is zero or collinear with the view direction,
+    /// +Y is used instead, or +Z when looking along Y.
+    pub fn look_at(
+        position: Vector3<N>,
+        target: Vector3<N>,
+        up: Vector3<N>,
+        projection: Projection<N>,
+        aspect_ratio: N,
+    ) -> Self {
+        let forward1 = (target - position).norm();
+
+        let mut right = Vector3::cross(forward1, up);
+        if right.abs() <= N::EPS * up.abs() {
+            let up = if forward1.y().abs() <= forward1.z().abs() {
+                Vector3::EY
+            } else {
+                Vector3::EZ
+            };
+
+            right = Vector3::cross(forward1, up);
+        }
+
+        let right1 = right.norm();
+        let up1 = Vector3::cross(right1, forward1);
+
+        Self {
+            position,
+            forward1,
+            right1,
+            up1,
+            projection,
+            aspect_ratio,
+        }
+    }
+
+    /// `orientation` rotates the default view, which looks towards -Z with +Y up.
+    pub fn oriented(
+        position: Vector3<N>,
+        orientation: Quaternion<N>,
+        projection: Projection<N>,
+        aspect_ratio: N,
+    ) -> Self {
+        Self {
+            position,
+            forward1: orientation.rotate(-Vector3::EZ),
+            right1: orientation.rotate(Vector3::EX),
+            up1: orientation.rotate(Vector3::EY),
+            projection,
+            aspect_ratio,
+        }
+    }
+
+    /// Rotation of the default view into this one, for interpolating between cameras.
+    pub fn orientation(&self) -> Quaternion<N> {
+        Quaternion::from_matrix3(&Matrix3::from_cols(self.right1, self.up1, -self.forward1))
+    }
+
+    pub fn perspective(
+        position: Vector3<N>,
+        target: Vector3<N>,
+        up: Vector3<N>,
+        fov_y: N,
+        aspect_ratio: N,
+    ) -> Self {
+        let projection = Projection::Perspective { fov_y };
+        Self::look_at(position, target, up, projection, aspect_ratio)
+    }
+
+    pub fn orthographic(
+        position: Vector3<N>,
+        target: Vector3<N>,
+        up: Vector3<N>,
+        height: N,
+        aspect_ratio: N,
+    ) -> Self {
+        let projection = Projection::Orthographic { height };
+        Self::look_at(position, target, up, projection, aspect_ratio)
+    }
+
+    /// `u` and `v` are screen coordinates from 0 to 1,
+    /// starting at the top left corner, like pixel coordinates.
+    pub fn ray(&self, u: N, v: N) -> Ray<N> {
+        let two = N::ONE + N::ONE;
+
+        // from -1 to 1, with y pointing up
+        let screen_x = u * two - N::ONE;
+        let screen_y = N::ONE - v * two;
+
+        match self.projection {
+            Projection::Perspective { fov_y } => {
+                let half_height = (fov_y / two).sin() / (fov_y / two).cos();
+                let half_width = half_height * self.aspect_ratio;
+
+                let dir = self.forward1
+                    + self.right1 * (screen_x * half_width)
+                    + self.up1 * (screen_y * half_height);
+
+                Ray {
+                    src: self.position,
+                    dir1: dir.norm(),
+                }
+            }
+
+            Projection::Orthographic { height } => {
+                let half_height = height / two;
+                let half_width = half_height * self.aspect_ratio;
+
+                let src = self.position
+                    + self.right1 * (screen_x * half_width)
+                    + self.up1 * (screen_y * half_height);
+
+                Ray {
+                    src,
+                    dir1: self.forward1,
+                }
+            }
+        }
+    }
+
+    /// Ray through a point of an image of `width` x `height` pixels;
+    /// pixel centers are at half-integer coordinates.
+    pub fn image_ray(&self, x: N, y: N, width: usize, height: usize) -> Ray<N> {
+        self.ray(x / N::from_usize(width), y / N::from_usize(height))
+    }
+
+    /// Ray through the center of a pixel.
+    pub fn pixel_ray(&self, pixel_x: usize, pixel_y: usize, width: usize, height: usize) -> Ray<N> {
+        let half = N::ONE / (N::ONE + N::ONE);
+
+        self.image_ray(
+            N::from_usize(pixel_x) + half,
+            N::from_usize(pixel_y) + half,
+            width,
+            height,
+        )
+    }
+}
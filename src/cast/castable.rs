@@ -3,5 +3,38 @@ use crate::math::*;
 use super::*;
 
 pub trait Castable<'a, N: Num> {
-    fn cast_ray(&'a self, ray: Ray<N>, max_d: N) -> Option<RayIntersection<'a, N>>;
+    /// Closest hit of a front side within `[min_d; max_d]`;
+    /// a positive `min_d` keeps rays leaving a surface from hitting it again.
+    fn cast_ray(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> Option<RayIntersection<'a, N>> {
+        self.cast_ray_culled(ray, min_d, max_d, Culling::Back)
+    }
+
+    fn cast_ray_culled(
+        &'a self,
+        ray: Ray<N>,
+        min_d: N,
+        max_d: N,
+        culling: Culling,
+    ) -> Option<RayIntersection<'a, N>>;
+
+    /// Whether any front side is hit within `[min_d; max_d]`;
+    /// can stop at the first hit found, which is all that shadow rays need.
+    fn is_occluded(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> bool;
+
+    /// `cast_ray` for several rays at once, one per SIMD lane;
+    /// faster than casting them one by one if they are coherent, like primary rays of neighbouring pixels.
+    fn cast_packet<const L: usize>(
+        &'a self,
+        rays: &[Ray<N>; L],
+        min_d: N,
+        max_d: N,
+    ) -> [Option<RayIntersection<'a, N>>; L]
+    where
+        N: SimdNum,
+    {
+        rays.map(|ray| self.cast_ray(ray, min_d, max_d))
+    }
+
+    /// Every triangle crossed within `[min_d; max_d]`, from either side, sorted by distance.
+    fn cast_ray_all(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> Vec<RayIntersection<'a, N>>;
 }
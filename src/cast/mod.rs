@@ -1,11 +1,23 @@
+mod aabb;
 mod bsp_tree;
+mod build_key;
+mod bvh;
+mod camera;
 mod castable;
 mod ray;
+mod ray_packet;
+mod scene;
 mod triangle;
 mod triangle_list;
 
+pub use aabb::*;
 pub use bsp_tree::*;
+pub use build_key::*;
+pub use bvh::*;
+pub use camera::*;
 pub use castable::*;
 pub use ray::*;
+pub use ray_packet::*;
+pub use scene::*;
 pub use triangle::*;
 pub use triangle_list::*;
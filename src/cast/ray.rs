@@ -1,5 +1,7 @@
 use crate::math::*;
 
+use std::cmp::Ordering;
+
 use super::*;
 
 #[derive(Debug, Clone, Copy)]
@@ -11,6 +13,15 @@ pub struct Ray<N: Num> {
     pub dir1: Vector3<N>,
 }
 
+/// Which sides of triangles rays pass through without hitting.
+/// The front side is the one the triangle normal points to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Culling {
+    Back,
+    Front,
+    None,
+}
+
 #[derive(Debug)]
 pub struct RayIntersection<'a, N: Num> {
     /// triangle
@@ -21,9 +32,23 @@ pub struct RayIntersection<'a, N: Num> {
 
     /// intersection point in (AB, AC, N1) space
     pub p_abc: Vector3<N>,
+
+    /// whether the ray came from the side the triangle normal points to
+    pub is_front_face: bool,
+
+    /// for triangles of scene objects, the transform from world into mesh coordinates;
+    /// `None` for triangles already in world coordinates
+    pub world_to_mesh: Option<&'a Affine3<N>>,
+
+    /// for triangles of scene objects with their own material, overrides the one of the triangle
+    pub object_material: Option<usize>,
 }
 
 impl<'a, N: Num> RayIntersection<'a, N> {
+    pub fn material(&self) -> usize {
+        self.object_material.unwrap_or(self.tri.meta.material)
+    }
+
     pub fn interpolate_meta(&self) -> InterpolatedMeta<N> {
         let w = Vector3::new(
             N::ONE - self.p_abc.x() - self.p_abc.y(),
@@ -31,10 +56,32 @@ impl<'a, N: Num> RayIntersection<'a, N> {
             self.p_abc.y(),
         );
 
+        let mut n_p = self.tri.meta.abc_nc * w;
+
+        // normals transform with the inverse transpose
+        if let Some(world_to_mesh) = self.world_to_mesh {
+            n_p = world_to_mesh.mat.tr() * n_p;
+        }
+
         InterpolatedMeta {
             w,
-            n1_p: (self.tri.meta.abc_nc * w).norm(),
+            n1_p: n_p.norm(),
             p_uv: self.tri.meta.abc_uv * w,
         }
     }
 }
+
+/// Sorts by distance and drops repeated hits of the same triangle,
+/// which acceleration structures referencing a triangle more than once can produce.
+/// Hits of a mesh shared by several scene objects are told apart by their transforms.
+pub(crate) fn sort_intersections<N: Num>(isecs: &mut Vec<RayIntersection<'_, N>>) {
+    let key = |isec: &RayIntersection<N>| {
+        let world_to_mesh = isec
+            .world_to_mesh
+            .map_or(std::ptr::null(), |t| t as *const _);
+        (isec.d, isec.tri as *const Triangle<N>, world_to_mesh)
+    };
+
+    isecs.sort_by(|a, b| key(a).partial_cmp(&key(b)).unwrap_or(Ordering::Equal));
+    isecs.dedup_by(|a, b| key(a) == key(b));
+}
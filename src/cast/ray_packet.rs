@@ -0,0 +1,82 @@
+use crate::math::*;
+
+use std::simd::prelude::*;
+
+use super::*;
+
+/// `L` rays cast together, one per SIMD lane.
+#[derive(Debug, Clone, Copy)]
+pub struct RayPacket<const L: usize> {
+    pub src: Vector3Simd<L>,
+    pub dir1: Vector3Simd<L>,
+}
+
+impl<const L: usize> RayPacket<L> {
+    pub fn from_rays<N: SimdNum>(rays: &[Ray<N>; L]) -> Self {
+        Self {
+            src: Vector3Simd::from_lanes(rays.map(|ray| ray.src)),
+            dir1: Vector3Simd::from_lanes(rays.map(|ray| ray.dir1)),
+        }
+    }
+}
+
+/// Closest hits found so far, for every lane of a packet.
+pub(crate) struct PacketHits<'a, N: Num, const L: usize> {
+    /// distance to the hit, or the maximum distance for lanes without one
+    pub d: Simd<f32, L>,
+
+    pub tris: [Option<&'a Triangle<N>>; L],
+
+    /// intersection points in (AB, AC, N1) spaces of the triangles hit
+    pub p_abc: Vector3Simd<L>,
+}
+
+impl<'a, N: SimdNum, const L: usize> PacketHits<'a, N, L> {
+    pub fn new(max_d: N) -> Self {
+        Self {
+            d: Simd::splat(max_d.to_f32()),
+            tris: [None; L],
+            p_abc: Vector3Simd::splat(Vector3::<N>::ZERO),
+        }
+    }
+
+    /// Replaces the hits in the `hit` lanes with ones of `tri`.
+    #[inline(always)]
+    pub fn update(
+        &mut self,
+        tri: &'a Triangle<N>,
+        hit: Mask<i32, L>,
+        d: Simd<f32, L>,
+        p_abc: Vector3Simd<L>,
+    ) {
+        self.d = hit.select(d, self.d);
+        self.p_abc = Vector3Simd::select(hit, p_abc, self.p_abc);
+
+        for (i, cur_tri) in self.tris.iter_mut().enumerate() {
+            if hit.test(i) {
+                *cur_tri = Some(tri);
+            }
+        }
+    }
+
+    /// Packets are only cast with `Culling::Back`, so every hit is on the front face.
+    pub fn into_intersections(self, packet: &RayPacket<L>) -> [Option<RayIntersection<'a, N>>; L] {
+        std::array::from_fn(|i| {
+            self.tris[i].map(|tri| {
+                debug_assert!(
+                    (tri.m_abc * (packet.src.lane::<N>(i) - tri.a)).z() > N::ZERO,
+                    "packet hit on a back face"
+                );
+
+                RayIntersection {
+                    tri,
+                    d: N::from_f32(self.d[i]),
+                    p_abc: self.p_abc.lane(i),
+                    is_front_face: true,
+                    world_to_mesh: None,
+                    object_material: None,
+                }
+            })
+        })
+    }
+}
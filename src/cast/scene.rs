@@ -0,0 +1,209 @@
+use crate::math::*;
+
+use super::*;
+
+/// A mesh placed into a scene; several objects can share one mesh.
+#[derive(Debug, Clone, Copy)]
+pub struct SceneObject<N: Num> {
+    /// index into `Scene::meshes`
+    pub mesh: usize,
+
+    /// from mesh into world coordinates
+    pub transform: Affine3<N>,
+
+    /// overrides the materials of the mesh triangles
+    pub material: Option<usize>,
+}
+
+pub struct Scene<N: Num> {
+    pub meshes: Vec<TriangleList<N>>,
+    pub objects: Vec<SceneObject<N>>,
+}
+
+impl<N: Num> Scene<N> {
+    pub fn new() -> Self {
+        Self {
+            meshes: Vec::new(),
+            objects: Vec::new(),
+        }
+    }
+
+    /// Returns the index to reference the mesh by.
+    pub fn add_mesh(&mut self, mesh: TriangleList<N>) -> usize {
+        self.meshes.push(mesh);
+        self.meshes.len() - 1
+    }
+
+    /// Returns the object, to set its material.
+    pub fn add_object(&mut self, mesh: usize, transform: Affine3<N>) -> &mut SceneObject<N> {
+        assert!(mesh < self.meshes.len(), "no mesh {mesh}");
+
+        self.objects.push(SceneObject {
+            mesh,
+            transform,
+            material: None,
+        });
+        self.objects.last_mut().unwrap()
+    }
+}
+
+impl<N: Num> Default for Scene<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Two-level acceleration structure: a BVH over the triangles of every mesh,
+/// and one over the bounding boxes of the objects.
+///
+/// Rays are cast into mesh coordinates without renormalizing the direction,
+/// so that distances stay in world units.
+pub struct SceneBvh<'a, N: Num> {
+    scene: &'a Scene<N>,
+
+    /// one per mesh of `scene`
+    meshes: Vec<Bvh<N>>,
+    objects: BvhNodes<N>,
+
+    /// in the order of `objects.items`
+    instances: Vec<Instance<N>>,
+}
+
+#[derive(Clone, Copy)]
+struct Instance<N: Num> {
+    mesh: usize,
+    world_to_mesh: Affine3<N>,
+    material: Option<usize>,
+}
+
+impl<'a, N: Num> SceneBvh<'a, N> {
+    /// Objects with empty meshes are left out.
+    /// `None` if some object has a degenerate transform, which cannot be inverted.
+    pub fn build(scene: &'a Scene<N>) -> Option<Self> {
+        let meshes: Vec<_> = scene
+            .meshes
+            .iter()
+            .map(|mesh| Bvh::build(&mesh.triangles))
+            .collect();
+
+        let mut instances = Vec::new();
+        let mut bounds = Vec::new();
+
+        for object in scene.objects.iter() {
+            let Some(mesh_bounds) = meshes[object.mesh].bounds() else {
+                continue;
+            };
+
+            let world_to_mesh = object.transform.inv()?;
+
+            instances.push(Instance {
+                mesh: object.mesh,
+                world_to_mesh,
+                material: object.material,
+            });
+            bounds.push(transform_bounds(&object.transform, &mesh_bounds));
+        }
+
+        let objects = BvhNodes::build(&bounds);
+
+        let instances = objects.items().iter().map(|&i| instances[i]).collect();
+
+        Some(Self {
+            scene,
+            meshes,
+            objects,
+            instances,
+        })
+    }
+
+    pub fn bounds(&self) -> Option<Aabb<N>> {
+        self.objects.bounds()
+    }
+
+    fn mesh(&'a self, i_mesh: usize) -> BvhView<'a, N> {
+        self.meshes[i_mesh].with_triangles(&self.scene.meshes[i_mesh].triangles)
+    }
+}
+
+impl<N: Num> Instance<N> {
+    #[inline(always)]
+    fn mesh_ray(&self, ray: Ray<N>) -> Ray<N> {
+        Ray {
+            src: self.world_to_mesh.apply_point(ray.src),
+            dir1: self.world_to_mesh.apply_dir(ray.dir1),
+        }
+    }
+}
+
+/// Bounds of the transformed corners.
+fn transform_bounds<N: Num>(transform: &Affine3<N>, bounds: &Aabb<N>) -> Aabb<N> {
+    let corner = |i: usize| {
+        let pick = |axis: usize| {
+            if i & (1 << axis) == 0 {
+                bounds.min_coords.axis(axis)
+            } else {
+                bounds.max_coords.axis(axis)
+            }
+        };
+
+        transform.apply_point(Vector3::new(pick(0), pick(1), pick(2)))
+    };
+
+    (1..8).fold(Aabb::from_point(corner(0)), |b, i| b.extend(corner(i)))
+}
+
+impl<'a, N: Num> Castable<'a, N> for SceneBvh<'a, N> {
+    fn cast_ray_culled(
+        &'a self,
+        ray: Ray<N>,
+        min_d: N,
+        max_d: N,
+        culling: Culling,
+    ) -> Option<RayIntersection<'a, N>> {
+        self.objects.cast_closest(ray, min_d, max_d, |i, cur_d| {
+            let instance = &self.instances[i];
+
+            self.mesh(instance.mesh)
+                .closest_hit(instance.mesh_ray(ray), min_d, cur_d, culling)
+                .map(|isec| {
+                    let isec = RayIntersection {
+                        world_to_mesh: Some(&instance.world_to_mesh),
+                        object_material: instance.material,
+                        ..isec
+                    };
+                    (isec.d, isec)
+                })
+        })
+    }
+
+    fn is_occluded(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> bool {
+        self.objects.any_crossed(ray, min_d, max_d, |i| {
+            let instance = &self.instances[i];
+
+            self.mesh(instance.mesh)
+                .any_hit(instance.mesh_ray(ray), min_d, max_d)
+        })
+    }
+
+    fn cast_ray_all(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> Vec<RayIntersection<'a, N>> {
+        let mut isecs = Vec::new();
+
+        self.objects.any_crossed(ray, min_d, max_d, |i| {
+            let instance = &self.instances[i];
+
+            let mesh_isecs =
+                self.mesh(instance.mesh)
+                    .all_hits(instance.mesh_ray(ray), min_d, max_d);
+            isecs.extend(mesh_isecs.into_iter().map(|isec| RayIntersection {
+                world_to_mesh: Some(&instance.world_to_mesh),
+                object_material: instance.material,
+                ..isec
+            }));
+
+            false
+        });
+
+        sort_intersections(&mut isecs);
+        isecs
+    }
+}
@@ -1,5 +1,10 @@
 use crate::math::*;
 
+use std::intrinsics::likely;
+use std::simd::prelude::*;
+
+use super::*;
+
 #[derive(Debug)]
 pub struct Triangle<N: Num> {
     /// vertex A
@@ -24,6 +29,9 @@ pub struct TriangleMeta<N: Num> {
 
     /// cols are vertex UV
     pub abc_uv: Matrix3<N>,
+
+    /// index into the materials of the renderer
+    pub material: usize,
 }
 
 #[derive(Debug)]
@@ -37,3 +45,140 @@ pub struct InterpolatedMeta<N: Num> {
     /// UV coords of the intersection point
     pub p_uv: Vector3<N>,
 }
+
+impl<N: Num> Triangle<N> {
+    /// `None` for degenerate triangles, with vertices on one line.
+    pub fn from_meta(meta: TriangleMeta<N>) -> Option<Self> {
+        let ab = meta.b - meta.a;
+        let ac = meta.c - meta.a;
+
+        let n = Vector3::cross(ab, ac);
+        if n == Vector3::ZERO {
+            return None;
+        }
+
+        Some(Self {
+            a: meta.a,
+            m_abc: Matrix3::from_cols(ab, ac, n.norm()).inv()?,
+            meta: Box::new(meta),
+        })
+    }
+
+    /// Hits with `d` in `[min_d; max_d]` on the sides not culled.
+    /// Rays starting closer than `EPS` to the plane never hit.
+    #[inline(always)]
+    pub fn cast_ray_between(
+        &self,
+        ray: Ray<N>,
+        min_d: N,
+        max_d: N,
+        culling: Culling,
+    ) -> Option<RayIntersection<'_, N>> {
+        let src_abc = self.m_abc * (ray.src - self.a);
+        let dir_abc = self.m_abc * ray.dir1;
+
+        // the normal points to the front side
+        let is_front_face = src_abc.z() > N::ZERO;
+
+        let is_culled = match culling {
+            Culling::Back => !is_front_face,
+            Culling::Front => is_front_face,
+            Culling::None => false,
+        };
+
+        if is_culled || src_abc.z().abs() < N::EPS {
+            return None;
+        }
+
+        // moving away from the plane or along it, which would give an infinite `d`
+        if dir_abc.z() == N::ZERO || (dir_abc.z() > N::ZERO) == is_front_face {
+            return None;
+        }
+
+        let d = -src_abc.z() / dir_abc.z();
+
+        if d < min_d || d > max_d {
+            return None;
+        }
+
+        let p_abc = src_abc + dir_abc * d;
+
+        if likely(
+            p_abc.x() < -N::EPS || p_abc.y() < -N::EPS || p_abc.x() + p_abc.y() > N::ONE + N::EPS,
+        ) {
+            return None;
+        }
+
+        Some(RayIntersection {
+            tri: self,
+            d,
+            p_abc,
+            is_front_face,
+            world_to_mesh: None,
+            object_material: None,
+        })
+    }
+}
+
+impl<N: SimdNum> Triangle<N> {
+    /// Same test as `cast_ray_between` with `Culling::Back`, for the `active` lanes of a packet;
+    /// hits closer than the ones in `hits` replace them.
+    #[inline(always)]
+    pub(crate) fn cast_packet_between<'a, const L: usize>(
+        &'a self,
+        packet: &RayPacket<L>,
+        active: Mask<i32, L>,
+        min_d: Simd<f32, L>,
+        hits: &mut PacketHits<'a, N, L>,
+    ) {
+        let src_abc = Vector3Simd::transform(&self.m_abc, packet.src - Vector3Simd::splat(self.a));
+        let dir_abc = Vector3Simd::transform(&self.m_abc, packet.dir1);
+
+        let zero = Simd::splat(0.0);
+        let one = Simd::splat(1.0);
+        let eps = Simd::splat(N::EPS.to_f32());
+
+        // on the front side, not too close to the plane, and moving towards it
+        let mut hit = active & src_abc.z().simd_ge(eps) & dir_abc.z().simd_lt(zero);
+
+        if !hit.any() {
+            return;
+        }
+
+        let d = -src_abc.z() / dir_abc.z();
+        hit &= d.simd_ge(min_d) & d.simd_le(hits.d);
+
+        let p_abc = src_abc + dir_abc * d;
+        hit &= p_abc.x().simd_ge(-eps)
+            & p_abc.y().simd_ge(-eps)
+            & (p_abc.x() + p_abc.y()).simd_le(one + eps);
+
+        if hit.any() {
+            hits.update(self, hit, d, p_abc);
+        }
+    }
+}
+
+impl<'a, N: Num> Castable<'a, N> for Triangle<N> {
+    #[inline(always)]
+    fn cast_ray_culled(
+        &'a self,
+        ray: Ray<N>,
+        min_d: N,
+        max_d: N,
+        culling: Culling,
+    ) -> Option<RayIntersection<'a, N>> {
+        self.cast_ray_between(ray, min_d, max_d, culling)
+    }
+
+    #[inline(always)]
+    fn is_occluded(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> bool {
+        self.cast_ray(ray, min_d, max_d).is_some()
+    }
+
+    fn cast_ray_all(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> Vec<RayIntersection<'a, N>> {
+        self.cast_ray_between(ray, min_d, max_d, Culling::None)
+            .into_iter()
+            .collect()
+    }
+}
@@ -1,6 +1,6 @@
 use crate::math::*;
 
-use std::intrinsics::likely;
+use std::simd::prelude::*;
 
 use super::*;
 
@@ -16,6 +16,68 @@ impl<N: Num> TriangleList<N> {
     }
 }
 
+impl<N: Num> TriangleList<N> {
+    /// Moves the triangles to where `transform` maps them. Normals keep their lengths;
+    /// mirroring transforms swap the B and C vertices so that front faces stay on the outside.
+    /// Triangles that end up degenerate are dropped.
+    ///
+    /// Panics if the transform is degenerate.
+    pub fn transform(&mut self, transform: &Affine3<N>) {
+        let normal_mat = transform
+            .normal_matrix()
+            .expect("cannot transform by a degenerate transform");
+        let is_mirroring = transform.is_mirroring();
+
+        let transform_normal = |n: Vector3<N>| {
+            let n_t = normal_mat * n;
+
+            if n_t == Vector3::ZERO {
+                n_t
+            } else {
+                n_t * (n.abs() / n_t.abs())
+            }
+        };
+
+        let triangles = std::mem::take(&mut self.triangles);
+
+        self.triangles = triangles
+            .into_iter()
+            .filter_map(|tri| {
+                let meta = *tri.meta;
+
+                // columns as rows
+                let abc_n = meta.abc_nc.tr();
+                let abc_n = Matrix3(
+                    transform_normal(abc_n.0),
+                    transform_normal(abc_n.1),
+                    transform_normal(abc_n.2),
+                );
+                let abc_uv = meta.abc_uv.tr();
+
+                let (b, c, abc_n, abc_uv) = if is_mirroring {
+                    (
+                        meta.c,
+                        meta.b,
+                        Matrix3(abc_n.0, abc_n.2, abc_n.1),
+                        Matrix3(abc_uv.0, abc_uv.2, abc_uv.1),
+                    )
+                } else {
+                    (meta.b, meta.c, abc_n, abc_uv)
+                };
+
+                Triangle::from_meta(TriangleMeta {
+                    a: transform.apply_point(meta.a),
+                    b: transform.apply_point(b),
+                    c: transform.apply_point(c),
+                    abc_nc: abc_n.tr(),
+                    abc_uv: abc_uv.tr(),
+                    material: meta.material,
+                })
+            })
+            .collect();
+    }
+}
+
 impl<N: Num> From<Vec<Triangle<N>>> for TriangleList<N> {
     fn from(triangles: Vec<Triangle<N>>) -> Self {
         Self { triangles }
@@ -23,48 +85,61 @@ impl<N: Num> From<Vec<Triangle<N>>> for TriangleList<N> {
 }
 
 impl<'a, N: Num> Castable<'a, N> for TriangleList<N> {
-    fn cast_ray(&'a self, ray: Ray<N>, max_d: N) -> Option<RayIntersection<'a, N>> {
+    fn cast_ray_culled(
+        &'a self,
+        ray: Ray<N>,
+        min_d: N,
+        max_d: N,
+        culling: Culling,
+    ) -> Option<RayIntersection<'a, N>> {
         let mut cur_d = max_d;
-        let mut cur_tri: Option<&Triangle<N>> = None;
-        let mut cur_p_abc = Vector3::ZERO;
+        let mut cur_isec = None;
 
         for tri in self.triangles.iter() {
-            let src_abc = tri.m_abc * (ray.src - tri.a);
-            let dir_abc = tri.m_abc * ray.dir1;
-
-            if src_abc.z() < N::EPS || dir_abc.z() > N::EPS {
-                continue;
+            if let Some(isec) = tri.cast_ray_between(ray, min_d, cur_d, culling) {
+                cur_d = isec.d;
+                cur_isec = Some(isec);
             }
+        }
 
-            let d = -src_abc.z() / dir_abc.z();
-
-            if d >= cur_d {
-                continue;
-            }
+        cur_isec
+    }
 
-            let p_abc = src_abc + dir_abc * d;
+    fn is_occluded(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> bool {
+        self.triangles.iter().any(|tri| {
+            tri.cast_ray_between(ray, min_d, max_d, Culling::Back)
+                .is_some()
+        })
+    }
 
-            if likely(
-                p_abc.x() < -N::EPS
-                    || p_abc.y() < -N::EPS
-                    || p_abc.x() + p_abc.y() > N::ONE + N::EPS,
-            ) {
-                continue;
-            }
+    fn cast_packet<const L: usize>(
+        &'a self,
+        rays: &[Ray<N>; L],
+        min_d: N,
+        max_d: N,
+    ) -> [Option<RayIntersection<'a, N>>; L]
+    where
+        N: SimdNum,
+    {
+        let packet = RayPacket::from_rays(rays);
+        let min_d = Simd::splat(min_d.to_f32());
+        let mut hits = PacketHits::new(max_d);
 
-            cur_d = d;
-            cur_tri = Some(tri);
-            cur_p_abc = p_abc;
+        for tri in self.triangles.iter() {
+            tri.cast_packet_between(&packet, Mask::splat(true), min_d, &mut hits);
         }
 
-        if let Some(cur_tri) = cur_tri {
-            Some(RayIntersection {
-                tri: cur_tri,
-                d: cur_d,
-                p_abc: cur_p_abc,
-            })
-        } else {
-            None
-        }
+        hits.into_intersections(&packet)
+    }
+
+    fn cast_ray_all(&'a self, ray: Ray<N>, min_d: N, max_d: N) -> Vec<RayIntersection<'a, N>> {
+        let mut isecs: Vec<_> = self
+            .triangles
+            .iter()
+            .filter_map(|tri| tri.cast_ray_between(ray, min_d, max_d, Culling::None))
+            .collect();
+
+        sort_intersections(&mut isecs);
+        isecs
     }
 }
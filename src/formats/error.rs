@@ -0,0 +1,55 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+
+#[derive(Debug)]
+pub enum FormatError {
+    /// underlying reader or writer failed
+    Io(io::Error),
+
+    /// header fields are malformed or contradict each other
+    InvalidHeader(String),
+
+    /// data after the header is malformed
+    InvalidData(String),
+
+    /// well-formed, but a variant we cannot read or write
+    Unsupported(String),
+
+    /// input ended before all declared data was read
+    Truncated,
+
+    /// a size or count does not fit into the format's fields
+    OversizeDimension(String),
+}
+
+impl Display for FormatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{e}"),
+            Self::InvalidHeader(message) => write!(f, "invalid header: {message}"),
+            Self::InvalidData(message) => write!(f, "invalid data: {message}"),
+            Self::Unsupported(message) => write!(f, "unsupported: {message}"),
+            Self::Truncated => write!(f, "unexpected end of data"),
+            Self::OversizeDimension(message) => write!(f, "too big: {message}"),
+        }
+    }
+}
+
+impl Error for FormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for FormatError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::UnexpectedEof => Self::Truncated,
+            _ => Self::Io(e),
+        }
+    }
+}
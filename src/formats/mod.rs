@@ -1,2 +1,10 @@
+mod error;
+
+pub mod obj;
+pub mod ply;
+pub mod png;
+pub mod pnm;
 pub mod stl;
 pub mod tga;
+
+pub use error::*;
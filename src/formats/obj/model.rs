@@ -0,0 +1,217 @@
+use crate::cast;
+use crate::formats::FormatError;
+use crate::math::*;
+
+use std::io::{BufRead, BufReader, Read};
+
+/// Indices are zero-based and already resolved from negative (relative) form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ObjVertex {
+    pub p: usize,
+    pub uv: Option<usize>,
+    pub n: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjFace {
+    /// at least three; polygons are triangulated as a fan around the first vertex
+    pub vertices: Vec<ObjVertex>,
+}
+
+/// Only geometry statements (`v`, `vt`, `vn`, `f`) are supported;
+/// everything else (groups, materials, smoothing, lines) is skipped.
+#[derive(Debug, Clone, Default)]
+pub struct ObjModel {
+    pub positions: Vec<ff32_3>,
+
+    /// third coordinate is zero if not present in the file
+    pub uvs: Vec<ff32_3>,
+
+    pub normals: Vec<ff32_3>,
+    pub faces: Vec<ObjFace>,
+}
+
+fn invalid_data(line_number: usize, message: String) -> FormatError {
+    FormatError::InvalidData(format!("OBJ line {line_number}: {message}"))
+}
+
+impl ObjModel {
+    fn parse_coords<'a, I: Iterator<Item = &'a str>>(
+        line_number: usize,
+        tokens: I,
+        min_count: usize,
+    ) -> Result<ff32_3, FormatError> {
+        let mut coords = [ff32(0.0); 3];
+        let mut count = 0;
+
+        for token in tokens.take(3) {
+            coords[count] = ff32(token.parse().map_err(|_| {
+                invalid_data(line_number, format!("expected a number, got {token:?}"))
+            })?);
+            count += 1;
+        }
+
+        if count < min_count {
+            return Err(invalid_data(
+                line_number,
+                format!("expected at least {min_count} coordinates, got {count}"),
+            ));
+        }
+
+        Ok(ff32_3::new(coords[0], coords[1], coords[2]))
+    }
+
+    fn resolve_index(line_number: usize, token: &str, count: usize) -> Result<usize, FormatError> {
+        let index: i64 = token
+            .parse()
+            .map_err(|_| invalid_data(line_number, format!("expected an index, got {token:?}")))?;
+
+        let resolved = if index > 0 {
+            index - 1
+        } else {
+            count as i64 + index
+        };
+
+        if index == 0 || resolved < 0 || resolved >= count as i64 {
+            return Err(invalid_data(
+                line_number,
+                format!("index {index} out of range for {count} elements"),
+            ));
+        }
+
+        Ok(resolved as usize)
+    }
+
+    fn parse_face_vertex(&self, line_number: usize, token: &str) -> Result<ObjVertex, FormatError> {
+        let mut parts = token.split('/');
+
+        let p = Self::resolve_index(line_number, parts.next().unwrap(), self.positions.len())?;
+
+        let uv = match parts.next() {
+            None | Some("") => None,
+            Some(uv) => Some(Self::resolve_index(line_number, uv, self.uvs.len())?),
+        };
+
+        let n = match parts.next() {
+            None | Some("") => None,
+            Some(n) => Some(Self::resolve_index(line_number, n, self.normals.len())?),
+        };
+
+        if parts.next().is_some() {
+            return Err(invalid_data(
+                line_number,
+                format!("invalid face vertex {token:?}"),
+            ));
+        }
+
+        Ok(ObjVertex { p, uv, n })
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let mut model = Self::default();
+
+        for (i, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let line_number = i + 1;
+
+            let line = line.split('#').next().unwrap();
+            let mut tokens = line.split_ascii_whitespace();
+
+            match tokens.next() {
+                Some("v") => {
+                    let p = Self::parse_coords(line_number, tokens, 3)?;
+                    model.positions.push(p);
+                }
+
+                Some("vt") => {
+                    let uv = Self::parse_coords(line_number, tokens, 1)?;
+                    model.uvs.push(uv);
+                }
+
+                Some("vn") => {
+                    let n = Self::parse_coords(line_number, tokens, 3)?;
+                    model.normals.push(n);
+                }
+
+                Some("f") => {
+                    let vertices = tokens
+                        .map(|token| model.parse_face_vertex(line_number, token))
+                        .collect::<Result<Vec<_>, _>>()?;
+
+                    if vertices.len() < 3 {
+                        return Err(invalid_data(
+                            line_number,
+                            format!("face has {} vertices, expected at least 3", vertices.len()),
+                        ));
+                    }
+
+                    model.faces.push(ObjFace { vertices });
+                }
+
+                _ => continue,
+            }
+        }
+
+        Ok(model)
+    }
+
+    /// Degenerate (zero-area) triangles are skipped.
+    pub fn to_triangle_list(&self) -> cast::TriangleList<ff32> {
+        let mut triangles = Vec::<cast::Triangle<ff32>>::new();
+
+        for face in self.faces.iter() {
+            let v0 = face.vertices[0];
+
+            for (&v1, &v2) in face.vertices[1..].iter().zip(face.vertices[2..].iter()) {
+                if let Some(tri) = self.make_cast_triangle(v0, v1, v2) {
+                    triangles.push(tri);
+                }
+            }
+        }
+
+        cast::TriangleList::from(triangles)
+    }
+
+    fn make_cast_triangle(
+        &self,
+        va: ObjVertex,
+        vb: ObjVertex,
+        vc: ObjVertex,
+    ) -> Option<cast::Triangle<ff32>> {
+        let a = self.positions[va.p];
+        let b = self.positions[vb.p];
+        let c = self.positions[vc.p];
+
+        let n = ff32_3::cross(b - a, c - a);
+        if n == ff32_3::ZERO {
+            return None;
+        }
+
+        let n1 = n.norm();
+
+        // vertices without a usable normal fall back to the flat face normal
+        let vertex_normal = |v: ObjVertex| match v.n.map(|n| self.normals[n]) {
+            Some(n) if n != ff32_3::ZERO => n.norm(),
+            _ => n1,
+        };
+
+        // STL-like identity mapping unless all three vertices have UVs
+        let abc_uv = match (va.uv, vb.uv, vc.uv) {
+            (Some(uv_a), Some(uv_b), Some(uv_c)) => {
+                ff32_3x3::from_cols(self.uvs[uv_a], self.uvs[uv_b], self.uvs[uv_c])
+            }
+
+            _ => ff32_3x3::ONE,
+        };
+
+        cast::Triangle::from_meta(cast::TriangleMeta {
+            a,
+            b,
+            c,
+
+            abc_nc: ff32_3x3::from_cols(vertex_normal(va), vertex_normal(vb), vertex_normal(vc)),
+            abc_uv,
+            material: 0,
+        })
+    }
+}
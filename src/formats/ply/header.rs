@@ -0,0 +1,262 @@
+use crate::formats::FormatError;
+
+use std::io::{BufRead, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlyFormat {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlyScalarType {
+    Char,
+    UChar,
+    Short,
+    UShort,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlyPropertyType {
+    Scalar(PlyScalarType),
+    List {
+        count: PlyScalarType,
+        item: PlyScalarType,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlyProperty {
+    pub name: String,
+    pub ty: PlyPropertyType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlyElementDef {
+    pub name: String,
+    pub count: usize,
+    pub properties: Vec<PlyProperty>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlyHeader {
+    pub format: PlyFormat,
+
+    /// `comment` and `obj_info` lines, without the keyword
+    pub comments: Vec<String>,
+
+    pub elements: Vec<PlyElementDef>,
+}
+
+pub(super) fn invalid_data(message: String) -> FormatError {
+    FormatError::InvalidData(message)
+}
+
+fn invalid_header(message: String) -> FormatError {
+    FormatError::InvalidHeader(message)
+}
+
+impl PlyFormat {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Ascii => "ascii",
+            Self::BinaryLittleEndian => "binary_little_endian",
+            Self::BinaryBigEndian => "binary_big_endian",
+        }
+    }
+}
+
+impl PlyScalarType {
+    /// Accepts both the original and the sized (`int8`, `float32`, ...) names.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "char" | "int8" => Some(Self::Char),
+            "uchar" | "uint8" => Some(Self::UChar),
+            "short" | "int16" => Some(Self::Short),
+            "ushort" | "uint16" => Some(Self::UShort),
+            "int" | "int32" => Some(Self::Int),
+            "uint" | "uint32" => Some(Self::UInt),
+            "float" | "float32" => Some(Self::Float),
+            "double" | "float64" => Some(Self::Double),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Char => "char",
+            Self::UChar => "uchar",
+            Self::Short => "short",
+            Self::UShort => "ushort",
+            Self::Int => "int",
+            Self::UInt => "uint",
+            Self::Float => "float",
+            Self::Double => "double",
+        }
+    }
+
+    pub fn is_integer(&self) -> bool {
+        !matches!(self, Self::Float | Self::Double)
+    }
+
+    /// Whether `value` is written without wrapping or truncation;
+    /// floating-point types hold any value.
+    pub fn can_hold(&self, value: f64) -> bool {
+        let (min, max) = match self {
+            Self::Char => (i8::MIN as f64, i8::MAX as f64),
+            Self::UChar => (u8::MIN as f64, u8::MAX as f64),
+            Self::Short => (i16::MIN as f64, i16::MAX as f64),
+            Self::UShort => (u16::MIN as f64, u16::MAX as f64),
+            Self::Int => (i32::MIN as f64, i32::MAX as f64),
+            Self::UInt => (u32::MIN as f64, u32::MAX as f64),
+            Self::Float | Self::Double => return true,
+        };
+
+        value.fract() == 0.0 && value >= min && value <= max
+    }
+
+    fn parse(name: &str) -> Result<Self, FormatError> {
+        Self::from_name(name).ok_or_else(|| invalid_header(format!("unknown PLY type {name:?}")))
+    }
+}
+
+impl PlyElementDef {
+    pub fn property_index(&self, name: &str) -> Option<usize> {
+        self.properties.iter().position(|p| p.name == name)
+    }
+}
+
+impl PlyHeader {
+    pub fn read_from<R: BufRead>(reader: &mut R) -> Result<Self, FormatError> {
+        let mut lines = reader.lines();
+
+        let mut next_line = || -> Result<String, FormatError> {
+            match lines.next() {
+                Some(line) => Ok(line?),
+                None => Err(FormatError::Truncated),
+            }
+        };
+
+        if next_line()?.trim() != "ply" {
+            return Err(invalid_header("missing PLY magic".to_string()));
+        }
+
+        let mut format: Option<PlyFormat> = None;
+        let mut comments = Vec::<String>::new();
+        let mut elements = Vec::<PlyElementDef>::new();
+
+        loop {
+            let line = next_line()?;
+            let mut tokens = line.split_ascii_whitespace();
+
+            match tokens.next() {
+                Some("format") => {
+                    format = Some(match (tokens.next(), tokens.next()) {
+                        (Some("ascii"), Some("1.0")) => PlyFormat::Ascii,
+                        (Some("binary_little_endian"), Some("1.0")) => {
+                            PlyFormat::BinaryLittleEndian
+                        }
+                        (Some("binary_big_endian"), Some("1.0")) => PlyFormat::BinaryBigEndian,
+                        _ => return Err(FormatError::Unsupported(format!("PLY {line:?}"))),
+                    });
+                }
+
+                Some(keyword @ ("comment" | "obj_info")) => {
+                    let text = line.trim_start()[keyword.len()..].trim();
+                    comments.push(text.to_string());
+                }
+
+                Some("element") => {
+                    let (name, count) = match (tokens.next(), tokens.next()) {
+                        (Some(name), Some(count)) => (name, count),
+                        _ => return Err(invalid_header(format!("invalid PLY {line:?}"))),
+                    };
+
+                    elements.push(PlyElementDef {
+                        name: name.to_string(),
+                        count: count
+                            .parse()
+                            .map_err(|_| invalid_header(format!("invalid PLY {line:?}")))?,
+                        properties: vec![],
+                    });
+                }
+
+                Some("property") => {
+                    let element = elements.last_mut().ok_or_else(|| {
+                        invalid_header(format!("PLY property outside of element: {line:?}"))
+                    })?;
+
+                    let ty = match tokens.next() {
+                        Some("list") => PlyPropertyType::List {
+                            count: PlyScalarType::parse(tokens.next().unwrap_or_default())?,
+                            item: PlyScalarType::parse(tokens.next().unwrap_or_default())?,
+                        },
+
+                        Some(ty) => PlyPropertyType::Scalar(PlyScalarType::parse(ty)?),
+                        None => return Err(invalid_header(format!("invalid PLY {line:?}"))),
+                    };
+
+                    let name = tokens
+                        .next()
+                        .ok_or_else(|| invalid_header(format!("invalid PLY {line:?}")))?;
+
+                    element.properties.push(PlyProperty {
+                        name: name.to_string(),
+                        ty,
+                    });
+                }
+
+                Some("end_header") => break,
+                None => continue,
+
+                Some(_) => return Err(invalid_header(format!("unknown PLY header line {line:?}"))),
+            }
+        }
+
+        let format = format.ok_or_else(|| invalid_header("missing PLY format".to_string()))?;
+
+        Ok(Self {
+            format,
+            comments,
+            elements,
+        })
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), FormatError> {
+        writeln!(writer, "ply")?;
+        writeln!(writer, "format {} 1.0", self.format.name())?;
+
+        for comment in self.comments.iter() {
+            writeln!(writer, "comment {comment}")?;
+        }
+
+        for element in self.elements.iter() {
+            writeln!(writer, "element {} {}", element.name, element.count)?;
+
+            for property in element.properties.iter() {
+                match property.ty {
+                    PlyPropertyType::Scalar(ty) => {
+                        writeln!(writer, "property {} {}", ty.name(), property.name)?
+                    }
+
+                    PlyPropertyType::List { count, item } => writeln!(
+                        writer,
+                        "property list {} {} {}",
+                        count.name(),
+                        item.name(),
+                        property.name
+                    )?,
+                }
+            }
+        }
+
+        writeln!(writer, "end_header")?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+use crate::cast;
+use crate::formats::FormatError;
+use crate::math::*;
+
+use super::*;
+
+/// Triangle or polygon mesh extracted from the `vertex` and `face` elements.
+#[derive(Debug, Clone, Default)]
+pub struct PlyMesh {
+    pub positions: Vec<ff32_3>,
+
+    pub normals: Option<Vec<ff32_3>>,
+
+    /// in the [0; 1] range; integer color channels are divided by 255;
+    /// not carried into triangle lists, which have no per-vertex colors
+    pub colors: Option<Vec<ff32_3>>,
+
+    /// third coordinate is always zero
+    pub uvs: Option<Vec<ff32_3>>,
+
+    /// polygons are triangulated as a fan around the first vertex
+    pub faces: Vec<Vec<usize>>,
+}
+
+fn vertex_property_type(vertex: &PlyElementDef, name: &str) -> Option<PlyScalarType> {
+    let i = vertex.property_index(name)?;
+
+    match vertex.properties[i].ty {
+        PlyPropertyType::Scalar(ty) => Some(ty),
+        PlyPropertyType::List { .. } => None,
+    }
+}
+
+/// `None` if none of the properties are present; if some are, all of them must be.
+/// Vectors of two properties, like UVs, get a zero third component.
+fn vertex_vectors(vertex: &PlyElement, names: &[&str]) -> Result<Option<Vec<ff32_3>>, FormatError> {
+    if names
+        .iter()
+        .all(|name| vertex.def.property_index(name).is_none())
+    {
+        return Ok(None);
+    }
+
+    let columns = names
+        .iter()
+        .map(|name| {
+            vertex.scalars(name).ok_or_else(|| {
+                FormatError::InvalidHeader(format!(
+                    "PLY vertices have no scalar {name:?} property, expected {names:?}"
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let component =
+        |k: usize, i: usize| ff32(columns.get(k).map_or(0.0, |column| column[i]) as f32);
+
+    let vectors = (0..vertex.rows.len())
+        .map(|i| ff32_3::new(component(0, i), component(1, i), component(2, i)))
+        .collect();
+
+    Ok(Some(vectors))
+}
+
+impl PlyMesh {
+    pub fn from_model(model: &PlyModel) -> Result<Self, FormatError> {
+        let vertex = model
+            .element("vertex")
+            .ok_or_else(|| invalid_data("PLY has no vertex element".to_string()))?;
+
+        let positions = vertex_vectors(vertex, &["x", "y", "z"])?.ok_or_else(|| {
+            FormatError::InvalidHeader("PLY vertices have no x, y, z".to_string())
+        })?;
+
+        let normals = vertex_vectors(vertex, &["nx", "ny", "nz"])?;
+
+        let mut uvs = None;
+        for names in [["u", "v"], ["s", "t"], ["texture_u", "texture_v"]] {
+            uvs = vertex_vectors(vertex, &names)?;
+
+            if uvs.is_some() {
+                break;
+            }
+        }
+
+        let colors = vertex_vectors(vertex, &["red", "green", "blue"])?;
+        let colors = match vertex_property_type(&vertex.def, "red") {
+            Some(ty) if ty.is_integer() => {
+                colors.map(|colors| colors.into_iter().map(|c| c / ff32(255.0)).collect())
+            }
+            _ => colors,
+        };
+
+        let mut faces = Vec::<Vec<usize>>::new();
+
+        if let Some(face) = model.element("face") {
+            let lists = face
+                .lists("vertex_indices")
+                .or_else(|| face.lists("vertex_index"))
+                .ok_or_else(|| invalid_data("PLY faces have no vertex_indices".to_string()))?;
+
+            for list in lists {
+                let indices = list
+                    .iter()
+                    .map(|&i| {
+                        if i >= 0.0 && (i as usize) < positions.len() {
+                            Ok(i as usize)
+                        } else {
+                            Err(invalid_data(format!("PLY vertex index {i} out of range")))
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if indices.len() < 3 {
+                    return Err(invalid_data(format!(
+                        "PLY face has {} vertices, expected at least 3",
+                        indices.len()
+                    )));
+                }
+
+                faces.push(indices);
+            }
+        }
+
+        Ok(Self {
+            positions,
+            normals,
+            colors,
+            uvs,
+            faces,
+        })
+    }
+
+    /// Colors are written as `uchar`, everything else as `float`.
+    /// Face lengths are `uchar`, or `uint` if some face has more than 255 vertices.
+    pub fn to_model(&self, format: PlyFormat) -> PlyModel {
+        let float = PlyPropertyType::Scalar(PlyScalarType::Float);
+        let uchar = PlyPropertyType::Scalar(PlyScalarType::UChar);
+
+        let mut properties = Vec::<PlyProperty>::new();
+        let mut add_properties = |names: [&str; 3], ty: PlyPropertyType| {
+            for name in names {
+                properties.push(PlyProperty {
+                    name: name.to_string(),
+                    ty,
+                });
+            }
+        };
+
+        add_properties(["x", "y", "z"], float);
+
+        if self.normals.is_some() {
+            add_properties(["nx", "ny", "nz"], float);
+        }
+
+        if self.colors.is_some() {
+            add_properties(["red", "green", "blue"], uchar);
+        }
+
+        if self.uvs.is_some() {
+            properties.push(PlyProperty {
+                name: "u".to_string(),
+                ty: float,
+            });
+            properties.push(PlyProperty {
+                name: "v".to_string(),
+                ty: float,
+            });
+        }
+
+        let rows = (0..self.positions.len())
+            .map(|i| {
+                let mut row = Vec::<PlyValue>::new();
+                let mut add_values = |v: ff32_3, count: usize| {
+                    for x in [v.0, v.1, v.2].into_iter().take(count) {
+                        row.push(PlyValue::Scalar(x.0 as f64));
+                    }
+                };
+
+                add_values(self.positions[i], 3);
+
+                if let Some(normals) = self.normals.as_ref() {
+                    add_values(normals[i], 3);
+                }
+
+                if let Some(colors) = self.colors.as_ref() {
+                    let to_uchar = |x: ff32| ff32((x.0 * 255.0).round().clamp(0.0, 255.0));
+                    let Vector3(r, g, b) = colors[i];
+                    add_values(Vector3::new(to_uchar(r), to_uchar(g), to_uchar(b)), 3);
+                }
+
+                if let Some(uvs) = self.uvs.as_ref() {
+                    add_values(uvs[i], 2);
+                }
+
+                row
+            })
+            .collect();
+
+        let vertex = PlyElement {
+            def: PlyElementDef {
+                name: "vertex".to_string(),
+                count: self.positions.len(),
+                properties,
+            },
+            rows,
+        };
+
+        let face = PlyElement {
+            def: PlyElementDef {
+                name: "face".to_string(),
+                count: self.faces.len(),
+                properties: vec![PlyProperty {
+                    name: "vertex_indices".to_string(),
+                    ty: PlyPropertyType::List {
+                        count: if self.faces.iter().all(|face| face.len() <= u8::MAX as usize) {
+                            PlyScalarType::UChar
+                        } else {
+                            PlyScalarType::UInt
+                        },
+                        item: PlyScalarType::Int,
+                    },
+                }],
+            },
+            rows: self
+                .faces
+                .iter()
+                .map(|face| vec![PlyValue::List(face.iter().map(|&i| i as f64).collect())])
+                .collect(),
+        };
+
+        PlyModel {
+            format,
+            comments: vec![],
+            elements: vec![vertex, face],
+        }
+    }
+
+    /// Degenerate (zero-area) triangles are skipped; colors are dropped.
+    pub fn to_triangle_list(&self) -> cast::TriangleList<ff32> {
+        let mut triangles = Vec::<cast::Triangle<ff32>>::new();
+
+        for face in self.faces.iter() {
+            for (&i1, &i2) in face[1..].iter().zip(face[2..].iter()) {
+                if let Some(tri) = self.make_cast_triangle(face[0], i1, i2) {
+                    triangles.push(tri);
+                }
+            }
+        }
+
+        cast::TriangleList::from(triangles)
+    }
+
+    fn make_cast_triangle(&self, ia: usize, ib: usize, ic: usize) -> Option<cast::Triangle<ff32>> {
+        let a = self.positions[ia];
+        let b = self.positions[ib];
+        let c = self.positions[ic];
+
+        let n = ff32_3::cross(b - a, c - a);
+        if n == ff32_3::ZERO {
+            return None;
+        }
+
+        let n1 = n.norm();
+
+        // vertices without a usable normal fall back to the flat face normal
+        let vertex_normal = |i: usize| match self.normals.as_ref().map(|normals| normals[i]) {
+            Some(n) if n != ff32_3::ZERO => n.norm(),
+            _ => n1,
+        };
+
+        let abc_uv = match self.uvs.as_ref() {
+            Some(uvs) => ff32_3x3::from_cols(uvs[ia], uvs[ib], uvs[ic]),
+            None => ff32_3x3::ONE,
+        };
+
+        cast::Triangle::from_meta(cast::TriangleMeta {
+            a,
+            b,
+            c,
+
+            abc_nc: ff32_3x3::from_cols(vertex_normal(ia), vertex_normal(ib), vertex_normal(ic)),
+            abc_uv,
+            material: 0,
+        })
+    }
+}
+
+impl PlyModel {
+    pub fn to_triangle_list(&self) -> Result<cast::TriangleList<ff32>, FormatError> {
+        Ok(PlyMesh::from_model(self)?.to_triangle_list())
+    }
+}
@@ -0,0 +1,7 @@
+mod header;
+mod mesh;
+mod model;
+
+pub use header::*;
+pub use mesh::*;
+pub use model::*;
@@ -0,0 +1,353 @@
+use crate::formats::FormatError;
+
+use std::io::{BufReader, Read, Write};
+
+use byteorder::{ByteOrder, ReadBytesExt, WriteBytesExt, BE, LE};
+
+use super::*;
+
+/// All PLY scalar types fit into `f64` without loss.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlyValue {
+    Scalar(f64),
+    List(Vec<f64>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyElement {
+    pub def: PlyElementDef,
+
+    /// one row per element instance, one value per property
+    pub rows: Vec<Vec<PlyValue>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlyModel {
+    pub format: PlyFormat,
+    pub comments: Vec<String>,
+    pub elements: Vec<PlyElement>,
+}
+
+impl PlyValue {
+    pub fn as_scalar(&self) -> Option<f64> {
+        match self {
+            Self::Scalar(x) => Some(*x),
+            Self::List(_) => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[f64]> {
+        match self {
+            Self::Scalar(_) => None,
+            Self::List(xs) => Some(xs),
+        }
+    }
+}
+
+impl PlyModel {
+    pub fn header(&self) -> PlyHeader {
+        PlyHeader {
+            format: self.format,
+            comments: self.comments.clone(),
+            elements: self.elements.iter().map(|e| e.def.clone()).collect(),
+        }
+    }
+
+    pub fn element(&self, name: &str) -> Option<&PlyElement> {
+        self.elements.iter().find(|e| e.def.name == name)
+    }
+}
+
+trait ValueReader {
+    fn read_scalar(&mut self, ty: PlyScalarType) -> Result<f64, FormatError>;
+
+    fn read_value(&mut self, ty: PlyPropertyType) -> Result<PlyValue, FormatError> {
+        match ty {
+            PlyPropertyType::Scalar(ty) => Ok(PlyValue::Scalar(self.read_scalar(ty)?)),
+
+            PlyPropertyType::List { count, item } => {
+                let count = self.read_scalar(count)?;
+
+                if !(count >= 0.0 && count.fract() == 0.0) {
+                    return Err(invalid_data(format!("invalid PLY list length {count}")));
+                }
+
+                let items = (0..count as usize)
+                    .map(|_| self.read_scalar(item))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                Ok(PlyValue::List(items))
+            }
+        }
+    }
+}
+
+struct AsciiValueReader<'a, I: Iterator<Item = &'a str>> {
+    tokens: I,
+}
+
+impl<'a, I: Iterator<Item = &'a str>> ValueReader for AsciiValueReader<'a, I> {
+    fn read_scalar(&mut self, ty: PlyScalarType) -> Result<f64, FormatError> {
+        let token = self.tokens.next().ok_or(FormatError::Truncated)?;
+
+        let value: f64 = token
+            .parse()
+            .map_err(|_| invalid_data(format!("expected a number in PLY data, got {token:?}")))?;
+
+        if ty.is_integer() && value.fract() != 0.0 {
+            return Err(invalid_data(format!(
+                "expected an integer in PLY data, got {token:?}"
+            )));
+        }
+
+        Ok(value)
+    }
+}
+
+struct BinaryValueReader<'a, R: Read, B: ByteOrder> {
+    reader: &'a mut R,
+    byte_order: std::marker::PhantomData<B>,
+}
+
+impl<'a, R: Read, B: ByteOrder> ValueReader for BinaryValueReader<'a, R, B> {
+    fn read_scalar(&mut self, ty: PlyScalarType) -> Result<f64, FormatError> {
+        let reader = &mut self.reader;
+
+        Ok(match ty {
+            PlyScalarType::Char => reader.read_i8()? as f64,
+            PlyScalarType::UChar => reader.read_u8()? as f64,
+            PlyScalarType::Short => reader.read_i16::<B>()? as f64,
+            PlyScalarType::UShort => reader.read_u16::<B>()? as f64,
+            PlyScalarType::Int => reader.read_i32::<B>()? as f64,
+            PlyScalarType::UInt => reader.read_u32::<B>()? as f64,
+            PlyScalarType::Float => reader.read_f32::<B>()? as f64,
+            PlyScalarType::Double => reader.read_f64::<B>()?,
+        })
+    }
+}
+
+impl PlyModel {
+    fn read_elements<V: ValueReader>(
+        header: &PlyHeader,
+        values: &mut V,
+    ) -> Result<Vec<PlyElement>, FormatError> {
+        let mut elements = Vec::<PlyElement>::with_capacity(header.elements.len());
+
+        for def in header.elements.iter() {
+            let mut rows = Vec::<Vec<PlyValue>>::with_capacity(def.count.min(1 << 20));
+
+            for _ in 0..def.count {
+                let row = def
+                    .properties
+                    .iter()
+                    .map(|p| values.read_value(p.ty))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                rows.push(row);
+            }
+
+            elements.push(PlyElement {
+                def: def.clone(),
+                rows,
+            });
+        }
+
+        Ok(elements)
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let mut reader = BufReader::new(reader);
+        let header = PlyHeader::read_from(&mut reader)?;
+
+        let elements = match header.format {
+            PlyFormat::Ascii => {
+                let mut text = String::new();
+                reader.read_to_string(&mut text)?;
+
+                let mut values = AsciiValueReader {
+                    tokens: text.split_ascii_whitespace(),
+                };
+
+                Self::read_elements(&header, &mut values)?
+            }
+
+            PlyFormat::BinaryLittleEndian => {
+                let mut values = BinaryValueReader::<_, LE> {
+                    reader: &mut reader,
+                    byte_order: Default::default(),
+                };
+
+                Self::read_elements(&header, &mut values)?
+            }
+
+            PlyFormat::BinaryBigEndian => {
+                let mut values = BinaryValueReader::<_, BE> {
+                    reader: &mut reader,
+                    byte_order: Default::default(),
+                };
+
+                Self::read_elements(&header, &mut values)?
+            }
+        };
+
+        Ok(Self {
+            format: header.format,
+            comments: header.comments,
+            elements,
+        })
+    }
+}
+
+fn write_ascii_scalar<W: Write>(
+    writer: &mut W,
+    ty: PlyScalarType,
+    value: f64,
+) -> Result<(), FormatError> {
+    if ty.is_integer() {
+        write!(writer, "{}", value as i64)?;
+    } else if ty == PlyScalarType::Float {
+        write!(writer, "{}", value as f32)?;
+    } else {
+        write!(writer, "{value}")?;
+    }
+
+    Ok(())
+}
+
+fn write_binary_scalar<W: Write, B: ByteOrder>(
+    writer: &mut W,
+    ty: PlyScalarType,
+    value: f64,
+) -> Result<(), FormatError> {
+    match ty {
+        PlyScalarType::Char => writer.write_i8(value as i8)?,
+        PlyScalarType::UChar => writer.write_u8(value as u8)?,
+        PlyScalarType::Short => writer.write_i16::<B>(value as i16)?,
+        PlyScalarType::UShort => writer.write_u16::<B>(value as u16)?,
+        PlyScalarType::Int => writer.write_i32::<B>(value as i32)?,
+        PlyScalarType::UInt => writer.write_u32::<B>(value as u32)?,
+        PlyScalarType::Float => writer.write_f32::<B>(value as f32)?,
+        PlyScalarType::Double => writer.write_f64::<B>(value)?,
+    }
+
+    Ok(())
+}
+
+impl PlyModel {
+    fn write_scalar<W: Write>(
+        &self,
+        writer: &mut W,
+        ty: PlyScalarType,
+        value: f64,
+    ) -> Result<(), FormatError> {
+        if !ty.can_hold(value) {
+            return Err(invalid_data(format!(
+                "PLY {} cannot hold {value}",
+                ty.name()
+            )));
+        }
+
+        match self.format {
+            PlyFormat::Ascii => write_ascii_scalar(writer, ty, value),
+            PlyFormat::BinaryLittleEndian => write_binary_scalar::<W, LE>(writer, ty, value),
+            PlyFormat::BinaryBigEndian => write_binary_scalar::<W, BE>(writer, ty, value),
+        }
+    }
+
+    fn write_separator<W: Write>(
+        &self,
+        writer: &mut W,
+        separator: &str,
+    ) -> Result<(), FormatError> {
+        if self.format == PlyFormat::Ascii {
+            writer.write_all(separator.as_bytes())?;
+        }
+
+        Ok(())
+    }
+
+    /// Every row must have one value per property; integer values must be whole
+    /// and in the range of their declared types; `float` values are rounded to `f32`.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), FormatError> {
+        self.header().write_to(writer)?;
+
+        for element in self.elements.iter() {
+            if element.rows.len() != element.def.count {
+                return Err(invalid_data(format!(
+                    "PLY element {:?} declares {} rows, has {}",
+                    element.def.name,
+                    element.def.count,
+                    element.rows.len()
+                )));
+            }
+
+            for row in element.rows.iter() {
+                if row.len() != element.def.properties.len() {
+                    return Err(invalid_data(format!(
+                        "PLY element {:?} declares {} properties, a row has {} values",
+                        element.def.name,
+                        element.def.properties.len(),
+                        row.len()
+                    )));
+                }
+
+                for (i, (property, value)) in element.def.properties.iter().zip(row).enumerate() {
+                    if i > 0 {
+                        self.write_separator(writer, " ")?;
+                    }
+
+                    match (property.ty, value) {
+                        (PlyPropertyType::Scalar(ty), PlyValue::Scalar(x)) => {
+                            self.write_scalar(writer, ty, *x)?
+                        }
+
+                        (PlyPropertyType::List { count, item }, PlyValue::List(xs)) => {
+                            if !count.can_hold(xs.len() as f64) {
+                                return Err(FormatError::OversizeDimension(format!(
+                                    "PLY list of {} items with a {} length",
+                                    xs.len(),
+                                    count.name()
+                                )));
+                            }
+
+                            self.write_scalar(writer, count, xs.len() as f64)?;
+
+                            for x in xs.iter() {
+                                self.write_separator(writer, " ")?;
+                                self.write_scalar(writer, item, *x)?;
+                            }
+                        }
+
+                        _ => {
+                            return Err(invalid_data(format!(
+                                "PLY property {:?} has mismatched value {value:?}",
+                                property.name
+                            )))
+                        }
+                    }
+                }
+
+                self.write_separator(writer, "\n")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PlyElement {
+    /// Values of a scalar property for every row.
+    pub fn scalars(&self, name: &str) -> Option<Vec<f64>> {
+        let i = self.def.property_index(name)?;
+        self.rows
+            .iter()
+            .map(|row| row.get(i)?.as_scalar())
+            .collect()
+    }
+
+    /// Values of a list property for every row.
+    pub fn lists(&self, name: &str) -> Option<Vec<&[f64]>> {
+        let i = self.def.property_index(name)?;
+        self.rows.iter().map(|row| row.get(i)?.as_list()).collect()
+    }
+}
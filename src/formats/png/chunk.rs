@@ -0,0 +1,112 @@
+use std::io::{Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+
+use crate::formats::FormatError;
+
+pub(super) const SIGNATURE: &[u8; 8] = b"\x89PNG\r\n\x1a\n";
+
+/// Chunks longer than this are rejected, as required by the PNG spec.
+const MAX_CHUNK_LENGTH: u32 = (1 << 31) - 1;
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+
+    let mut n = 0;
+    while n < 256 {
+        let mut c = n as u32;
+
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB88320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+
+        table[n] = c;
+        n += 1;
+    }
+
+    table
+}
+
+const CRC_TABLE: [u32; 256] = crc_table();
+
+/// CRC-32 of the concatenation of `parts`.
+pub(super) fn crc32(parts: &[&[u8]]) -> u32 {
+    let mut c = 0xFFFFFFFFu32;
+
+    for part in parts {
+        for &b in part.iter() {
+            c = CRC_TABLE[((c ^ b as u32) & 0xFF) as usize] ^ (c >> 8);
+        }
+    }
+
+    c ^ 0xFFFFFFFF
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct PngChunk {
+    pub kind: [u8; 4],
+    pub data: Vec<u8>,
+}
+
+impl PngChunk {
+    pub fn new(kind: &[u8; 4], data: Vec<u8>) -> Self {
+        Self { kind: *kind, data }
+    }
+
+    /// Critical chunks must be understood by the decoder; ancillary ones may be skipped.
+    pub fn is_critical(&self) -> bool {
+        self.kind[0] & 0x20 == 0
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let length = reader.read_u32::<BE>()?;
+        if length > MAX_CHUNK_LENGTH {
+            return Err(FormatError::InvalidData(format!(
+                "PNG chunk of {length} bytes"
+            )));
+        }
+
+        let mut kind = [0u8; 4];
+        reader.read_exact(&mut kind)?;
+
+        // not preallocated, so that a bogus length cannot exhaust memory
+        let mut data = Vec::new();
+        reader.take(length as u64).read_to_end(&mut data)?;
+        if data.len() < length as usize {
+            return Err(FormatError::Truncated);
+        }
+
+        let crc = reader.read_u32::<BE>()?;
+        if crc != crc32(&[&kind, &data]) {
+            return Err(FormatError::InvalidData(format!(
+                "PNG chunk {} has a wrong CRC",
+                String::from_utf8_lossy(&kind)
+            )));
+        }
+
+        Ok(Self { kind, data })
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), FormatError> {
+        if self.data.len() > MAX_CHUNK_LENGTH as usize {
+            return Err(FormatError::OversizeDimension(format!(
+                "PNG chunk {} of {} bytes",
+                String::from_utf8_lossy(&self.kind),
+                self.data.len()
+            )));
+        }
+
+        writer.write_u32::<BE>(self.data.len() as u32)?;
+        writer.write_all(&self.kind)?;
+        writer.write_all(&self.data)?;
+        writer.write_u32::<BE>(crc32(&[&self.kind, &self.data]))?;
+
+        Ok(())
+    }
+}
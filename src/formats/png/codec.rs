@@ -0,0 +1,316 @@
+use std::io::{Read, Write};
+
+use miniz_oxide::inflate::TINFLStatus;
+
+use crate::formats::FormatError;
+use crate::image::*;
+
+use super::chunk::*;
+use super::filter::*;
+use super::*;
+
+/// Reads the `index`-th sample of a scanline; samples narrower than a byte
+/// are packed starting from the most significant bit.
+fn read_sample(line: &[u8], index: usize, bit_depth: u8) -> u16 {
+    match bit_depth {
+        16 => u16::from_be_bytes([line[2 * index], line[2 * index + 1]]),
+        8 => line[index] as u16,
+
+        _ => {
+            let bit = index * bit_depth as usize;
+            let shift = 8 - bit_depth as usize - bit % 8;
+            let mask = (1u16 << bit_depth) - 1;
+
+            (line[bit / 8] as u16 >> shift) & mask
+        }
+    }
+}
+
+/// Scales a sample to 8 bits; 16-bit samples lose their low byte.
+fn sample_to_u8(value: u16, bit_depth: u8) -> u8 {
+    match bit_depth {
+        16 => (value >> 8) as u8,
+        _ => (value as u32 * 255 / ((1u32 << bit_depth) - 1)) as u8,
+    }
+}
+
+fn inflate(data: &[u8], expected_size: usize) -> Result<Vec<u8>, FormatError> {
+    let bytes = miniz_oxide::inflate::decompress_to_vec_zlib_with_limit(data, expected_size)
+        .map_err(|status| match status {
+            TINFLStatus::FailedCannotMakeProgress => FormatError::Truncated,
+            TINFLStatus::HasMoreOutput => {
+                FormatError::InvalidData("PNG image data is too long".to_string())
+            }
+            _ => FormatError::InvalidData(format!("PNG image data: {status:?}")),
+        })?;
+
+    if bytes.len() < expected_size {
+        return Err(FormatError::Truncated);
+    }
+
+    Ok(bytes)
+}
+
+/// Pixel types that can be written to PNG without conversion.
+pub trait PngPixel: Copy {
+    const COLOR_TYPE: PngColorType;
+
+    fn push_samples(self, out: &mut Vec<u8>);
+}
+
+impl PngPixel for u8_gray {
+    const COLOR_TYPE: PngColorType = PngColorType::Grayscale;
+
+    fn push_samples(self, out: &mut Vec<u8>) {
+        out.push(self.0);
+    }
+}
+
+impl PngPixel for u8_rgb {
+    const COLOR_TYPE: PngColorType = PngColorType::TrueColor;
+
+    fn push_samples(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[self.0, self.1, self.2]);
+    }
+}
+
+impl PngPixel for u8_rgba {
+    const COLOR_TYPE: PngColorType = PngColorType::TrueColorAlpha;
+
+    fn push_samples(self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[self.0, self.1, self.2, self.3]);
+    }
+}
+
+impl<P: Copy + From<u8_rgba>> Image<P> {
+    /// Reads a non-interlaced PNG of any color type and bit depth.
+    /// 16-bit samples are truncated to 8 bits; transparency (tRNS) becomes alpha.
+    pub fn read_png_from<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let mut signature = [0u8; 8];
+        reader.read_exact(&mut signature)?;
+
+        if &signature != SIGNATURE {
+            return Err(FormatError::InvalidHeader(
+                "PNG signature not found".to_string(),
+            ));
+        }
+
+        let chunk = PngChunk::read_from(reader)?;
+        if &chunk.kind != b"IHDR" {
+            return Err(FormatError::InvalidHeader(
+                "PNG does not start with an IHDR chunk".to_string(),
+            ));
+        }
+
+        let header = PngHeader::from_bytes(&chunk.data)?;
+
+        if header.is_interlaced {
+            return Err(FormatError::Unsupported("interlaced PNG".to_string()));
+        }
+
+        let image_size = (header.line_bytes() + 1)
+            .checked_mul(header.height as usize)
+            .ok_or_else(|| {
+                FormatError::OversizeDimension(format!(
+                    "PNG of {}x{} pixels",
+                    header.width, header.height
+                ))
+            })?;
+
+        let mut palette = Vec::<u8_rgb>::new();
+        let mut palette_alpha = Vec::<u8>::new();
+        let mut transparent_key = None::<[u16; 3]>;
+        let mut data = Vec::<u8>::new();
+
+        loop {
+            let chunk = PngChunk::read_from(reader)?;
+
+            match &chunk.kind {
+                b"PLTE" => {
+                    if chunk.data.len() % 3 != 0 || chunk.data.len() > 3 * 256 {
+                        return Err(FormatError::InvalidData(format!(
+                            "PNG palette of {} bytes",
+                            chunk.data.len()
+                        )));
+                    }
+
+                    palette = chunk
+                        .data
+                        .chunks_exact(3)
+                        .map(|c| u8_rgb(c[0], c[1], c[2]))
+                        .collect();
+                }
+
+                b"tRNS" => match header.color_type {
+                    PngColorType::Indexed => palette_alpha = chunk.data,
+
+                    PngColorType::Grayscale if chunk.data.len() == 2 => {
+                        let y = u16::from_be_bytes([chunk.data[0], chunk.data[1]]);
+                        transparent_key = Some([y, y, y]);
+                    }
+
+                    PngColorType::TrueColor if chunk.data.len() == 6 => {
+                        let mut key = [0u16; 3];
+                        for (k, c) in key.iter_mut().zip(chunk.data.chunks_exact(2)) {
+                            *k = u16::from_be_bytes([c[0], c[1]]);
+                        }
+                        transparent_key = Some(key);
+                    }
+
+                    _ => {
+                        return Err(FormatError::InvalidData(format!(
+                            "PNG tRNS chunk of {} bytes for {:?}",
+                            chunk.data.len(),
+                            header.color_type
+                        )))
+                    }
+                },
+
+                b"IDAT" => data.extend_from_slice(&chunk.data),
+                b"IEND" => break,
+
+                _ if chunk.is_critical() => {
+                    return Err(FormatError::Unsupported(format!(
+                        "PNG chunk {}",
+                        String::from_utf8_lossy(&chunk.kind)
+                    )))
+                }
+
+                _ => {}
+            }
+        }
+
+        if header.color_type == PngColorType::Indexed && palette.is_empty() {
+            return Err(FormatError::InvalidData(
+                "PNG palette is missing".to_string(),
+            ));
+        }
+
+        let width = header.width as usize;
+        let height = header.height as usize;
+        let line_bytes = header.line_bytes();
+        let bpp = header.bits_per_pixel().div_ceil(8);
+
+        let mut bytes = inflate(&data, image_size)?;
+
+        let mut prev = vec![0u8; line_bytes];
+        for line in bytes.chunks_exact_mut(line_bytes + 1) {
+            let (filter, line) = line.split_first_mut().unwrap();
+            unfilter(*filter, line, &prev, bpp)?;
+            prev.copy_from_slice(line);
+        }
+
+        let channels = header.color_type.channels();
+        let depth = header.bit_depth;
+
+        let mut pixels = Vec::with_capacity(width * height);
+
+        for line in bytes.chunks_exact(line_bytes + 1) {
+            let line = &line[1..];
+
+            for x in 0..width {
+                let sample = |i: usize| read_sample(line, x * channels + i, depth);
+
+                let (color, a) = match header.color_type {
+                    PngColorType::Indexed => {
+                        let index = sample(0) as usize;
+                        let color = *palette.get(index).ok_or_else(|| {
+                            FormatError::InvalidData(format!("PNG palette index {index}"))
+                        })?;
+
+                        (color, palette_alpha.get(index).copied().unwrap_or(255))
+                    }
+
+                    PngColorType::Grayscale | PngColorType::GrayscaleAlpha => {
+                        let y = sample(0);
+                        let a = match header.color_type {
+                            PngColorType::GrayscaleAlpha => sample_to_u8(sample(1), depth),
+                            _ if transparent_key == Some([y, y, y]) => 0,
+                            _ => 255,
+                        };
+
+                        let y = sample_to_u8(y, depth);
+                        (u8_rgb(y, y, y), a)
+                    }
+
+                    PngColorType::TrueColor | PngColorType::TrueColorAlpha => {
+                        let rgb = [sample(0), sample(1), sample(2)];
+                        let a = match header.color_type {
+                            PngColorType::TrueColorAlpha => sample_to_u8(sample(3), depth),
+                            _ if transparent_key == Some(rgb) => 0,
+                            _ => 255,
+                        };
+
+                        let [r, g, b] = rgb.map(|c| sample_to_u8(c, depth));
+                        (u8_rgb(r, g, b), a)
+                    }
+                };
+
+                pixels.push(P::from(u8_rgba(color.0, color.1, color.2, a)));
+            }
+        }
+
+        Ok(Image::from_pixels(width, height, pixels))
+    }
+}
+
+impl<P: PngPixel> Image<P> {
+    /// Writes 8-bit samples, with the color type chosen by the pixel type.
+    /// Both dimensions must be from 1 to 2^31 - 1, as in the reader,
+    /// and the compressed samples must fit in one chunk of at most 2^31 - 1 bytes.
+    pub fn write_png_to<W: Write>(&self, writer: &mut W) -> Result<(), FormatError> {
+        let (width, height) = (self.width(), self.height());
+
+        if width > i32::MAX as usize || height > i32::MAX as usize {
+            return Err(FormatError::OversizeDimension(format!(
+                "PNG of {width}x{height} pixels"
+            )));
+        }
+
+        if width == 0 || height == 0 {
+            return Err(FormatError::InvalidData(format!(
+                "PNG of {width}x{height} pixels"
+            )));
+        }
+
+        let color_type = P::COLOR_TYPE;
+
+        let header = PngHeader {
+            width: width as u32,
+            height: height as u32,
+            bit_depth: 8,
+            color_type,
+            is_interlaced: false,
+        };
+
+        let bpp = color_type.channels();
+        let line_bytes = header.line_bytes();
+
+        let mut bytes = Vec::with_capacity((line_bytes + 1) * self.height());
+        let mut line = Vec::with_capacity(line_bytes);
+        let mut prev = vec![0u8; line_bytes];
+
+        for row in self.rows() {
+            line.clear();
+
+            for &pixel in row {
+                pixel.push_samples(&mut line);
+            }
+
+            filter_best(&line, &prev, bpp, &mut bytes);
+            std::mem::swap(&mut line, &mut prev);
+        }
+
+        let data = miniz_oxide::deflate::compress_to_vec_zlib(&bytes, 6);
+
+        let mut out = Vec::with_capacity(data.len() + 64);
+        out.extend_from_slice(SIGNATURE);
+        PngChunk::new(b"IHDR", header.to_bytes()).write_to(&mut out)?;
+        PngChunk::new(b"IDAT", data).write_to(&mut out)?;
+        PngChunk::new(b"IEND", vec![]).write_to(&mut out)?;
+
+        writer.write_all(&out)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,83 @@
+use crate::formats::FormatError;
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Predicts a byte from its left (`a`), upper (`b`) and upper-left (`c`) neighbours.
+fn predict(filter: u8, a: u8, b: u8, c: u8) -> u8 {
+    match filter {
+        0 => 0,
+        1 => a,
+        2 => b,
+        3 => ((a as u16 + b as u16) / 2) as u8,
+        4 => paeth(a, b, c),
+
+        _ => unreachable!(),
+    }
+}
+
+/// Reverses filtering of `line` in place; `prev` is the already unfiltered previous line,
+/// or zeros for the first one. `bpp` is the number of bytes per pixel, at least 1.
+pub(super) fn unfilter(
+    filter: u8,
+    line: &mut [u8],
+    prev: &[u8],
+    bpp: usize,
+) -> Result<(), FormatError> {
+    if filter > 4 {
+        return Err(FormatError::InvalidData(format!(
+            "invalid PNG filter type {filter}"
+        )));
+    }
+
+    for i in 0..line.len() {
+        let a = if i >= bpp { line[i - bpp] } else { 0 };
+        let c = if i >= bpp { prev[i - bpp] } else { 0 };
+
+        line[i] = line[i].wrapping_add(predict(filter, a, prev[i], c));
+    }
+
+    Ok(())
+}
+
+/// Appends the filter type byte and the filtered `line`, choosing the filter
+/// with the smallest sum of absolute differences, as suggested by the PNG spec.
+pub(super) fn filter_best(line: &[u8], prev: &[u8], bpp: usize, out: &mut Vec<u8>) {
+    let mut best_filter = 0;
+    let mut best_bytes = Vec::new();
+    let mut best_cost = u64::MAX;
+
+    for filter in 0..=4 {
+        let bytes: Vec<u8> = (0..line.len())
+            .map(|i| {
+                let a = if i >= bpp { line[i - bpp] } else { 0 };
+                let c = if i >= bpp { prev[i - bpp] } else { 0 };
+
+                line[i].wrapping_sub(predict(filter, a, prev[i], c))
+            })
+            .collect();
+
+        let cost = bytes.iter().map(|&b| (b as i8).unsigned_abs() as u64).sum();
+
+        if cost < best_cost {
+            best_filter = filter;
+            best_bytes = bytes;
+            best_cost = cost;
+        }
+    }
+
+    out.push(best_filter);
+    out.extend_from_slice(&best_bytes);
+}
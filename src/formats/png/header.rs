@@ -0,0 +1,133 @@
+use byteorder::{ReadBytesExt, WriteBytesExt, BE};
+
+use crate::formats::FormatError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PngColorType {
+    Grayscale = 0,
+    TrueColor = 2,
+    Indexed = 3,
+    GrayscaleAlpha = 4,
+    TrueColorAlpha = 6,
+}
+
+impl TryFrom<u8> for PngColorType {
+    type Error = FormatError;
+
+    fn try_from(value: u8) -> Result<Self, FormatError> {
+        match value {
+            0 => Ok(Self::Grayscale),
+            2 => Ok(Self::TrueColor),
+            3 => Ok(Self::Indexed),
+            4 => Ok(Self::GrayscaleAlpha),
+            6 => Ok(Self::TrueColorAlpha),
+            _ => Err(FormatError::InvalidHeader(format!(
+                "invalid PNG color type {value}"
+            ))),
+        }
+    }
+}
+
+impl PngColorType {
+    pub fn channels(&self) -> usize {
+        match self {
+            Self::Grayscale | Self::Indexed => 1,
+            Self::GrayscaleAlpha => 2,
+            Self::TrueColor => 3,
+            Self::TrueColorAlpha => 4,
+        }
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        matches!(self, Self::GrayscaleAlpha | Self::TrueColorAlpha)
+    }
+}
+
+/// Contents of the IHDR chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PngHeader {
+    pub width: u32,
+    pub height: u32,
+
+    /// bits per sample, or per palette index
+    pub bit_depth: u8,
+
+    pub color_type: PngColorType,
+    pub is_interlaced: bool,
+}
+
+impl PngHeader {
+    pub(super) fn from_bytes(mut bytes: &[u8]) -> Result<Self, FormatError> {
+        if bytes.len() != 13 {
+            return Err(FormatError::InvalidHeader(format!(
+                "PNG IHDR chunk of {} bytes",
+                bytes.len()
+            )));
+        }
+
+        let width = bytes.read_u32::<BE>()?;
+        let height = bytes.read_u32::<BE>()?;
+        let bit_depth = bytes.read_u8()?;
+        let color_type = PngColorType::try_from(bytes.read_u8()?)?;
+        let compression_method = bytes.read_u8()?;
+        let filter_method = bytes.read_u8()?;
+        let interlace_method = bytes.read_u8()?;
+
+        if width == 0 || height == 0 || width > i32::MAX as u32 || height > i32::MAX as u32 {
+            return Err(FormatError::InvalidHeader(format!(
+                "PNG dimensions {width}x{height}"
+            )));
+        }
+
+        let is_valid_depth = match color_type {
+            PngColorType::Grayscale => matches!(bit_depth, 1 | 2 | 4 | 8 | 16),
+            PngColorType::Indexed => matches!(bit_depth, 1 | 2 | 4 | 8),
+            _ => matches!(bit_depth, 8 | 16),
+        };
+
+        if !is_valid_depth {
+            return Err(FormatError::InvalidHeader(format!(
+                "PNG color type {color_type:?} with bit depth {bit_depth}"
+            )));
+        }
+
+        if compression_method != 0 || filter_method != 0 || interlace_method > 1 {
+            return Err(FormatError::InvalidHeader(format!(
+                "PNG compression, filter or interlace method \
+                 {compression_method}, {filter_method}, {interlace_method}"
+            )));
+        }
+
+        Ok(Self {
+            width,
+            height,
+            bit_depth,
+            color_type,
+            is_interlaced: interlace_method == 1,
+        })
+    }
+
+    pub(super) fn to_bytes(self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(13);
+
+        bytes.write_u32::<BE>(self.width).unwrap();
+        bytes.write_u32::<BE>(self.height).unwrap();
+        bytes.push(self.bit_depth);
+        bytes.push(self.color_type as u8);
+        bytes.push(0); // deflate
+        bytes.push(0); // adaptive filtering
+        bytes.push(self.is_interlaced as u8);
+
+        bytes
+    }
+
+    pub fn bits_per_pixel(&self) -> usize {
+        self.color_type.channels() * self.bit_depth as usize
+    }
+
+    /// Size of a scanline without the filter type byte.
+    pub fn line_bytes(&self) -> usize {
+        (self.width as usize * self.bits_per_pixel()).div_ceil(8)
+    }
+}
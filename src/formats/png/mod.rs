@@ -0,0 +1,7 @@
+mod chunk;
+mod codec;
+mod filter;
+mod header;
+
+pub use codec::*;
+pub use header::*;
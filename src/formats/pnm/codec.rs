@@ -0,0 +1,185 @@
+use std::io::{Read, Write};
+
+use crate::formats::FormatError;
+use crate::image::*;
+
+use super::*;
+
+/// Netpbm recommends keeping lines of ASCII files within 70 characters.
+const MAX_ASCII_LINE: usize = 70;
+
+fn read_integer_samples(
+    tokens: &mut Tokenizer,
+    header: &PnmHeader,
+) -> Result<Vec<u16>, FormatError> {
+    let count = header.sample_count()?;
+
+    if header.format.is_ascii() {
+        // each sample takes at least two bytes, so this bounds the allocation
+        let mut samples = Vec::with_capacity(count.min(tokens.bytes.len() / 2));
+
+        for _ in 0..count {
+            let value = tokens.next_number::<u32>()?;
+            if value > header.max_value as u32 {
+                return Err(FormatError::InvalidData(format!(
+                    "Netpbm sample {value} is above the maximum {}",
+                    header.max_value
+                )));
+            }
+
+            samples.push(value as u16);
+        }
+
+        return Ok(samples);
+    }
+
+    let data = tokens.binary_data();
+    let sample_size = if header.max_value > 255 { 2 } else { 1 };
+
+    if data.len() / sample_size < count {
+        return Err(FormatError::Truncated);
+    }
+
+    let samples = data[..count * sample_size]
+        .chunks_exact(sample_size)
+        .map(|b| match b {
+            [x] => *x as u16,
+            [hi, lo] => u16::from_be_bytes([*hi, *lo]),
+            _ => unreachable!(),
+        })
+        .collect();
+
+    Ok(samples)
+}
+
+fn write_integer_samples<W: Write>(
+    writer: &mut W,
+    header: &PnmHeader,
+    samples: &[u16],
+) -> Result<(), FormatError> {
+    let mut out = header.to_text().into_bytes();
+
+    if header.format.is_ascii() {
+        let mut line = String::new();
+
+        for sample in samples {
+            let token = sample.to_string();
+
+            if !line.is_empty() && line.len() + 1 + token.len() > MAX_ASCII_LINE {
+                out.extend_from_slice(line.as_bytes());
+                out.push(b'\n');
+                line.clear();
+            }
+
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+
+        out.extend_from_slice(line.as_bytes());
+        out.push(b'\n');
+    } else if header.max_value > 255 {
+        for sample in samples {
+            out.extend_from_slice(&sample.to_be_bytes());
+        }
+    } else {
+        out.extend(samples.iter().map(|&x| x as u8));
+    }
+
+    writer.write_all(&out)?;
+
+    Ok(())
+}
+
+fn scale_to_u8(value: u16, max_value: u16) -> u8 {
+    ((value as u32 * 255 + max_value as u32 / 2) / max_value as u32) as u8
+}
+
+/// Pixel types that can be written to PGM or PPM without conversion.
+pub trait PnmPixel: Copy {
+    const CHANNELS: usize;
+
+    fn push_samples(self, out: &mut Vec<u16>);
+}
+
+impl PnmPixel for u8_gray {
+    const CHANNELS: usize = 1;
+
+    fn push_samples(self, out: &mut Vec<u16>) {
+        out.push(self.0 as u16);
+    }
+}
+
+impl PnmPixel for u8_rgb {
+    const CHANNELS: usize = 3;
+
+    fn push_samples(self, out: &mut Vec<u16>) {
+        out.extend_from_slice(&[self.0 as u16, self.1 as u16, self.2 as u16]);
+    }
+}
+
+impl<P: Copy + From<u8_rgb>> Image<P> {
+    /// Reads a PGM or PPM image, either ASCII or binary, with any maximum value.
+    pub fn read_pnm_from<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        let mut tokens = Tokenizer::new(&bytes);
+        let header = PnmHeader::parse(&mut tokens)?;
+
+        if header.format.is_float() {
+            return Err(FormatError::Unsupported(
+                "PFM into an 8-bit image, use read_pfm_from".to_string(),
+            ));
+        }
+
+        let samples = read_integer_samples(&mut tokens, &header)?;
+        let samples: Vec<u8> = samples
+            .iter()
+            .map(|&x| scale_to_u8(x, header.max_value))
+            .collect();
+
+        let pixels: Vec<P> = match header.format.channels() {
+            1 => samples.iter().map(|&y| P::from(u8_rgb(y, y, y))).collect(),
+            _ => samples
+                .chunks_exact(3)
+                .map(|c| P::from(u8_rgb(c[0], c[1], c[2])))
+                .collect(),
+        };
+
+        Ok(Image::from_pixels(header.width, header.height, pixels))
+    }
+}
+
+impl<P: PnmPixel> Image<P> {
+    /// Writes PGM or PPM, as chosen by the pixel type, with a maximum value of 255.
+    pub fn write_pnm_to<W: Write>(
+        &self,
+        writer: &mut W,
+        is_ascii: bool,
+    ) -> Result<(), FormatError> {
+        let format = match (P::CHANNELS, is_ascii) {
+            (1, true) => PnmFormat::PgmAscii,
+            (1, false) => PnmFormat::PgmBinary,
+            (_, true) => PnmFormat::PpmAscii,
+            (_, false) => PnmFormat::PpmBinary,
+        };
+
+        let header = PnmHeader {
+            format,
+            width: self.width(),
+            height: self.height(),
+            max_value: 255,
+            scale: 1.0,
+            is_little_endian: false,
+        };
+
+        let mut samples = Vec::with_capacity(header.sample_count()?);
+        for &pixel in self.pixels() {
+            pixel.push_samples(&mut samples);
+        }
+
+        write_integer_samples(writer, &header, &samples)
+    }
+}
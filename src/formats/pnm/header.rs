@@ -0,0 +1,207 @@
+use crate::formats::FormatError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PnmFormat {
+    PgmAscii,
+    PpmAscii,
+    PgmBinary,
+    PpmBinary,
+    PfmGray,
+    PfmColor,
+}
+
+impl PnmFormat {
+    pub fn from_magic(magic: &[u8]) -> Result<Self, FormatError> {
+        match magic {
+            b"P2" => Ok(Self::PgmAscii),
+            b"P3" => Ok(Self::PpmAscii),
+            b"P5" => Ok(Self::PgmBinary),
+            b"P6" => Ok(Self::PpmBinary),
+            b"Pf" => Ok(Self::PfmGray),
+            b"PF" => Ok(Self::PfmColor),
+            _ => Err(FormatError::InvalidHeader(format!(
+                "unsupported Netpbm magic {:?}",
+                String::from_utf8_lossy(magic)
+            ))),
+        }
+    }
+
+    pub fn magic(&self) -> &'static str {
+        match self {
+            Self::PgmAscii => "P2",
+            Self::PpmAscii => "P3",
+            Self::PgmBinary => "P5",
+            Self::PpmBinary => "P6",
+            Self::PfmGray => "Pf",
+            Self::PfmColor => "PF",
+        }
+    }
+
+    pub fn channels(&self) -> usize {
+        match self {
+            Self::PgmAscii | Self::PgmBinary | Self::PfmGray => 1,
+            Self::PpmAscii | Self::PpmBinary | Self::PfmColor => 3,
+        }
+    }
+
+    pub fn is_ascii(&self) -> bool {
+        matches!(self, Self::PgmAscii | Self::PpmAscii)
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, Self::PfmGray | Self::PfmColor)
+    }
+}
+
+/// Splits the text parts of a Netpbm file into tokens, skipping `#` comments.
+pub(super) struct Tokenizer<'a> {
+    pub bytes: &'a [u8],
+    pub pos: usize,
+}
+
+impl<'a> Tokenizer<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while self.pos < self.bytes.len() {
+            match self.bytes[self.pos] {
+                b'#' => {
+                    while self.pos < self.bytes.len() && self.bytes[self.pos] != b'\n' {
+                        self.pos += 1;
+                    }
+                }
+
+                b if b.is_ascii_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+    }
+
+    pub fn next_token(&mut self) -> Result<&'a str, FormatError> {
+        self.skip_whitespace_and_comments();
+
+        let start = self.pos;
+        while self.pos < self.bytes.len() && !self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
+        }
+
+        if start == self.pos {
+            return Err(FormatError::Truncated);
+        }
+
+        std::str::from_utf8(&self.bytes[start..self.pos])
+            .map_err(|_| FormatError::InvalidData("Netpbm token is not ASCII".to_string()))
+    }
+
+    pub fn next_number<T: std::str::FromStr>(&mut self) -> Result<T, FormatError> {
+        let token = self.next_token()?;
+
+        token.parse().map_err(|_| {
+            FormatError::InvalidData(format!("expected a number in Netpbm, got {token:?}"))
+        })
+    }
+
+    /// Binary data starts after exactly one whitespace byte following the header.
+    pub fn binary_data(&self) -> &'a [u8] {
+        let start = (self.pos + 1).min(self.bytes.len());
+        &self.bytes[start..]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PnmHeader {
+    pub format: PnmFormat,
+    pub width: usize,
+    pub height: usize,
+
+    /// largest integer sample value; unused for PFM
+    pub max_value: u16,
+
+    /// absolute value of the PFM scale factor; unused for integer formats
+    pub scale: f32,
+
+    /// byte order of PFM samples
+    pub is_little_endian: bool,
+}
+
+impl PnmHeader {
+    pub(super) fn parse(tokens: &mut Tokenizer) -> Result<Self, FormatError> {
+        let magic = tokens.next_token()?;
+        let format = PnmFormat::from_magic(magic.as_bytes())?;
+
+        let width = tokens.next_number::<usize>()?;
+        let height = tokens.next_number::<usize>()?;
+
+        let mut header = Self {
+            format,
+            width,
+            height,
+            max_value: 255,
+            scale: 1.0,
+            is_little_endian: false,
+        };
+
+        if format.is_float() {
+            let scale = tokens.next_number::<f32>()?;
+            if scale == 0.0 || !scale.is_finite() {
+                return Err(FormatError::InvalidHeader(format!(
+                    "PFM scale factor {scale}"
+                )));
+            }
+
+            header.scale = scale.abs();
+            header.is_little_endian = scale < 0.0;
+        } else {
+            let max_value = tokens.next_number::<u32>()?;
+            if max_value == 0 || max_value > u16::MAX as u32 {
+                return Err(FormatError::InvalidHeader(format!(
+                    "Netpbm maximum value {max_value}"
+                )));
+            }
+
+            header.max_value = max_value as u16;
+        }
+
+        Ok(header)
+    }
+
+    pub(super) fn to_text(self) -> String {
+        if self.format.is_float() {
+            let scale = if self.is_little_endian {
+                -self.scale
+            } else {
+                self.scale
+            };
+
+            format!(
+                "{}\n{} {}\n{:?}\n",
+                self.format.magic(),
+                self.width,
+                self.height,
+                scale
+            )
+        } else {
+            format!(
+                "{}\n{} {}\n{}\n",
+                self.format.magic(),
+                self.width,
+                self.height,
+                self.max_value
+            )
+        }
+    }
+
+    pub fn sample_count(&self) -> Result<usize, FormatError> {
+        self.width
+            .checked_mul(self.height)
+            .and_then(|n| n.checked_mul(self.format.channels()))
+            .ok_or_else(|| {
+                FormatError::OversizeDimension(format!(
+                    "Netpbm of {}x{} pixels",
+                    self.width, self.height
+                ))
+            })
+    }
+}
@@ -0,0 +1,6 @@
+mod codec;
+mod header;
+mod pfm;
+
+pub use codec::*;
+pub use header::*;
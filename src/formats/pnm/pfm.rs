@@ -0,0 +1,86 @@
+use std::io::{Read, Write};
+
+use crate::formats::FormatError;
+use crate::image::*;
+
+use super::*;
+
+impl Image<f32_rgb> {
+    /// Reads a color or grayscale PFM; grayscale is expanded to RGB.
+    /// The scale factor is not applied, as most writers leave it at 1.
+    pub fn read_pfm_from<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        let mut tokens = Tokenizer::new(&bytes);
+        let header = PnmHeader::parse(&mut tokens)?;
+
+        if !header.format.is_float() {
+            return Err(FormatError::Unsupported(format!(
+                "{:?} into a float image",
+                header.format
+            )));
+        }
+
+        let count = header.sample_count()?;
+        let data = tokens.binary_data();
+
+        if data.len() / 4 < count {
+            return Err(FormatError::Truncated);
+        }
+
+        let samples: Vec<f32> = data[..count * 4]
+            .chunks_exact(4)
+            .map(|b| {
+                let b = [b[0], b[1], b[2], b[3]];
+
+                if header.is_little_endian {
+                    f32::from_le_bytes(b)
+                } else {
+                    f32::from_be_bytes(b)
+                }
+            })
+            .collect();
+
+        let pixels: Vec<f32_rgb> = match header.format.channels() {
+            1 => samples.iter().map(|&y| f32_rgb(y, y, y)).collect(),
+            _ => samples
+                .chunks_exact(3)
+                .map(|c| f32_rgb(c[0], c[1], c[2]))
+                .collect(),
+        };
+
+        // PFM rows go from bottom to top
+        let rows = pixels.chunks_exact(header.width.max(1)).rev();
+        let pixels: Vec<f32_rgb> = rows.flatten().copied().collect();
+
+        Ok(Image::from_pixels(header.width, header.height, pixels))
+    }
+
+    /// Writes a little-endian color PFM.
+    pub fn write_pfm_to<W: Write>(&self, writer: &mut W) -> Result<(), FormatError> {
+        let header = PnmHeader {
+            format: PnmFormat::PfmColor,
+            width: self.width(),
+            height: self.height(),
+            max_value: 0,
+            scale: 1.0,
+            is_little_endian: true,
+        };
+
+        let mut out = header.to_text().into_bytes();
+        out.reserve(header.sample_count()? * 4);
+
+        for row in self.rows().rev() {
+            for pixel in row {
+                for sample in [pixel.0, pixel.1, pixel.2] {
+                    out.extend_from_slice(&sample.to_le_bytes());
+                }
+            }
+        }
+
+        writer.write_all(&out)?;
+
+        Ok(())
+    }
+}
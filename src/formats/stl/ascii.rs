@@ -0,0 +1,184 @@
+use crate::formats::FormatError;
+use crate::math::*;
+
+use std::io::{Read, Write};
+
+use super::*;
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    fn is_at_end(&mut self) -> bool {
+        self.skip_whitespace();
+        self.pos == self.text.len()
+    }
+
+    fn next_token(&mut self) -> Result<&'a str, FormatError> {
+        self.skip_whitespace();
+
+        let rest = &self.text[self.pos..];
+        let len = rest
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(rest.len());
+
+        if len == 0 {
+            return Err(FormatError::Truncated);
+        }
+
+        self.pos += len;
+        Ok(&rest[..len])
+    }
+
+    fn rest_of_line(&mut self) -> &'a str {
+        let rest = &self.text[self.pos..];
+        let len = rest.find('\n').unwrap_or(rest.len());
+
+        self.pos += len;
+        rest[..len].trim()
+    }
+
+    fn expect(&mut self, keyword: &str) -> Result<(), FormatError> {
+        let token = self.next_token()?;
+
+        if token != keyword {
+            return Err(FormatError::InvalidData(format!(
+                "expected {keyword:?} in ASCII STL, got {token:?}"
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn read_ff32_3(&mut self) -> Result<ff32_3, FormatError> {
+        let mut coords = [ff32(0.0); 3];
+
+        for coord in coords.iter_mut() {
+            let token = self.next_token()?;
+            *coord = ff32(token.parse().map_err(|_| {
+                FormatError::InvalidData(format!("expected a number in ASCII STL, got {token:?}"))
+            })?);
+        }
+
+        Ok(ff32_3::new(coords[0], coords[1], coords[2]))
+    }
+
+    fn read_facet(&mut self) -> Result<StlTriangle, FormatError> {
+        self.expect("normal")?;
+        let n = self.read_ff32_3()?;
+
+        self.expect("outer")?;
+        self.expect("loop")?;
+
+        self.expect("vertex")?;
+        let a = self.read_ff32_3()?;
+        self.expect("vertex")?;
+        let b = self.read_ff32_3()?;
+        self.expect("vertex")?;
+        let c = self.read_ff32_3()?;
+
+        self.expect("endloop")?;
+        self.expect("endfacet")?;
+
+        Ok(StlTriangle {
+            n,
+            a,
+            b,
+            c,
+            attr: 0,
+        })
+    }
+}
+
+impl StlModel {
+    /// Multiple concatenated solids are merged into one model,
+    /// with the header taken from the first solid's name.
+    pub fn read_ascii_from<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let mut bytes = Vec::<u8>::new();
+        reader.read_to_end(&mut bytes)?;
+
+        let text = String::from_utf8(bytes)
+            .map_err(|_| FormatError::InvalidData("ASCII STL is not UTF-8".to_string()))?;
+
+        let mut parser = Parser {
+            text: &text,
+            pos: 0,
+        };
+
+        let mut header: Option<String> = None;
+        let mut triangles = Vec::<StlTriangle>::new();
+
+        while header.is_none() || !parser.is_at_end() {
+            parser.expect("solid")?;
+            let name = parser.rest_of_line();
+            header.get_or_insert_with(|| name.to_string());
+
+            loop {
+                match parser.next_token()? {
+                    "facet" => triangles.push(parser.read_facet()?),
+
+                    "endsolid" => {
+                        parser.rest_of_line();
+                        break;
+                    }
+
+                    token => {
+                        return Err(FormatError::InvalidData(format!(
+                            "expected \"facet\" or \"endsolid\" in ASCII STL, got {token:?}"
+                        )))
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            header: header.unwrap_or_default(),
+            triangles,
+        })
+    }
+
+    /// The header is cut at the first NUL or line break to form the solid name.
+    pub fn write_ascii_to<W: Write>(&self, writer: &mut W) -> Result<(), FormatError> {
+        let name = self
+            .header
+            .split(['\0', '\r', '\n'])
+            .next()
+            .unwrap_or_default()
+            .trim();
+
+        writeln!(writer, "solid {name}")?;
+
+        for tri in self.triangles.iter() {
+            let StlTriangle { n, a, b, c, .. } = tri;
+
+            writeln!(
+                writer,
+                "  facet normal {:e} {:e} {:e}",
+                n.0 .0, n.1 .0, n.2 .0
+            )?;
+            writeln!(writer, "    outer loop")?;
+
+            for v in [a, b, c] {
+                writeln!(
+                    writer,
+                    "      vertex {:e} {:e} {:e}",
+                    v.0 .0, v.1 .0, v.2 .0
+                )?;
+            }
+
+            writeln!(writer, "    endloop")?;
+            writeln!(writer, "  endfacet")?;
+        }
+
+        writeln!(writer, "endsolid {name}")?;
+
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+mod ascii;
 mod model;
 mod triangle;
 
@@ -1,8 +1,8 @@
 use crate::cast;
+use crate::formats::FormatError;
 use crate::math::*;
 
-use std::io;
-use std::io::{Read, Write};
+use std::io::{Cursor, Read, Write};
 
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 
@@ -14,14 +14,57 @@ pub struct StlModel {
     pub triangles: Vec<StlTriangle>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StlFormat {
+    Binary,
+    Ascii,
+}
+
+impl StlFormat {
+    /// Binary files are allowed to have a header starting with "solid",
+    /// so we first check whether the size matches the binary triangle count.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.len() >= 84 {
+            let triangle_count = u32::from_le_bytes(bytes[80..84].try_into().unwrap());
+            let binary_size = 84 + 50 * (triangle_count as u64);
+
+            if binary_size == bytes.len() as u64 {
+                return Self::Binary;
+            }
+        }
+
+        let first_non_space = bytes.iter().position(|b| !b.is_ascii_whitespace());
+
+        match first_non_space {
+            Some(i) if bytes[i..].starts_with(b"solid") => Self::Ascii,
+            _ => Self::Binary,
+        }
+    }
+}
+
 impl StlModel {
-    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, io::Error> {
-        let mut header = String::with_capacity(80);
-        reader.by_ref().take(80).read_to_string(&mut header)?;
+    /// Reads either a binary or an ASCII file; see `StlFormat::detect`.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        match StlFormat::detect(&bytes) {
+            StlFormat::Binary => Self::read_binary_from(&mut Cursor::new(bytes)),
+            StlFormat::Ascii => Self::read_ascii_from(&mut Cursor::new(bytes)),
+        }
+    }
+
+    pub fn read_binary_from<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let mut header = [0u8; 80];
+        reader.read_exact(&mut header)?;
+        let header = String::from_utf8(header.to_vec())
+            .map_err(|_| FormatError::InvalidHeader("STL header is not UTF-8".to_string()))?;
 
         let triangle_count = reader.read_u32::<LE>()?;
 
-        let mut triangles = Vec::<StlTriangle>::with_capacity(triangle_count as usize);
+        // do not trust the count with a huge allocation before any triangle is read
+        let capacity = (triangle_count as usize).min(1 << 20);
+        let mut triangles = Vec::<StlTriangle>::with_capacity(capacity);
 
         for _ in 0..triangle_count {
             let triangle = StlTriangle::read_from(reader)?;
@@ -31,13 +74,22 @@ impl StlModel {
         Ok(Self { header, triangles })
     }
 
-    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), FormatError> {
+        if self.header.len() > 80 {
+            return Err(FormatError::OversizeDimension(format!(
+                "STL header of {} bytes",
+                self.header.len()
+            )));
+        }
+
+        let triangle_count = u32::try_from(self.triangles.len()).map_err(|_| {
+            FormatError::OversizeDimension(format!("STL of {} triangles", self.triangles.len()))
+        })?;
+
         let mut header_bytes = self.header.as_bytes().to_vec();
         header_bytes.resize(80, 0);
         writer.write_all(&header_bytes)?;
 
-        // TODO: handle possible overflow?
-        let triangle_count = u32::try_from(self.triangles.len()).unwrap();
         writer.write_u32::<LE>(triangle_count)?;
 
         for triangle in self.triangles.iter() {
@@ -47,11 +99,23 @@ impl StlModel {
         Ok(())
     }
 
+    pub fn write_to_format<W: Write>(
+        &self,
+        writer: &mut W,
+        format: StlFormat,
+    ) -> Result<(), FormatError> {
+        match format {
+            StlFormat::Binary => self.write_to(writer),
+            StlFormat::Ascii => self.write_ascii_to(writer),
+        }
+    }
+
+    /// Degenerate (zero-area) facets are skipped.
     pub fn to_triangle_list(&self) -> cast::TriangleList<ff32> {
         let triangles: Vec<cast::Triangle<ff32>> = self
             .triangles
             .iter()
-            .map(|tri| tri.to_cast_triangle())
+            .filter_map(|tri| tri.to_cast_triangle())
             .collect();
 
         cast::TriangleList::from(triangles)
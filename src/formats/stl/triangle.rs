@@ -56,30 +56,32 @@ impl StlTriangle {
         Ok(())
     }
 
-    pub fn to_cast_triangle(&self) -> cast::Triangle<ff32> {
-        let n1 = if self.n != ff32_3::ZERO {
-            self.n.norm()
+    /// `None` for degenerate triangles, with vertices on one line.
+    pub fn to_cast_triangle(&self) -> Option<cast::Triangle<ff32>> {
+        let n = if self.n != ff32_3::ZERO {
+            self.n
         } else {
-            ff32_3::cross(self.b - self.a, self.c - self.a).norm()
+            ff32_3::cross(self.b - self.a, self.c - self.a)
         };
 
-        cast::Triangle {
+        if n == ff32_3::ZERO {
+            return None;
+        }
+
+        let n1 = n.norm();
+
+        cast::Triangle::from_meta(cast::TriangleMeta {
             a: self.a,
-            m_abc: ff32_3x3::from_cols(self.b - self.a, self.c - self.a, n1)
-                .inv()
-                .unwrap(),
+            b: self.b,
+            c: self.c,
 
-            meta: Box::new(cast::TriangleMeta {
-                a: self.a,
-                b: self.b,
-                c: self.c,
+            // STL has no vertex normals
+            abc_nc: ff32_3x3::from_cols(n1, n1, n1),
 
-                /// STL has no vertex normals
-                abc_nc: ff32_3x3::from_cols(n1, n1, n1),
+            // STL has no UV mapping info
+            abc_uv: ff32_3x3::ONE,
 
-                /// STL has no UV mapping info
-                abc_uv: ff32_3x3::ONE,
-            }),
-        }
+            material: 0,
+        })
     }
 }
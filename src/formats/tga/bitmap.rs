@@ -1,14 +1,20 @@
-use std::io;
-use std::io::{Read, Write};
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
 
 use byteorder::{ReadBytesExt, WriteBytesExt, LE};
 
-#[derive(Debug, Copy, Clone, PartialEq)]
-#[allow(non_camel_case_types)]
-pub struct u8_rgb(pub u8, pub u8, pub u8);
+use crate::formats::FormatError;
+use crate::image::*;
 
-/// Only TGA 1.0 uncompressed 24-bit images are supported.
-/// Only top-to-bottom, left-to-right pixel order is supported.
+use super::codec::*;
+use super::extension::*;
+
+/// Supports uncompressed and RLE true-color (15/16/24/32-bit),
+/// grayscale (8-bit, or 16-bit with alpha) and color-mapped (8/16-bit index) images,
+/// as well as the TGA 2.0 extension area.
+///
+/// Pixels are always stored top-to-bottom, left-to-right;
+/// the file's own pixel order is remembered and used again on writing.
 pub struct TgaBitmap {
     color_map_type: ColorMapType,
     image_type: ImageType,
@@ -29,25 +35,38 @@ pub struct TgaBitmap {
 
     id: String,
     color_map_bytes: Vec<u8>,
-    pixels: Vec<u8_rgb>,
+    pixels: Image<u8_rgb>,
+
+    /// raw attribute values, even if `alpha_depth` is zero; 255 if the format has none
+    alpha: Vec<u8>,
+
+    /// whether to write the TGA 2.0 footer
+    is_tga2: bool,
+    extension: Option<TgaExtension>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum ColorMapType {
     None = 0,
     Present = 1,
 }
 
-impl From<u8> for ColorMapType {
-    fn from(value: u8) -> Self {
+impl TryFrom<u8> for ColorMapType {
+    type Error = FormatError;
+
+    fn try_from(value: u8) -> Result<Self, FormatError> {
         match value {
-            0 => Self::None,
-            1 => Self::Present,
-            _ => panic!("invalid color map type"),
+            0 => Ok(Self::None),
+            1 => Ok(Self::Present),
+            _ => Err(FormatError::InvalidHeader(format!(
+                "invalid TGA color map type {value}"
+            ))),
         }
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
 pub enum ImageType {
     None = 0,
@@ -59,21 +78,46 @@ pub enum ImageType {
     RleGrayscale = 11,
 }
 
-impl From<u8> for ImageType {
-    fn from(value: u8) -> Self {
+impl TryFrom<u8> for ImageType {
+    type Error = FormatError;
+
+    fn try_from(value: u8) -> Result<Self, FormatError> {
         match value {
-            0 => Self::None,
-            1 => Self::ColorMapped,
-            2 => Self::TrueColor,
-            3 => Self::Grayscale,
-            9 => Self::RleColorMapped,
-            10 => Self::RleTrueColor,
-            11 => Self::RleGrayscale,
-            _ => panic!("invalid image type"),
+            0 => Ok(Self::None),
+            1 => Ok(Self::ColorMapped),
+            2 => Ok(Self::TrueColor),
+            3 => Ok(Self::Grayscale),
+            9 => Ok(Self::RleColorMapped),
+            10 => Ok(Self::RleTrueColor),
+            11 => Ok(Self::RleGrayscale),
+            _ => Err(FormatError::InvalidHeader(format!(
+                "invalid TGA image type {value}"
+            ))),
         }
     }
 }
 
+impl ImageType {
+    pub fn is_rle(&self) -> bool {
+        matches!(
+            self,
+            Self::RleColorMapped | Self::RleTrueColor | Self::RleGrayscale
+        )
+    }
+
+    pub fn is_color_mapped(&self) -> bool {
+        matches!(self, Self::ColorMapped | Self::RleColorMapped)
+    }
+
+    pub fn is_true_color(&self) -> bool {
+        matches!(self, Self::TrueColor | Self::RleTrueColor)
+    }
+
+    pub fn is_grayscale(&self) -> bool {
+        matches!(self, Self::Grayscale | Self::RleGrayscale)
+    }
+}
+
 impl TgaBitmap {
     pub fn width(&self) -> usize {
         self.width as usize
@@ -84,42 +128,101 @@ impl TgaBitmap {
     }
 
     pub fn get(&self, pixel_x: usize, pixel_y: usize) -> u8_rgb {
-        self.pixels[pixel_y * self.width() + pixel_x]
+        self.pixels.get(pixel_x, pixel_y)
     }
 
     pub fn get_mut(&mut self, pixel_x: usize, pixel_y: usize) -> &mut u8_rgb {
+        self.pixels.get_mut(pixel_x, pixel_y)
+    }
+
+    pub fn get_alpha(&self, pixel_x: usize, pixel_y: usize) -> u8 {
+        self.alpha[pixel_y * self.width() + pixel_x]
+    }
+
+    pub fn get_alpha_mut(&mut self, pixel_x: usize, pixel_y: usize) -> &mut u8 {
         let width = self.width();
-        &mut self.pixels[pixel_y * width + pixel_x]
+        &mut self.alpha[pixel_y * width + pixel_x]
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        self.alpha_depth > 0
+    }
+
+    pub fn image_type(&self) -> ImageType {
+        self.image_type
+    }
+
+    pub fn pixel_depth(&self) -> u8 {
+        self.pixel_depth
+    }
+
+    pub fn alpha_depth(&self) -> u8 {
+        self.alpha_depth
+    }
+
+    pub fn is_top_to_bottom(&self) -> bool {
+        self.is_top_to_bottom
+    }
+
+    pub fn is_right_to_left(&self) -> bool {
+        self.is_right_to_left
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn extension(&self) -> Option<&TgaExtension> {
+        self.extension.as_ref()
+    }
+
+    pub fn image(&self) -> &Image<u8_rgb> {
+        &self.pixels
+    }
+
+    pub fn image_mut(&mut self) -> &mut Image<u8_rgb> {
+        &mut self.pixels
+    }
+
+    pub fn into_image(self) -> Image<u8_rgb> {
+        self.pixels
+    }
+
+    /// Alpha is 255 everywhere if the bitmap has no alpha channel.
+    pub fn to_rgba_image(&self) -> Image<u8_rgba> {
+        let has_alpha = self.has_alpha();
+
+        let colors = self.pixels.pixels().iter();
+        let pixels = colors.zip(self.alpha.iter()).map(|(c, &a)| {
+            let a = if has_alpha { a } else { 255 };
+            u8_rgba(c.0, c.1, c.2, a)
+        });
+
+        Image::from_pixels(self.width(), self.height(), pixels)
     }
 }
 
 impl TgaBitmap {
-    pub fn with_dimensions(width: usize, height: usize, fill: u8_rgb) -> Self {
-        TgaBitmap {
-            id: String::new(),
-            color_map_type: ColorMapType::None,
-            image_type: ImageType::TrueColor,
-            color_map_first_index: 0,
-            color_map_length: 0,
-            color_map_entry_size: 0,
-            origin_x: 0,
-            origin_y: 0,
-            width: width.try_into().expect("bitmap width too big"),
-            height: height.try_into().expect("image height too big"),
-            pixel_depth: 24,
-            alpha_depth: 0,
-            is_top_to_bottom: true,
-            is_right_to_left: false,
-            color_map_bytes: vec![],
-            pixels: vec![fill; width * height],
-        }
+    pub fn with_dimensions(width: usize, height: usize, fill: u8_rgb) -> Result<Self, FormatError> {
+        Self::from_pixels(width, height, std::iter::repeat_n(fill, width * height))
+    }
+
+    pub fn from_pixels<I: Iterator<Item = u8_rgb>>(
+        width: usize,
+        height: usize,
+        pixels: I,
+    ) -> Result<Self, FormatError> {
+        Self::from_image(Image::from_pixels(width, height, pixels))
     }
 
-    pub fn from_pixels<I: Iterator<Item = u8_rgb>>(width: usize, height: usize, pixels: I) -> Self {
-        let pixels: Vec<u8_rgb> = pixels.collect();
-        assert!(pixels.len() == width * height);
+    /// Uses 24-bit uncompressed true color until `set_encoding` is called.
+    pub fn from_image(pixels: Image<u8_rgb>) -> Result<Self, FormatError> {
+        let (width, height) = (pixels.width(), pixels.height());
 
-        TgaBitmap {
+        let oversize =
+            || FormatError::OversizeDimension(format!("TGA image of {width}x{height} pixels"));
+
+        Ok(TgaBitmap {
             id: String::new(),
             color_map_type: ColorMapType::None,
             image_type: ImageType::TrueColor,
@@ -128,36 +231,284 @@ impl TgaBitmap {
             color_map_entry_size: 0,
             origin_x: 0,
             origin_y: 0,
-            width: width.try_into().expect("bitmap width too big"),
-            height: height.try_into().expect("image height too big"),
+            width: width.try_into().map_err(|_| oversize())?,
+            height: height.try_into().map_err(|_| oversize())?,
             pixel_depth: 24,
             alpha_depth: 0,
             is_top_to_bottom: true,
             is_right_to_left: false,
             color_map_bytes: vec![],
+            alpha: vec![255; width * height],
             pixels,
+            is_tga2: false,
+            extension: None,
+        })
+    }
+
+    /// Uses 32-bit uncompressed true color until `set_encoding` is called.
+    pub fn from_rgba_image(image: &Image<u8_rgba>) -> Result<Self, FormatError> {
+        let mut bitmap = Self::from_image(image.convert())?;
+        bitmap.alpha = image.pixels().iter().map(|c| c.3).collect();
+        bitmap.alpha_depth = 8;
+        bitmap.pixel_depth = 32;
+
+        Ok(bitmap)
+    }
+}
+
+impl TryFrom<Image<u8_rgb>> for TgaBitmap {
+    type Error = FormatError;
+
+    fn try_from(image: Image<u8_rgb>) -> Result<Self, FormatError> {
+        Self::from_image(image)
+    }
+}
+
+impl TryFrom<&Image<u8_rgba>> for TgaBitmap {
+    type Error = FormatError;
+
+    fn try_from(image: &Image<u8_rgba>) -> Result<Self, FormatError> {
+        Self::from_rgba_image(image)
+    }
+}
+
+impl TgaBitmap {
+    /// Checks that the image type, pixel depth, alpha depth and color map fit together.
+    fn validate_encoding(
+        image_type: ImageType,
+        pixel_depth: u8,
+        alpha_depth: u8,
+        color_map_type: ColorMapType,
+        color_map_entry_size: u8,
+    ) -> Result<(), FormatError> {
+        // any image type may carry a color map, and it is decoded even if unused
+        if color_map_type == ColorMapType::Present
+            && !matches!(color_map_entry_size, 15 | 16 | 24 | 32)
+        {
+            return Err(FormatError::Unsupported(format!(
+                "TGA color map with {color_map_entry_size}-bit entries"
+            )));
         }
+
+        let is_valid = match image_type {
+            ImageType::None => true,
+
+            ImageType::TrueColor | ImageType::RleTrueColor => matches!(
+                (pixel_depth, alpha_depth),
+                (15, 0) | (16, 0 | 1) | (24, 0) | (32, 0 | 8)
+            ),
+
+            ImageType::Grayscale | ImageType::RleGrayscale => {
+                matches!((pixel_depth, alpha_depth), (8, 0) | (16, 0 | 8))
+            }
+
+            ImageType::ColorMapped | ImageType::RleColorMapped => {
+                matches!(pixel_depth, 8 | 16)
+                    && color_map_type == ColorMapType::Present
+                    && matches!(color_map_entry_size, 15 | 16 | 24 | 32)
+                    && alpha_depth <= 8
+            }
+        };
+
+        if !is_valid {
+            return Err(FormatError::Unsupported(format!(
+                "TGA encoding {image_type:?} with {pixel_depth}-bit pixels, \
+                 {alpha_depth}-bit alpha, {color_map_entry_size}-bit color map"
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Chooses how `write_to` will encode pixels.
+    /// Color-mapped types require a color map, see `set_color_map`.
+    pub fn set_encoding(
+        &mut self,
+        image_type: ImageType,
+        pixel_depth: u8,
+        alpha_depth: u8,
+    ) -> Result<(), FormatError> {
+        Self::validate_encoding(
+            image_type,
+            pixel_depth,
+            alpha_depth,
+            self.color_map_type,
+            self.color_map_entry_size,
+        )?;
+
+        self.image_type = image_type;
+        self.pixel_depth = pixel_depth;
+        self.alpha_depth = alpha_depth;
+
+        Ok(())
+    }
+
+    /// Pixel order used by `write_to`; does not change how pixels are accessed.
+    pub fn set_orientation(&mut self, is_top_to_bottom: bool, is_right_to_left: bool) {
+        self.is_top_to_bottom = is_top_to_bottom;
+        self.is_right_to_left = is_right_to_left;
+    }
+
+    /// Replaces the color map with 24-bit entries.
+    pub fn set_color_map(&mut self, colors: &[u8_rgb]) -> Result<(), FormatError> {
+        self.color_map_length = colors.len().try_into().map_err(|_| {
+            FormatError::OversizeDimension(format!("TGA color map of {} entries", colors.len()))
+        })?;
+
+        self.color_map_type = ColorMapType::Present;
+        self.color_map_first_index = 0;
+        self.color_map_entry_size = 24;
+
+        self.color_map_bytes.clear();
+        for &color in colors {
+            encode_true_color(color, 255, 3, &mut self.color_map_bytes);
+        }
+
+        Ok(())
+    }
+
+    /// Setting an extension also enables the TGA 2.0 footer.
+    pub fn set_extension(&mut self, extension: Option<TgaExtension>) {
+        self.is_tga2 |= extension.is_some();
+        self.extension = extension;
     }
 }
 
 impl TgaBitmap {
-    fn read_pixel_from<R: Read>(reader: &mut R) -> Result<u8_rgb, io::Error> {
-        let b = reader.read_u8()?;
-        let g = reader.read_u8()?;
-        let r = reader.read_u8()?;
+    /// Normalizes alpha the same way the color map entry format would store it.
+    fn color_map_key(color: u8_rgb, alpha: u8, entry_bpp: usize) -> (u8_rgb, u8) {
+        match entry_bpp {
+            2 => (color, if alpha >= 128 { 255 } else { 0 }),
+            4 => (color, alpha),
+            _ => (color, 255),
+        }
+    }
+
+    fn color_map_entries(&self) -> Vec<(u8_rgb, u8)> {
+        let entry_bpp = bytes_per_pixel(self.color_map_entry_size);
 
-        Ok(u8_rgb(r, g, b))
+        if entry_bpp == 0 {
+            return vec![];
+        }
+
+        self.color_map_bytes
+            .chunks_exact(entry_bpp)
+            .map(decode_true_color)
+            .collect()
     }
 
-    fn write_pixel_to<W: Write>(pixel: &u8_rgb, writer: &mut W) -> Result<(), io::Error> {
-        writer.write_u8(pixel.2)?;
-        writer.write_u8(pixel.1)?;
-        writer.write_u8(pixel.0)?;
+    /// Maps an index into pixel storage to a position in the file's pixel order.
+    fn file_index(&self, i: usize) -> usize {
+        let (width, height) = (self.width(), self.height());
+        let (y, x) = (i / width, i % width);
+
+        let row = if self.is_top_to_bottom {
+            y
+        } else {
+            height - 1 - y
+        };
+        let col = if self.is_right_to_left {
+            width - 1 - x
+        } else {
+            x
+        };
+
+        row * width + col
+    }
+
+    fn decode_pixels(&mut self, bytes: &[u8]) -> Result<(), FormatError> {
+        let bpp = bytes_per_pixel(self.pixel_depth);
+        let color_map = self.color_map_entries();
+        let first_index = self.color_map_first_index as usize;
+
+        let units: Vec<&[u8]> = bytes.chunks_exact(bpp).collect();
+
+        for i in 0..self.alpha.len() {
+            let unit = units[self.file_index(i)];
+
+            let (color, alpha) = if self.image_type.is_true_color() {
+                decode_true_color(unit)
+            } else if self.image_type.is_grayscale() {
+                decode_grayscale(unit)
+            } else {
+                let index = decode_index(unit);
+
+                *index
+                    .checked_sub(first_index)
+                    .and_then(|i| color_map.get(i))
+                    .ok_or_else(|| {
+                        FormatError::InvalidData(format!(
+                            "TGA color map index {index} out of range"
+                        ))
+                    })?
+            };
+
+            self.pixels.pixels_mut()[i] = color;
+            self.alpha[i] = alpha;
+        }
 
         Ok(())
     }
 
-    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, io::Error> {
+    fn encode_pixels(&self) -> Result<Vec<u8>, FormatError> {
+        let bpp = bytes_per_pixel(self.pixel_depth);
+        let entry_bpp = bytes_per_pixel(self.color_map_entry_size);
+        let first_index = self.color_map_first_index as usize;
+
+        let color_map: HashMap<(u8_rgb, u8), usize> = self
+            .color_map_entries()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (color, alpha))| {
+                let key = Self::color_map_key(color, alpha, entry_bpp);
+                (key, first_index + i)
+            })
+            .rev() // first entry wins on duplicates
+            .collect();
+
+        let mut units = vec![0u8; bpp * self.alpha.len()];
+        let mut unit = Vec::<u8>::with_capacity(bpp);
+
+        for (i, (&color, &alpha)) in self.pixels.pixels().iter().zip(&self.alpha).enumerate() {
+            unit.clear();
+
+            if self.image_type.is_true_color() {
+                encode_true_color(color, alpha, bpp, &mut unit);
+            } else if self.image_type.is_grayscale() {
+                encode_grayscale(color, alpha, bpp, &mut unit);
+            } else {
+                let key = Self::color_map_key(color, alpha, entry_bpp);
+                let index = color_map.get(&key).ok_or_else(|| {
+                    FormatError::InvalidData(format!(
+                        "TGA color map has no entry for {color:?}, alpha {alpha}"
+                    ))
+                })?;
+
+                encode_index(*index, bpp, &mut unit);
+            }
+
+            let j = self.file_index(i);
+            units[j * bpp..(j + 1) * bpp].copy_from_slice(&unit);
+        }
+
+        if !self.image_type.is_rle() {
+            return Ok(units);
+        }
+
+        let mut bytes = Vec::<u8>::with_capacity(units.len());
+        write_rle_pixel_bytes(&units, bpp, self.width(), &mut bytes);
+
+        Ok(bytes)
+    }
+}
+
+impl TgaBitmap {
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let mut bytes = Vec::<u8>::new();
+        reader.read_to_end(&mut bytes)?;
+
+        let reader = &mut Cursor::new(&bytes[..]);
+
         let id_length = reader.read_u8()?;
         let color_map_type = reader.read_u8()?;
         let image_type = reader.read_u8()?;
@@ -179,28 +530,47 @@ impl TgaBitmap {
         let is_right_to_left = descriptor & 0x10 != 0;
         let is_top_to_bottom = descriptor & 0x20 != 0;
 
-        let mut id = String::with_capacity(id_length as usize);
-        reader
-            .by_ref()
-            .take(id_length as u64)
-            .read_to_string(&mut id)?;
+        let mut id = vec![0u8; id_length as usize];
+        reader.read_exact(&mut id)?;
+        let id = String::from_utf8(id)
+            .map_err(|_| FormatError::InvalidHeader("TGA image id is not UTF-8".to_string()))?;
 
-        let color_map_size = (color_map_length as usize) * (color_map_entry_size as usize);
-        let mut color_map_bytes = Vec::<u8>::with_capacity(color_map_size);
-        reader
-            .by_ref()
-            .take(color_map_size as u64)
-            .read_to_end(&mut color_map_bytes)?;
+        let color_map_size = (color_map_length as usize) * bytes_per_pixel(color_map_entry_size);
+        let mut color_map_bytes = vec![0u8; color_map_size];
+        reader.read_exact(&mut color_map_bytes)?;
+
+        let color_map_type = ColorMapType::try_from(color_map_type)?;
+        let image_type = ImageType::try_from(image_type)?;
+
+        Self::validate_encoding(
+            image_type,
+            pixel_depth,
+            alpha_depth,
+            color_map_type,
+            color_map_entry_size,
+        )?;
 
         let pixel_count = (width as usize) * (height as usize);
-        let mut pixels = Vec::<u8_rgb>::with_capacity(pixel_count);
-        for _ in 0..pixel_count {
-            pixels.push(Self::read_pixel_from(reader)?);
+
+        if image_type != ImageType::None {
+            let bpp = bytes_per_pixel(pixel_depth);
+            let remaining = bytes.len() - reader.position() as usize;
+
+            // an RLE packet takes at least 1 + bpp bytes and covers at most 128 pixels
+            let min_size = if image_type.is_rle() {
+                pixel_count.div_ceil(128) * (1 + bpp)
+            } else {
+                pixel_count * bpp
+            };
+
+            if remaining < min_size {
+                return Err(FormatError::Truncated);
+            }
         }
 
-        Ok(TgaBitmap {
-            color_map_type: color_map_type.into(),
-            image_type: image_type.into(),
+        let mut bitmap = TgaBitmap {
+            color_map_type,
+            image_type,
 
             color_map_first_index,
             color_map_length,
@@ -219,38 +589,91 @@ impl TgaBitmap {
 
             id,
             color_map_bytes,
-            pixels,
-        })
+            pixels: Image::new(width as usize, height as usize, u8_rgb(0, 0, 0)),
+            alpha: vec![255; pixel_count],
+
+            is_tga2: false,
+            extension: None,
+        };
+
+        if image_type != ImageType::None {
+            let bpp = bytes_per_pixel(pixel_depth);
+            let pixel_bytes = read_pixel_bytes(reader, bpp, pixel_count, image_type.is_rle())?;
+            bitmap.decode_pixels(&pixel_bytes)?;
+        }
+
+        if bytes.len() >= FOOTER_SIZE && bytes.ends_with(FOOTER_SIGNATURE) {
+            bitmap.is_tga2 = true;
+
+            let footer = &bytes[bytes.len() - FOOTER_SIZE..];
+            let extension_offset = u32::from_le_bytes(footer[0..4].try_into().unwrap()) as usize;
+
+            if extension_offset != 0 {
+                let extension_bytes = bytes
+                    .get(extension_offset..bytes.len() - FOOTER_SIZE)
+                    .ok_or_else(|| {
+                        FormatError::InvalidHeader("TGA extension offset out of range".into())
+                    })?;
+
+                bitmap.extension = Some(TgaExtension::read_from(&mut &extension_bytes[..])?);
+            }
+        }
+
+        Ok(bitmap)
     }
 
-    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
-        writer.write_u8(self.id.len().try_into().unwrap())?;
-        writer.write_u8(self.color_map_type as u8)?;
-        writer.write_u8(self.image_type as u8)?;
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), FormatError> {
+        let mut bytes = Vec::<u8>::new();
+        let w = &mut bytes;
+
+        let id_length = u8::try_from(self.id.len()).map_err(|_| {
+            FormatError::OversizeDimension(format!("TGA image id of {} bytes", self.id.len()))
+        })?;
 
-        writer.write_u16::<LE>(self.color_map_first_index)?;
-        writer.write_u16::<LE>(self.color_map_length)?;
-        writer.write_u8(self.color_map_entry_size)?;
+        w.write_u8(id_length)?;
+        w.write_u8(self.color_map_type as u8)?;
+        w.write_u8(self.image_type as u8)?;
 
-        writer.write_u16::<LE>(self.origin_x)?;
-        writer.write_u16::<LE>(self.origin_y)?;
-        writer.write_u16::<LE>(self.width)?;
-        writer.write_u16::<LE>(self.height)?;
+        w.write_u16::<LE>(self.color_map_first_index)?;
+        w.write_u16::<LE>(self.color_map_length)?;
+        w.write_u8(self.color_map_entry_size)?;
 
-        writer.write_u8(self.pixel_depth)?;
+        w.write_u16::<LE>(self.origin_x)?;
+        w.write_u16::<LE>(self.origin_y)?;
+        w.write_u16::<LE>(self.width)?;
+        w.write_u16::<LE>(self.height)?;
+
+        w.write_u8(self.pixel_depth)?;
 
         let descriptor = self.alpha_depth
             | (if self.is_right_to_left { 0x10 } else { 0 })
             | (if self.is_top_to_bottom { 0x20 } else { 0 });
-        writer.write_u8(descriptor)?;
+        w.write_u8(descriptor)?;
 
-        writer.write_all(self.id.as_bytes())?;
-        writer.write_all(&self.color_map_bytes)?;
+        w.write_all(self.id.as_bytes())?;
+        w.write_all(&self.color_map_bytes)?;
 
-        for pixel in self.pixels.iter() {
-            Self::write_pixel_to(pixel, writer)?;
+        if self.image_type != ImageType::None {
+            w.write_all(&self.encode_pixels()?)?;
         }
 
+        if self.is_tga2 {
+            let mut extension_offset = 0;
+
+            if let Some(extension) = self.extension.as_ref() {
+                extension_offset = w.len().try_into().map_err(|_| {
+                    FormatError::OversizeDimension(format!("TGA file of {} bytes", w.len()))
+                })?;
+                extension.write_to(w)?;
+            }
+
+            w.write_u32::<LE>(extension_offset)?;
+            w.write_u32::<LE>(0)?; // developer directory offset
+            w.write_all(FOOTER_SIGNATURE)?;
+        }
+
+        writer.write_all(&bytes)?;
+
         Ok(())
     }
 }
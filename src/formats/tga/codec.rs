@@ -0,0 +1,170 @@
+use std::io::Read;
+
+use crate::formats::FormatError;
+use crate::image::*;
+
+pub(super) fn bytes_per_pixel(pixel_depth: u8) -> usize {
+    (pixel_depth as usize).div_ceil(8)
+}
+
+/// Expands a 5-bit channel to 8 bits, so that 0x1F maps to 0xFF.
+fn expand_5_bits(x: u16) -> u8 {
+    let x = (x & 0x1F) as u8;
+    (x << 3) | (x >> 2)
+}
+
+/// Returns the color and the raw attribute (alpha) value.
+pub(super) fn decode_true_color(bytes: &[u8]) -> (u8_rgb, u8) {
+    match bytes.len() {
+        2 => {
+            let value = u16::from_le_bytes([bytes[0], bytes[1]]);
+            let color = u8_rgb(
+                expand_5_bits(value >> 10),
+                expand_5_bits(value >> 5),
+                expand_5_bits(value),
+            );
+            let alpha = if value & 0x8000 != 0 { 255 } else { 0 };
+
+            (color, alpha)
+        }
+
+        3 => (u8_rgb(bytes[2], bytes[1], bytes[0]), 255),
+        4 => (u8_rgb(bytes[2], bytes[1], bytes[0]), bytes[3]),
+
+        _ => unreachable!(),
+    }
+}
+
+pub(super) fn encode_true_color(color: u8_rgb, alpha: u8, bpp: usize, out: &mut Vec<u8>) {
+    match bpp {
+        2 => {
+            let value = ((color.0 as u16 >> 3) << 10)
+                | ((color.1 as u16 >> 3) << 5)
+                | (color.2 as u16 >> 3)
+                | (if alpha >= 128 { 0x8000 } else { 0 });
+
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+
+        3 => out.extend_from_slice(&[color.2, color.1, color.0]),
+        4 => out.extend_from_slice(&[color.2, color.1, color.0, alpha]),
+
+        _ => unreachable!(),
+    }
+}
+
+pub(super) fn decode_grayscale(bytes: &[u8]) -> (u8_rgb, u8) {
+    let y = bytes[0];
+    let alpha = if bytes.len() == 2 { bytes[1] } else { 255 };
+
+    (u8_rgb(y, y, y), alpha)
+}
+
+/// Uses the green channel, which carries most of the luminance.
+pub(super) fn encode_grayscale(color: u8_rgb, alpha: u8, bpp: usize, out: &mut Vec<u8>) {
+    match bpp {
+        1 => out.push(color.1),
+        2 => out.extend_from_slice(&[color.1, alpha]),
+
+        _ => unreachable!(),
+    }
+}
+
+pub(super) fn decode_index(bytes: &[u8]) -> usize {
+    match bytes.len() {
+        1 => bytes[0] as usize,
+        2 => u16::from_le_bytes([bytes[0], bytes[1]]) as usize,
+
+        _ => unreachable!(),
+    }
+}
+
+pub(super) fn encode_index(index: usize, bpp: usize, out: &mut Vec<u8>) {
+    match bpp {
+        1 => out.push(index as u8),
+        2 => out.extend_from_slice(&(index as u16).to_le_bytes()),
+
+        _ => unreachable!(),
+    }
+}
+
+/// Reads `count` pixels of `bpp` bytes each, expanding RLE packets if `is_rle`.
+pub(super) fn read_pixel_bytes<R: Read>(
+    reader: &mut R,
+    bpp: usize,
+    count: usize,
+    is_rle: bool,
+) -> Result<Vec<u8>, FormatError> {
+    let mut bytes = vec![0u8; bpp * count];
+
+    if !is_rle {
+        reader.read_exact(&mut bytes)?;
+        return Ok(bytes);
+    }
+
+    let mut i = 0;
+
+    while i < count {
+        let mut packet_header = [0u8; 1];
+        reader.read_exact(&mut packet_header)?;
+
+        let n = (packet_header[0] & 0x7F) as usize + 1;
+
+        if i + n > count {
+            return Err(FormatError::InvalidData(format!(
+                "TGA RLE packet overflows image by {} pixels",
+                i + n - count
+            )));
+        }
+
+        if packet_header[0] & 0x80 != 0 {
+            reader.read_exact(&mut bytes[i * bpp..(i + 1) * bpp])?;
+
+            for j in 1..n {
+                bytes.copy_within(i * bpp..(i + 1) * bpp, (i + j) * bpp);
+            }
+        } else {
+            reader.read_exact(&mut bytes[i * bpp..(i + n) * bpp])?;
+        }
+
+        i += n;
+    }
+
+    Ok(bytes)
+}
+
+/// Packets never cross scanline boundaries, as recommended by the TGA 2.0 spec.
+pub(super) fn write_rle_pixel_bytes(bytes: &[u8], bpp: usize, width: usize, out: &mut Vec<u8>) {
+    for line in bytes.chunks(bpp * width) {
+        let pixels: Vec<&[u8]> = line.chunks(bpp).collect();
+        let mut i = 0;
+
+        while i < pixels.len() {
+            let mut run = 1;
+            while run < 128 && i + run < pixels.len() && pixels[i + run] == pixels[i] {
+                run += 1;
+            }
+
+            if run > 1 {
+                out.push(0x80 | (run - 1) as u8);
+                out.extend_from_slice(pixels[i]);
+                i += run;
+                continue;
+            }
+
+            // raw packet continues until the next run of at least two pixels
+            let start = i;
+            while i < pixels.len()
+                && i - start < 128
+                && !(i + 1 < pixels.len() && pixels[i + 1] == pixels[i])
+            {
+                i += 1;
+            }
+
+            out.push((i - start - 1) as u8);
+            for pixel in pixels[start..i].iter() {
+                out.extend_from_slice(pixel);
+            }
+        }
+    }
+}
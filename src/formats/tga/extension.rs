@@ -0,0 +1,152 @@
+use std::io;
+use std::io::{Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+
+use crate::formats::FormatError;
+
+pub(super) const FOOTER_SIGNATURE: &[u8; 18] = b"TRUEVISION-XFILE.\0";
+pub(super) const FOOTER_SIZE: usize = 26;
+pub(super) const EXTENSION_SIZE: u16 = 495;
+
+/// TGA 2.0 extension area.
+/// Developer area, color correction, postage stamp and scan line tables are not preserved.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TgaExtension {
+    pub author_name: String,
+    pub author_comments: [String; 4],
+
+    /// month, day, year, hour, minute, second
+    pub date_time: [u16; 6],
+
+    pub job_name: String,
+
+    /// hours, minutes, seconds
+    pub job_time: [u16; 3],
+
+    pub software_id: String,
+
+    /// version number times 100, and a version letter (or a space)
+    pub software_version: (u16, u8),
+
+    /// background color as ARGB
+    pub key_color: u32,
+
+    /// numerator, denominator; zero denominator means no aspect ratio
+    pub pixel_aspect_ratio: (u16, u16),
+
+    /// numerator, denominator; zero denominator means no gamma value
+    pub gamma: (u16, u16),
+
+    /// meaning of the alpha channel: 0 = none, 1 = undefined (ignore),
+    /// 2 = undefined (retain), 3 = alpha, 4 = premultiplied alpha
+    pub attributes_type: u8,
+}
+
+fn read_fixed_string<R: Read>(reader: &mut R, len: usize) -> Result<String, io::Error> {
+    let mut bytes = vec![0u8; len];
+    reader.read_exact(&mut bytes)?;
+
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(len);
+    Ok(String::from_utf8_lossy(&bytes[..end]).into_owned())
+}
+
+/// Truncates to leave room for the terminating NUL.
+fn write_fixed_string<W: Write>(writer: &mut W, s: &str, len: usize) -> Result<(), io::Error> {
+    let mut bytes = s.as_bytes().to_vec();
+    bytes.truncate(len - 1);
+    bytes.resize(len, 0);
+
+    writer.write_all(&bytes)
+}
+
+impl TgaExtension {
+    pub(super) fn read_from<R: Read>(reader: &mut R) -> Result<Self, FormatError> {
+        let size = reader.read_u16::<LE>()?;
+
+        if size < EXTENSION_SIZE {
+            return Err(FormatError::InvalidHeader(format!(
+                "TGA extension area is {size} bytes, expected {EXTENSION_SIZE}"
+            )));
+        }
+
+        let author_name = read_fixed_string(reader, 41)?;
+
+        let mut author_comments: [String; 4] = Default::default();
+        for line in author_comments.iter_mut() {
+            *line = read_fixed_string(reader, 81)?;
+        }
+
+        let mut date_time = [0u16; 6];
+        reader.read_u16_into::<LE>(&mut date_time)?;
+
+        let job_name = read_fixed_string(reader, 41)?;
+
+        let mut job_time = [0u16; 3];
+        reader.read_u16_into::<LE>(&mut job_time)?;
+
+        let software_id = read_fixed_string(reader, 41)?;
+        let software_version = (reader.read_u16::<LE>()?, reader.read_u8()?);
+
+        let key_color = reader.read_u32::<LE>()?;
+        let pixel_aspect_ratio = (reader.read_u16::<LE>()?, reader.read_u16::<LE>()?);
+        let gamma = (reader.read_u16::<LE>()?, reader.read_u16::<LE>()?);
+
+        let _color_correction_offset = reader.read_u32::<LE>()?;
+        let _postage_stamp_offset = reader.read_u32::<LE>()?;
+        let _scan_line_offset = reader.read_u32::<LE>()?;
+
+        let attributes_type = reader.read_u8()?;
+
+        Ok(Self {
+            author_name,
+            author_comments,
+            date_time,
+            job_name,
+            job_time,
+            software_id,
+            software_version,
+            key_color,
+            pixel_aspect_ratio,
+            gamma,
+            attributes_type,
+        })
+    }
+
+    pub(super) fn write_to<W: Write>(&self, writer: &mut W) -> Result<(), io::Error> {
+        writer.write_u16::<LE>(EXTENSION_SIZE)?;
+
+        write_fixed_string(writer, &self.author_name, 41)?;
+        for line in self.author_comments.iter() {
+            write_fixed_string(writer, line, 81)?;
+        }
+
+        for x in self.date_time {
+            writer.write_u16::<LE>(x)?;
+        }
+
+        write_fixed_string(writer, &self.job_name, 41)?;
+        for x in self.job_time {
+            writer.write_u16::<LE>(x)?;
+        }
+
+        write_fixed_string(writer, &self.software_id, 41)?;
+        writer.write_u16::<LE>(self.software_version.0)?;
+        writer.write_u8(self.software_version.1)?;
+
+        writer.write_u32::<LE>(self.key_color)?;
+        writer.write_u16::<LE>(self.pixel_aspect_ratio.0)?;
+        writer.write_u16::<LE>(self.pixel_aspect_ratio.1)?;
+        writer.write_u16::<LE>(self.gamma.0)?;
+        writer.write_u16::<LE>(self.gamma.1)?;
+
+        // color correction, postage stamp and scan line tables
+        writer.write_u32::<LE>(0)?;
+        writer.write_u32::<LE>(0)?;
+        writer.write_u32::<LE>(0)?;
+
+        writer.write_u8(self.attributes_type)?;
+
+        Ok(())
+    }
+}
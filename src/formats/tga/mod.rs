@@ -1,3 +1,6 @@
 mod bitmap;
+mod codec;
+mod extension;
 
 pub use bitmap::*;
+pub use extension::*;
@@ -0,0 +1,116 @@
+use super::*;
+
+/// Pixel grid stored top-to-bottom, left-to-right, independent of any file format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Image<P> {
+    width: usize,
+    height: usize,
+    pixels: Vec<P>,
+}
+
+impl<P: Copy> Image<P> {
+    pub fn new(width: usize, height: usize, fill: P) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![fill; width * height],
+        }
+    }
+
+    pub fn from_pixels<I: IntoIterator<Item = P>>(width: usize, height: usize, pixels: I) -> Self {
+        let pixels: Vec<P> = pixels.into_iter().collect();
+        assert!(pixels.len() == width * height);
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, pixel_x: usize, pixel_y: usize) -> P {
+        assert!(pixel_x < self.width);
+        self.pixels[pixel_y * self.width + pixel_x]
+    }
+
+    pub fn get_mut(&mut self, pixel_x: usize, pixel_y: usize) -> &mut P {
+        assert!(pixel_x < self.width);
+        &mut self.pixels[pixel_y * self.width + pixel_x]
+    }
+
+    pub fn pixels(&self) -> &[P] {
+        &self.pixels
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut [P] {
+        &mut self.pixels
+    }
+
+    pub fn into_pixels(self) -> Vec<P> {
+        self.pixels
+    }
+
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &[P]> + '_ {
+        self.view(0, 0, self.width, self.height).into_rows()
+    }
+
+    pub fn rows_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut [P]> + '_ {
+        let (width, height) = (self.width, self.height);
+        self.view_mut(0, 0, width, height).into_rows_mut()
+    }
+
+    /// Iterates over `(pixel_x, pixel_y, pixel)`.
+    pub fn enumerate_pixels(&self) -> impl Iterator<Item = (usize, usize, P)> + '_ {
+        let width = self.width.max(1);
+
+        self.pixels
+            .iter()
+            .enumerate()
+            .map(move |(i, &p)| (i % width, i / width, p))
+    }
+
+    /// Borrows a rectangle of `width` x `height` pixels with its top left corner at `x`, `y`.
+    pub fn view(&self, x: usize, y: usize, width: usize, height: usize) -> ImageView<'_, P> {
+        self.assert_rect(x, y, width, height);
+
+        let start = (y * self.width + x).min(self.pixels.len());
+        ImageView::new(&self.pixels[start..], self.width, width, height)
+    }
+
+    pub fn view_mut(
+        &mut self,
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    ) -> ImageViewMut<'_, P> {
+        self.assert_rect(x, y, width, height);
+
+        let start = (y * self.width + x).min(self.pixels.len());
+        ImageViewMut::new(&mut self.pixels[start..], self.width, width, height)
+    }
+
+    fn assert_rect(&self, x: usize, y: usize, width: usize, height: usize) {
+        assert!(x + width <= self.width && y + height <= self.height);
+    }
+
+    pub fn map<Q: Copy, F: FnMut(P) -> Q>(&self, f: F) -> Image<Q> {
+        Image {
+            width: self.width,
+            height: self.height,
+            pixels: self.pixels.iter().copied().map(f).collect(),
+        }
+    }
+
+    pub fn convert<Q: Copy + From<P>>(&self) -> Image<Q> {
+        self.map(Q::from)
+    }
+}
@@ -0,0 +1,71 @@
+use super::*;
+
+/// Accumulates linear color samples per pixel, to be averaged with `resolve`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Framebuffer {
+    sums: Image<f32_rgb>,
+    weights: Image<f32>,
+}
+
+impl Framebuffer {
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            sums: Image::new(width, height, f32_rgb::default()),
+            weights: Image::new(width, height, 0.0),
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.sums.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.sums.height()
+    }
+
+    pub fn add_sample(&mut self, pixel_x: usize, pixel_y: usize, color: f32_rgb) {
+        self.add_weighted_sample(pixel_x, pixel_y, color, 1.0);
+    }
+
+    pub fn add_weighted_sample(
+        &mut self,
+        pixel_x: usize,
+        pixel_y: usize,
+        color: f32_rgb,
+        weight: f32,
+    ) {
+        *self.sums.get_mut(pixel_x, pixel_y) += color * weight;
+        *self.weights.get_mut(pixel_x, pixel_y) += weight;
+    }
+
+    /// Adds every pixel of `image` as a sample of the same pixel.
+    pub fn add_image(&mut self, image: &Image<f32_rgb>) {
+        assert!(image.width() == self.width() && image.height() == self.height());
+
+        for (sum, &color) in self.sums.pixels_mut().iter_mut().zip(image.pixels()) {
+            *sum += color;
+        }
+
+        for weight in self.weights.pixels_mut() {
+            *weight += 1.0;
+        }
+    }
+
+    /// Pixels without samples are black.
+    pub fn resolve(&self) -> Image<f32_rgb> {
+        let pixels = self
+            .sums
+            .pixels()
+            .iter()
+            .zip(self.weights.pixels())
+            .map(|(&sum, &weight)| {
+                if weight > 0.0 {
+                    sum * (1.0 / weight)
+                } else {
+                    f32_rgb::default()
+                }
+            });
+
+        Image::from_pixels(self.width(), self.height(), pixels)
+    }
+}
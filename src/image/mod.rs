@@ -0,0 +1,11 @@
+mod buffer;
+mod framebuffer;
+mod pixel;
+mod tone_map;
+mod view;
+
+pub use buffer::*;
+pub use framebuffer::*;
+pub use pixel::*;
+pub use tone_map::*;
+pub use view::*;
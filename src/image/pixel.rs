@@ -0,0 +1,120 @@
+#![allow(non_camel_case_types)]
+
+use std::ops::*;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct u8_rgb(pub u8, pub u8, pub u8);
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct u8_rgba(pub u8, pub u8, pub u8, pub u8);
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub struct u8_gray(pub u8);
+
+/// Linear color; values outside of 0..1 are allowed.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct f32_rgb(pub f32, pub f32, pub f32);
+
+/// Rec. 601 luma with integer weights, so that gray stays exactly gray.
+fn luma(r: u8, g: u8, b: u8) -> u8 {
+    ((299 * r as u32 + 587 * g as u32 + 114 * b as u32 + 500) / 1000) as u8
+}
+
+fn f32_to_u8(x: f32) -> u8 {
+    (x.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+impl From<u8_rgba> for u8_rgb {
+    fn from(c: u8_rgba) -> Self {
+        u8_rgb(c.0, c.1, c.2)
+    }
+}
+
+impl From<u8_gray> for u8_rgb {
+    fn from(c: u8_gray) -> Self {
+        u8_rgb(c.0, c.0, c.0)
+    }
+}
+
+/// Clamps to 0..1 without any gamma correction.
+impl From<f32_rgb> for u8_rgb {
+    fn from(c: f32_rgb) -> Self {
+        u8_rgb(f32_to_u8(c.0), f32_to_u8(c.1), f32_to_u8(c.2))
+    }
+}
+
+impl From<u8_rgb> for u8_rgba {
+    fn from(c: u8_rgb) -> Self {
+        u8_rgba(c.0, c.1, c.2, 255)
+    }
+}
+
+impl From<u8_gray> for u8_rgba {
+    fn from(c: u8_gray) -> Self {
+        u8_rgba(c.0, c.0, c.0, 255)
+    }
+}
+
+impl From<u8_rgb> for u8_gray {
+    fn from(c: u8_rgb) -> Self {
+        u8_gray(luma(c.0, c.1, c.2))
+    }
+}
+
+/// Ignores alpha.
+impl From<u8_rgba> for u8_gray {
+    fn from(c: u8_rgba) -> Self {
+        u8_gray(luma(c.0, c.1, c.2))
+    }
+}
+
+impl From<u8_rgb> for f32_rgb {
+    fn from(c: u8_rgb) -> Self {
+        f32_rgb(c.0 as f32 / 255.0, c.1 as f32 / 255.0, c.2 as f32 / 255.0)
+    }
+}
+
+impl From<u8_gray> for f32_rgb {
+    fn from(c: u8_gray) -> Self {
+        let y = c.0 as f32 / 255.0;
+        f32_rgb(y, y, y)
+    }
+}
+
+impl f32_rgb {
+    /// Rec. 709 relative luminance of a linear color.
+    pub fn luminance(&self) -> f32 {
+        0.2126 * self.0 + 0.7152 * self.1 + 0.0722 * self.2
+    }
+}
+
+impl Add for f32_rgb {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        f32_rgb(self.0 + rhs.0, self.1 + rhs.1, self.2 + rhs.2)
+    }
+}
+
+impl AddAssign for f32_rgb {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+/// Component-wise, as when filtering light through a colored surface.
+impl Mul for f32_rgb {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        f32_rgb(self.0 * rhs.0, self.1 * rhs.1, self.2 * rhs.2)
+    }
+}
+
+impl Mul<f32> for f32_rgb {
+    type Output = Self;
+
+    fn mul(self, rhs: f32) -> Self {
+        f32_rgb(self.0 * rhs, self.1 * rhs, self.2 * rhs)
+    }
+}
@@ -0,0 +1,86 @@
+use super::*;
+
+/// Compresses linear radiance into the displayable 0..1 range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapOperator {
+    Clamp,
+
+    /// `x / (1 + x)` per channel
+    Reinhard,
+
+    /// Narkowicz's fit of the ACES filmic curve
+    Aces,
+}
+
+impl ToneMapOperator {
+    pub fn apply(&self, x: f32) -> f32 {
+        let x = x.max(0.0);
+
+        let y = match self {
+            Self::Clamp => x,
+            Self::Reinhard => x / (1.0 + x),
+            Self::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+        };
+
+        y.clamp(0.0, 1.0)
+    }
+}
+
+pub fn srgb_encode(x: f32) -> f32 {
+    if x <= 0.0031308 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn srgb_decode(x: f32) -> f32 {
+    if x <= 0.04045 {
+        x / 12.92
+    } else {
+        ((x + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Converts linear float colors to 8-bit display colors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ToneMapping {
+    pub operator: ToneMapOperator,
+
+    /// in stops; every +1 doubles the brightness
+    pub exposure: f32,
+
+    /// otherwise the output stays linear
+    pub is_srgb: bool,
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self {
+            operator: ToneMapOperator::Clamp,
+            exposure: 0.0,
+            is_srgb: true,
+        }
+    }
+}
+
+impl ToneMapping {
+    pub fn map(&self, color: f32_rgb) -> u8_rgb {
+        let scale = self.exposure.exp2();
+
+        let channel = |x: f32| {
+            let y = self.operator.apply(x * scale);
+            let y = if self.is_srgb { srgb_encode(y) } else { y };
+
+            (y * 255.0).round() as u8
+        };
+
+        u8_rgb(channel(color.0), channel(color.1), channel(color.2))
+    }
+}
+
+impl Image<f32_rgb> {
+    pub fn tone_map(&self, tone_mapping: &ToneMapping) -> Image<u8_rgb> {
+        self.map(|c| tone_mapping.map(c))
+    }
+}
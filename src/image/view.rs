@@ -0,0 +1,124 @@
+use super::*;
+
+/// Rectangular part of an `Image`.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageView<'a, P> {
+    /// starts at the top left pixel of the view
+    pixels: &'a [P],
+
+    /// row length of the underlying image
+    stride: usize,
+
+    width: usize,
+    height: usize,
+}
+
+impl<'a, P: Copy> ImageView<'a, P> {
+    pub(super) fn new(pixels: &'a [P], stride: usize, width: usize, height: usize) -> Self {
+        Self {
+            pixels,
+            stride,
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, pixel_x: usize, pixel_y: usize) -> P {
+        assert!(pixel_x < self.width && pixel_y < self.height);
+        self.pixels[pixel_y * self.stride + pixel_x]
+    }
+
+    pub fn into_rows(self) -> impl DoubleEndedIterator<Item = &'a [P]> {
+        let width = self.width;
+
+        self.pixels
+            .chunks(self.stride.max(1))
+            .take(self.height)
+            .map(move |row| &row[..width])
+    }
+
+    pub fn rows(&self) -> impl DoubleEndedIterator<Item = &'a [P]> {
+        self.into_rows()
+    }
+
+    pub fn to_image(&self) -> Image<P> {
+        Image::from_pixels(self.width, self.height, self.rows().flatten().copied())
+    }
+}
+
+/// Mutable rectangular part of an `Image`.
+#[derive(Debug)]
+pub struct ImageViewMut<'a, P> {
+    pixels: &'a mut [P],
+    stride: usize,
+    width: usize,
+    height: usize,
+}
+
+impl<'a, P: Copy> ImageViewMut<'a, P> {
+    pub(super) fn new(pixels: &'a mut [P], stride: usize, width: usize, height: usize) -> Self {
+        Self {
+            pixels,
+            stride,
+            width,
+            height,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, pixel_x: usize, pixel_y: usize) -> P {
+        assert!(pixel_x < self.width && pixel_y < self.height);
+        self.pixels[pixel_y * self.stride + pixel_x]
+    }
+
+    pub fn get_mut(&mut self, pixel_x: usize, pixel_y: usize) -> &mut P {
+        assert!(pixel_x < self.width && pixel_y < self.height);
+        &mut self.pixels[pixel_y * self.stride + pixel_x]
+    }
+
+    pub fn as_view(&self) -> ImageView<'_, P> {
+        ImageView::new(self.pixels, self.stride, self.width, self.height)
+    }
+
+    pub fn into_rows_mut(self) -> impl DoubleEndedIterator<Item = &'a mut [P]> {
+        let width = self.width;
+
+        self.pixels
+            .chunks_mut(self.stride.max(1))
+            .take(self.height)
+            .map(move |row| &mut row[..width])
+    }
+
+    pub fn rows_mut(&mut self) -> impl DoubleEndedIterator<Item = &mut [P]> {
+        let width = self.width;
+
+        self.pixels
+            .chunks_mut(self.stride.max(1))
+            .take(self.height)
+            .map(move |row| &mut row[..width])
+    }
+
+    /// Copies pixels from a view of the same size.
+    pub fn copy_from(&mut self, source: ImageView<P>) {
+        assert!(source.width() == self.width && source.height() == self.height);
+
+        for (dst, src) in self.rows_mut().zip(source.rows()) {
+            dst.copy_from_slice(src);
+        }
+    }
+}
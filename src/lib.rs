@@ -1,8 +1,11 @@
 #![feature(core_intrinsics)]
+#![feature(portable_simd)]
 #![feature(type_alias_impl_trait)]
 #![feature(test)]
 
 pub mod cast;
 pub mod formats;
+pub mod image;
 pub mod math;
 pub mod primitives;
+pub mod render;
@@ -1,114 +1,682 @@
 use deer2::cast::*;
+use deer2::formats::obj::*;
+use deer2::formats::ply::*;
 use deer2::formats::stl::*;
 use deer2::formats::tga::*;
+use deer2::formats::FormatError;
+use deer2::image::*;
 use deer2::math::*;
+use deer2::render::*;
 
 use rand::rngs::SmallRng;
 use rand::SeedableRng;
 
+use std::error::Error;
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Write};
+use std::process::ExitCode;
 
-fn main() {
-    // let in_filename = std::env::args().nth(1).unwrap();
-    // let out_filename = std::env::args().nth(2).unwrap();
+const USAGE: &str = "\
+usage: deer2 [options] <input.stl|input.obj|input.ply> <output.tga|output.png|output.ppm|output.pgm|output.pfm>
 
-    let in_filename = "./data/stl/stanford_bunny.stl";
-    let out_filename = "./stanford_bunny.tga";
+options:
+    --size <W>x<H>          image size in pixels (default: 512x512)
+    --scale <X,Y,Z>         scale the model along the axes
+    --rotate <X,Y,Z,DEG>    rotate the model around the axis (X,Y,Z) by DEG degrees
+    --translate <X,Y,Z>     move the model by (X,Y,Z); model transforms apply
+                            in the order they are given
+    --camera <X,Y,Z>        camera position (default: 0,0,306)
+    --look-at <X,Y,Z>       point the camera looks at (default: 0,0,0)
+    --up <X,Y,Z>            camera up direction (default: 0,1,0)
+    --fov <DEG>             vertical field of view in degrees (default: 53.13)
+    --ortho <HEIGHT>        orthographic projection with this view height
+    --light <X,Y,Z>         direction towards the light (default: -1,1,1)
+    --material <KIND>       model material: diffuse, mirror, glossy or glass
+                            (default: diffuse)
+    --color <R,G,B>         albedo of diffuse and glossy materials, tint of mirror
+                            and glass ones (default: 1,1,1)
+    --shininess <N>         Blinn-Phong exponent of the glossy material (default: 32)
+    --ior <N>               index of refraction of the glass material (default: 1.5)
+    --bounces <N>           mirror and glass rays followed per camera ray (default: 4)
+    --background <R,G,B>    radiance of rays that hit nothing (default: 0,0,0)
+    --accel <KIND>          acceleration structure: bsp, kd, kd-sah, bvh or list
+                            (default: bsp)
+    --retries <N>           build retries for the bsp tree (default: 16)
+    --leaf-size <N>         triangles per leaf for the kd-sah tree (default: 4)
+    --tree-cache <FILE>     load the acceleration structure from FILE if it was saved
+                            by the same builder for the same transformed triangles;
+                            otherwise build and save it
+    --seed <N>              RNG seed (default: 117)
+    --samples <N>           jittered samples per pixel; 1 samples pixel centers (default: 1)
+    --threads <N>           render threads (default: number of CPUs)
+    --max-distance <D>      maximum ray length (default: 2000)
+    --shadow-bias <D>       shadow, reflected and refracted rays ignore hits closer
+                            than this (default: 0.01)
+    --culling <KIND>        triangle sides camera rays ignore: back, front or none
+                            (default: back)
+    --tone-map <KIND>       tone mapping: clamp, reinhard or aces (default: clamp)
+    --exposure <EV>         exposure adjustment in stops (default: 0)
+    --gamma <KIND>          output encoding: srgb or linear (default: srgb)
+    -h, --help              print this message
+";
 
-    // let in_filename = "./data/stl/utah_teapot.stl";
-    // let out_filename = "./utah_teapot.tga";
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Accel {
+    /// `BspTree::build_tri_randomized`
+    Bsp,
 
-    let in_file = File::options().read(true).open(in_filename).unwrap();
-    let mut in_file = BufReader::with_capacity(8 * 1024 * 1024, in_file);
+    /// `BspTree::build_kd`
+    Kd,
 
-    let out_file = File::options()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(out_filename)
-        .unwrap();
-    let mut out_file = BufWriter::with_capacity(8 * 1024 * 1024, out_file);
+    /// `BspTree::build_kd_sah`
+    KdSah,
 
-    let model = StlModel::read_from(&mut in_file).unwrap();
-    let triangles = model.to_triangle_list();
+    /// `Bvh::build`
+    Bvh,
 
-    let mut rng = SmallRng::seed_from_u64(117);
-    let bsp_tree = BspTree::build_tri_randomized(&triangles.triangles, &mut rng, 16);
-    // let bsp_tree = BspTree::build_kd(&triangles.triangles);
+    /// brute-force `TriangleList`
+    List,
+}
 
-    let mut bitmap = TgaBitmap::with_dimensions(512, 512, u8_rgb(0, 0, 0));
-    // let mut bitmap = TgaBitmap::with_dimensions(64, 64, u8_rgb(0, 0, 0));
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum MaterialKind {
+    Diffuse,
+    Mirror,
+    Glossy,
+    Glass,
+}
 
-    // // utah teapot settings
-    // let pov = ff32_3::new(ff32(0.0), ff32(0.0), ff32(26.0));
-    // let screen_00 = ff32_3::new(ff32(-0.5), ff32(0.5), ff32(25.0));
-    // let screen_step = ff32(1.0) / ff32::from_usize(bitmap.height());
+#[derive(Debug, Clone)]
+struct Args {
+    in_filename: String,
+    out_filename: String,
 
-    // stanford bunny settings
-    let pov = ff32_3::new(ff32(0.0), ff32(0.0), ff32(306.0));
-    let screen_00 = ff32_3::new(ff32(-0.5), ff32(0.5), ff32(305.0));
-    let screen_step = ff32(1.0) / ff32::from_usize(bitmap.height());
+    model_transform: ff32_affine3,
 
-    let light_dir1 = ff32_3::new(ff32(-1.0), ff32(1.0), ff32(1.0)).norm();
+    width: usize,
+    height: usize,
 
-    for pixel_y in 0..bitmap.height() {
-        for pixel_x in 0..bitmap.width() {
-            let screen_rel = ff32_3::new(
-                screen_step * ff32::from_usize(pixel_x),
-                -screen_step * ff32::from_usize(pixel_y),
-                ff32(0.0),
-            );
+    camera: ff32_3,
+    look_at: ff32_3,
+    up: ff32_3,
+    fov_deg: f32,
+    ortho_height: Option<f32>,
 
-            let screen_p = screen_00 + screen_rel;
+    light_dir: ff32_3,
+    material: MaterialKind,
+    color: f32_rgb,
+    shininess: f32,
+    ior: f32,
+    n_bounces: usize,
+    background: f32_rgb,
 
-            let ray = Ray {
-                src: pov,
-                dir1: (screen_p - pov).norm(),
-            };
+    accel: Accel,
+    n_retries: usize,
+    kd_sah_options: KdSahOptions,
+    tree_cache: Option<String>,
+    seed: u64,
+    max_d: ff32,
+    shadow_min_d: ff32,
+    culling: Culling,
+
+    n_samples: usize,
+    n_threads: usize,
+
+    tone_mapping: ToneMapping,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            in_filename: String::new(),
+            out_filename: String::new(),
+
+            model_transform: ff32_affine3::ONE,
+
+            width: 512,
+            height: 512,
+
+            camera: ff32_3::new(ff32(0.0), ff32(0.0), ff32(306.0)),
+            look_at: ff32_3::ZERO,
+            up: ff32_3::EY,
+            fov_deg: 53.13,
+            ortho_height: None,
+
+            light_dir: ff32_3::new(ff32(-1.0), ff32(1.0), ff32(1.0)),
+            material: MaterialKind::Diffuse,
+            color: f32_rgb(1.0, 1.0, 1.0),
+            shininess: 32.0,
+            ior: 1.5,
+            n_bounces: 4,
+            background: f32_rgb::default(),
+
+            accel: Accel::Bsp,
+            n_retries: 16,
+            kd_sah_options: KdSahOptions::default(),
+            tree_cache: None,
+            seed: 117,
+            max_d: ff32(2000.0),
+            shadow_min_d: ff32(0.01),
+            culling: Culling::Back,
+
+            n_samples: 1,
+            n_threads: TileRenderer::default().n_threads,
+
+            tone_mapping: ToneMapping::default(),
+        }
+    }
+}
 
-            // let isec = cast_ray_through_triangles(ray, &triangles, ff32(2000.0));
-            let isec = bsp_tree.cast_ray(ray, ff32(2000.0));
-            if let Some(isec) = isec {
-                // *bitmap.get_mut(pixel_x, pixel_y) = u8_rgb(255, 0, 0);
-
-                // let isec_meta = interpolate_triangle_meta(&isec);
-                // *bitmap.get_mut(pixel_x, pixel_y) = u8_rgb(
-                //     (isec_meta.n1_p.x() * ff32(127.5) + ff32(127.5)).0 as u8,
-                //     (isec_meta.n1_p.y() * ff32(127.5) + ff32(127.5)).0 as u8,
-                //     0,
-                // );
-
-                // *bitmap.get_mut(pixel_x, pixel_y) = u8_rgb(
-                //     ((isec.d - ff32(1.0)) * ff32(100.0)).0 as u8,
-                //     ((isec.d - ff32(1.0)) * ff32(100.0)).0 as u8,
-                //     ((isec.d - ff32(1.0)) * ff32(100.0)).0 as u8,
-                // );
-
-                let isec_meta = isec.interpolate_meta();
-                let light_dot = ff32_3::dot(light_dir1, isec_meta.n1_p);
-
-                let mut light = ff32(0.2);
-                // let mut light = ff32(0.8) * ff32(light_dot.0.clamp(0.0, 1.0));
-
-                if light_dot > ff32(0.0) {
-                    let light_ray = Ray {
-                        src: ray.src + ray.dir1 * isec.d,
-                        dir1: light_dir1,
-                    };
-
-                    // let light_isec = triangles.cast_ray(light_ray, ff32(1000.0));
-                    let light_isec = bsp_tree.cast_ray(light_ray, ff32(2000.0));
-                    if light_isec.is_none() {
-                        light += ff32(0.8 * light_dot.0.clamp(0.0, 1.0))
-                    }
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid value for {name}: {value:?}"))
+}
+
+fn parse_vector(name: &str, value: &str) -> Result<ff32_3, String> {
+    let coords: Vec<&str> = value.split(',').collect();
+
+    if let [x, y, z] = coords[..] {
+        Ok(ff32_3::new(
+            ff32(parse_number(name, x)?),
+            ff32(parse_number(name, y)?),
+            ff32(parse_number(name, z)?),
+        ))
+    } else {
+        Err(format!("expected X,Y,Z for {name}, got {value:?}"))
+    }
+}
+
+fn parse_color(name: &str, value: &str) -> Result<f32_rgb, String> {
+    let v = parse_vector(name, value)?;
+    let color = f32_rgb(v.x().0, v.y().0, v.z().0);
+
+    if !(color.0 >= 0.0 && color.1 >= 0.0 && color.2 >= 0.0) {
+        return Err(format!("{name}: color components must be non-negative"));
+    }
+
+    Ok(color)
+}
+
+fn parse_rotation(name: &str, value: &str) -> Result<ff32_affine3, String> {
+    let parts: Vec<&str> = value.split(',').collect();
+
+    let [x, y, z, angle_deg] = parts[..] else {
+        return Err(format!("expected X,Y,Z,DEG for {name}, got {value:?}"));
+    };
+
+    let axis = ff32_3::new(
+        ff32(parse_number(name, x)?),
+        ff32(parse_number(name, y)?),
+        ff32(parse_number(name, z)?),
+    );
+    let angle_deg: f32 = parse_number(name, angle_deg)?;
+
+    if axis == ff32_3::ZERO {
+        return Err(format!("rotation axis must be non-zero, got {value:?}"));
+    }
+
+    Ok(ff32_affine3::rotation(axis, ff32(angle_deg.to_radians())))
+}
+
+fn parse_size(name: &str, value: &str) -> Result<(usize, usize), String> {
+    let (width, height) = value
+        .split_once('x')
+        .ok_or_else(|| format!("expected <W>x<H> for {name}, got {value:?}"))?;
+
+    let width = parse_number(name, width)?;
+    let height = parse_number(name, height)?;
+
+    if width == 0 || height == 0 {
+        return Err(format!("image size must be non-zero, got {value:?}"));
+    }
+
+    Ok((width, height))
+}
+
+/// Returns `Ok(None)` if help was requested.
+fn parse_args<I: Iterator<Item = String>>(mut argv: I) -> Result<Option<Args>, String> {
+    let mut args = Args::default();
+    let mut positional = Vec::<String>::new();
+
+    while let Some(arg) = argv.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(None);
+        }
+
+        if !arg.starts_with("--") {
+            positional.push(arg);
+            continue;
+        }
+
+        let value = argv
+            .next()
+            .ok_or_else(|| format!("missing value for {arg}"))?;
+
+        match arg.as_str() {
+            "--size" => (args.width, args.height) = parse_size(&arg, &value)?,
+
+            "--scale" => {
+                let factors = parse_vector(&arg, &value)?;
+
+                if factors.x() == ff32(0.0) || factors.y() == ff32(0.0) || factors.z() == ff32(0.0)
+                {
+                    return Err(format!("scale factors must be non-zero, got {value:?}"));
                 }
 
-                let val = (light * ff32(255.0)).0 as u8;
-                *bitmap.get_mut(pixel_x, pixel_y) = u8_rgb(val, val, val);
+                args.model_transform = ff32_affine3::scaling(factors) * args.model_transform;
+            }
+
+            "--rotate" => {
+                args.model_transform = parse_rotation(&arg, &value)? * args.model_transform;
+            }
+
+            "--translate" => {
+                let shift = parse_vector(&arg, &value)?;
+                args.model_transform = ff32_affine3::translation(shift) * args.model_transform;
+            }
+
+            "--camera" => args.camera = parse_vector(&arg, &value)?,
+            "--look-at" => args.look_at = parse_vector(&arg, &value)?,
+            "--up" => args.up = parse_vector(&arg, &value)?,
+            "--fov" => args.fov_deg = parse_number(&arg, &value)?,
+            "--ortho" => args.ortho_height = Some(parse_number(&arg, &value)?),
+            "--light" => args.light_dir = parse_vector(&arg, &value)?,
+            "--color" => args.color = parse_color(&arg, &value)?,
+            "--shininess" => args.shininess = parse_number(&arg, &value)?,
+            "--ior" => args.ior = parse_number(&arg, &value)?,
+            "--bounces" => args.n_bounces = parse_number(&arg, &value)?,
+            "--background" => args.background = parse_color(&arg, &value)?,
+            "--retries" => args.n_retries = parse_number(&arg, &value)?,
+            "--leaf-size" => args.kd_sah_options.leaf_size = parse_number(&arg, &value)?,
+            "--tree-cache" => args.tree_cache = Some(value),
+            "--seed" => args.seed = parse_number(&arg, &value)?,
+            "--max-distance" => args.max_d = ff32(parse_number(&arg, &value)?),
+            "--shadow-bias" => args.shadow_min_d = ff32(parse_number(&arg, &value)?),
+            "--samples" => args.n_samples = parse_number(&arg, &value)?,
+            "--threads" => args.n_threads = parse_number(&arg, &value)?,
+            "--exposure" => args.tone_mapping.exposure = parse_number(&arg, &value)?,
+
+            "--accel" => {
+                args.accel = match value.as_str() {
+                    "bsp" => Accel::Bsp,
+                    "kd" => Accel::Kd,
+                    "kd-sah" => Accel::KdSah,
+                    "bvh" => Accel::Bvh,
+                    "list" => Accel::List,
+                    _ => return Err(format!("unknown acceleration structure: {value:?}")),
+                }
+            }
+
+            "--material" => {
+                args.material = match value.as_str() {
+                    "diffuse" => MaterialKind::Diffuse,
+                    "mirror" => MaterialKind::Mirror,
+                    "glossy" => MaterialKind::Glossy,
+                    "glass" => MaterialKind::Glass,
+                    _ => return Err(format!("unknown material: {value:?}")),
+                }
+            }
+
+            "--culling" => {
+                args.culling = match value.as_str() {
+                    "back" => Culling::Back,
+                    "front" => Culling::Front,
+                    "none" => Culling::None,
+                    _ => return Err(format!("unknown culling mode: {value:?}")),
+                }
             }
+
+            "--tone-map" => {
+                args.tone_mapping.operator = match value.as_str() {
+                    "clamp" => ToneMapOperator::Clamp,
+                    "reinhard" => ToneMapOperator::Reinhard,
+                    "aces" => ToneMapOperator::Aces,
+                    _ => return Err(format!("unknown tone mapping: {value:?}")),
+                }
+            }
+
+            "--gamma" => {
+                args.tone_mapping.is_srgb = match value.as_str() {
+                    "srgb" => true,
+                    "linear" => false,
+                    _ => return Err(format!("unknown output encoding: {value:?}")),
+                }
+            }
+
+            _ => return Err(format!("unknown option: {arg}")),
+        }
+    }
+
+    match <[String; 2]>::try_from(positional) {
+        Ok([in_filename, out_filename]) => {
+            args.in_filename = in_filename;
+            args.out_filename = out_filename;
+        }
+
+        Err(positional) => {
+            return Err(format!(
+                "expected input and output paths, got {} positional arguments",
+                positional.len()
+            ))
+        }
+    }
+
+    if !(args.fov_deg > 0.0 && args.fov_deg < 180.0) {
+        return Err(format!(
+            "field of view must be in (0; 180), got {}",
+            args.fov_deg
+        ));
+    }
+
+    if let Some(height) = args.ortho_height {
+        if !(height > 0.0 && height.is_finite()) {
+            return Err(format!(
+                "orthographic view height must be positive, got {height}"
+            ));
+        }
+    }
+
+    if args.n_samples == 0 {
+        return Err("number of samples must be non-zero".to_string());
+    }
+
+    if args.n_threads == 0 {
+        return Err("number of threads must be non-zero".to_string());
+    }
+
+    if args.n_retries == 0 {
+        return Err("number of retries must be non-zero".to_string());
+    }
+
+    if args.kd_sah_options.leaf_size == 0 {
+        return Err("leaf size must be non-zero".to_string());
+    }
+
+    if args.light_dir == ff32_3::ZERO {
+        return Err("light direction must be non-zero".to_string());
+    }
+
+    if !(args.shininess >= 0.0 && args.shininess.is_finite()) {
+        return Err(format!(
+            "shininess must be non-negative, got {}",
+            args.shininess
+        ));
+    }
+
+    if !(args.ior > 0.0 && args.ior.is_finite()) {
+        return Err(format!(
+            "index of refraction must be positive, got {}",
+            args.ior
+        ));
+    }
+
+    if args.camera == args.look_at {
+        return Err("camera position and look-at point must differ".to_string());
+    }
+
+    let forward1 = (args.look_at - args.camera).norm();
+    if ff32_3::cross(forward1, args.up).abs() <= ff32::EPS * args.up.abs() {
+        return Err(format!(
+            "up direction must be non-zero and not parallel to the view direction, got {}",
+            args.up
+        ));
+    }
+
+    Ok(Some(args))
+}
+
+fn make_camera(args: &Args) -> Camera<ff32> {
+    let aspect_ratio = ff32::from_usize(args.width) / ff32::from_usize(args.height);
+
+    let projection = match args.ortho_height {
+        Some(height) => Projection::Orthographic {
+            height: ff32(height),
+        },
+        None => Projection::Perspective {
+            fov_y: ff32(args.fov_deg.to_radians()),
+        },
+    };
+
+    Camera::look_at(args.camera, args.look_at, args.up, projection, aspect_ratio)
+}
+
+fn make_shader(args: &Args) -> Shader {
+    let material = match args.material {
+        MaterialKind::Diffuse => Material::Diffuse { albedo: args.color },
+        MaterialKind::Mirror => Material::Mirror { tint: args.color },
+
+        MaterialKind::Glossy => Material::Glossy {
+            albedo: args.color,
+            specular: f32_rgb(0.5, 0.5, 0.5),
+            shininess: args.shininess,
+        },
+
+        MaterialKind::Glass => Material::Glass {
+            ior: args.ior,
+            tint: args.color,
+        },
+    };
+
+    Shader {
+        materials: vec![material],
+        lights: vec![DirectionalLight {
+            dir1: args.light_dir.norm(),
+            radiance: f32_rgb(0.8, 0.8, 0.8),
+        }],
+        ambient: f32_rgb(0.2, 0.2, 0.2),
+        background: args.background,
+        culling: args.culling,
+        max_d: args.max_d,
+        // starting exactly on a surface, rays could hit their own triangle
+        bias: args.shadow_min_d,
+        max_depth: args.n_bounces,
+    }
+}
+
+/// Returns linear radiance, to be tone mapped before writing to 8-bit formats.
+fn render<'a, C: Castable<'a, ff32> + Sync>(args: &Args, castable: &'a C) -> Image<f32_rgb> {
+    let camera = make_camera(args);
+
+    let shader = make_shader(args);
+
+    let mut framebuffer = Framebuffer::new(args.width, args.height);
+
+    // one sample of every pixel per pass, every pass with its own seed
+    for pass in 0..args.n_samples {
+        let renderer = TileRenderer {
+            n_threads: args.n_threads,
+            seed: args.seed.wrapping_add(pass as u64),
+            ..TileRenderer::default()
+        };
+
+        let samples = renderer.render(args.width, args.height, |pixel_x, pixel_y, rng| {
+            let ray = if args.n_samples == 1 {
+                camera.pixel_ray(pixel_x, pixel_y, args.width, args.height)
+            } else {
+                let x = ff32::from_usize(pixel_x) + rng.random();
+                let y = ff32::from_usize(pixel_y) + rng.random();
+
+                camera.image_ray(x, y, args.width, args.height)
+            };
+
+            shader.shade(castable, ray)
+        });
+
+        framebuffer.add_image(&samples);
+    }
+
+    framebuffer.resolve()
+}
+
+fn build_tree(args: &Args, triangles: &TriangleList<ff32>) -> BspTree<ff32> {
+    match args.accel {
+        Accel::Bsp => {
+            let mut rng = SmallRng::seed_from_u64(args.seed);
+            BspTree::build_tri_randomized(&triangles.triangles, &mut rng, args.n_retries)
         }
+
+        Accel::Kd => BspTree::build_kd(&triangles.triangles),
+        Accel::KdSah => BspTree::build_kd_sah(&triangles.triangles, &args.kd_sah_options),
+
+        Accel::Bvh | Accel::List => unreachable!(),
+    }
+}
+
+/// Names the builder and every option that changes what it builds, for `BuildKey`.
+fn builder_name(args: &Args) -> String {
+    match args.accel {
+        Accel::Bsp => format!("bsp seed={} retries={}", args.seed, args.n_retries),
+        Accel::Kd => "kd".to_string(),
+        Accel::KdSah => format!("kd-sah {:?}", args.kd_sah_options),
+        Accel::Bvh => "bvh".to_string(),
+        Accel::List => unreachable!(),
+    }
+}
+
+/// Reuses the `--tree-cache` file if it was saved by the same builder for the same triangles,
+/// already transformed; otherwise builds the structure and saves it, replacing the file.
+fn load_or_build<T>(
+    args: &Args,
+    triangles: &TriangleList<ff32>,
+    read: impl FnOnce(&mut BufReader<File>) -> Result<(T, BuildKey), FormatError>,
+    build: impl FnOnce() -> T,
+    write: impl FnOnce(&T, &mut BufWriter<File>, &BuildKey) -> Result<(), FormatError>,
+) -> Result<T, Box<dyn Error>> {
+    let Some(filename) = &args.tree_cache else {
+        return Ok(build());
+    };
+
+    let key = BuildKey::new(&builder_name(args), &triangles.triangles);
+
+    if let Ok(file) = File::open(filename) {
+        match read(&mut BufReader::new(file)) {
+            Ok((accel, file_key)) if file_key == key => return Ok(accel),
+
+            // saved by another builder or for other triangles
+            Ok(_) => {}
+
+            Err(e) => eprintln!("warning: cannot read {filename}, rebuilding it: {e}"),
+        }
+    }
+
+    let accel = build();
+
+    let file = File::create(filename).map_err(|e| format!("cannot create {filename}: {e}"))?;
+    let mut writer = BufWriter::new(file);
+
+    write(&accel, &mut writer, &key)
+        .and_then(|()| writer.flush().map_err(FormatError::from))
+        .map_err(|e| format!("cannot write {filename}: {e}"))?;
+
+    Ok(accel)
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let in_file = File::options()
+        .read(true)
+        .open(&args.in_filename)
+        .map_err(|e| format!("cannot open {}: {e}", args.in_filename))?;
+    let mut in_file = BufReader::with_capacity(8 * 1024 * 1024, in_file);
+
+    let in_filename = args.in_filename.to_lowercase();
+
+    let mut triangles = if in_filename.ends_with(".obj") {
+        ObjModel::read_from(&mut in_file).map(|model| model.to_triangle_list())
+    } else if in_filename.ends_with(".ply") {
+        PlyModel::read_from(&mut in_file).and_then(|model| model.to_triangle_list())
+    } else {
+        StlModel::read_from(&mut in_file).map(|model| model.to_triangle_list())
+    }
+    .map_err(|e| format!("cannot read {}: {e}", args.in_filename))?;
+
+    if args.model_transform != ff32_affine3::ONE {
+        triangles.transform(&args.model_transform);
+    }
+
+    let image = match args.accel {
+        Accel::Bsp | Accel::Kd | Accel::KdSah => {
+            let build = || build_tree(args, &triangles);
+            let tree = load_or_build(
+                args,
+                &triangles,
+                BspTree::read_from,
+                build,
+                BspTree::write_to,
+            )?;
+            render(args, &tree.with_triangles(&triangles.triangles))
+        }
+
+        Accel::Bvh => {
+            let build = || Bvh::build(&triangles.triangles);
+            let bvh = load_or_build(args, &triangles, Bvh::read_from, build, Bvh::write_to)?;
+            render(args, &bvh.with_triangles(&triangles.triangles))
+        }
+
+        Accel::List => render(args, &triangles),
+    };
+
+    let out_file = File::options()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&args.out_filename)
+        .map_err(|e| format!("cannot create {}: {e}", args.out_filename))?;
+    let mut out_file = BufWriter::with_capacity(8 * 1024 * 1024, out_file);
+
+    let out_filename = args.out_filename.to_lowercase();
+
+    if out_filename.ends_with(".pfm") {
+        image.write_pfm_to(&mut out_file)
+    } else {
+        write_ldr(
+            &image.tone_map(&args.tone_mapping),
+            &out_filename,
+            &mut out_file,
+        )
+    }
+    .map_err(|e| format!("cannot write {}: {e}", args.out_filename))?;
+
+    Ok(())
+}
+
+/// Picks an 8-bit output format by file extension, defaulting to TGA.
+fn write_ldr<W: Write>(
+    image: &Image<u8_rgb>,
+    out_filename: &str,
+    out_file: &mut W,
+) -> Result<(), FormatError> {
+    if out_filename.ends_with(".png") {
+        image.write_png_to(out_file)
+    } else if out_filename.ends_with(".ppm") {
+        image.write_pnm_to(out_file, false)
+    } else if out_filename.ends_with(".pgm") {
+        image.convert::<u8_gray>().write_pnm_to(out_file, false)
+    } else {
+        TgaBitmap::try_from(image.clone())?.write_to(out_file)
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+
+        Ok(None) => {
+            print!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+
+        Err(e) => {
+            eprintln!("error: {e}\n\n{USAGE}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(e) = run(&args) {
+        eprintln!("error: {e}");
+        return ExitCode::FAILURE;
     }
 
-    bitmap.write_to(&mut out_file).unwrap();
+    ExitCode::SUCCESS
 }
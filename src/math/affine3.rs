@@ -0,0 +1,190 @@
+#![allow(non_camel_case_types)]
+
+use super::*;
+
+use std::fmt::Display;
+use std::ops::{Mul, MulAssign};
+
+/// Maps `p` to `mat * p + shift`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine3<T: Num> {
+    pub mat: Matrix3<T>,
+    pub shift: Vector3<T>,
+}
+
+pub type f32_affine3 = Affine3<f32>;
+pub type f64_affine3 = Affine3<f64>;
+
+pub type ff32_affine3 = Affine3<ff32>;
+
+pub type r64_affine3 = Affine3<r64>;
+
+impl<T: Num> Display for Affine3<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} + {}", self.mat, self.shift)
+    }
+}
+
+impl<T: Num> One for Affine3<T> {
+    const ONE: Self = Self {
+        mat: Matrix3::ONE,
+        shift: Vector3::ZERO,
+    };
+}
+
+/// Applies `rhs` first.
+impl<T: Num> Mul<Self> for Affine3<T> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            mat: self.mat * rhs.mat,
+            shift: self.mat * rhs.shift + self.shift,
+        }
+    }
+}
+
+impl<T: Num> MulAssign<Self> for Affine3<T> {
+    #[inline(always)]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<T: Num> Affine3<T> {
+    #[inline(always)]
+    pub fn new(mat: Matrix3<T>, shift: Vector3<T>) -> Self {
+        Self { mat, shift }
+    }
+
+    pub fn translation(shift: Vector3<T>) -> Self {
+        Self::new(Matrix3::ONE, shift)
+    }
+
+    /// Scales each axis by the corresponding coordinate of `factors`.
+    pub fn scaling(factors: Vector3<T>) -> Self {
+        let mat = Matrix3::from_rows(
+            Vector3::EX * factors.x(),
+            Vector3::EY * factors.y(),
+            Vector3::EZ * factors.z(),
+        );
+
+        Self::new(mat, Vector3::ZERO)
+    }
+
+    /// Counterclockwise by `angle` radians when looking against `axis`, which need not be unit.
+    pub fn rotation(axis: Vector3<T>, angle: T) -> Self {
+        let k = axis.norm();
+        let (sin, cos) = (angle.sin(), angle.cos());
+
+        let cross_k = Matrix3::from_rows(
+            Vector3::new(T::ZERO, -k.z(), k.y()),
+            Vector3::new(k.z(), T::ZERO, -k.x()),
+            Vector3::new(-k.y(), k.x(), T::ZERO),
+        );
+        let outer_k = Matrix3::from_rows(k * k.x(), k * k.y(), k * k.z());
+
+        let mat = Matrix3::ONE * cos + cross_k * sin + outer_k * (T::ONE - cos);
+        Self::new(mat, Vector3::ZERO)
+    }
+
+    #[inline(always)]
+    pub fn apply_point(&self, p: Vector3<T>) -> Vector3<T> {
+        self.mat * p + self.shift
+    }
+
+    /// Ignores the shift; directions are not renormalized.
+    #[inline(always)]
+    pub fn apply_dir(&self, v: Vector3<T>) -> Vector3<T> {
+        self.mat * v
+    }
+
+    /// Transforms normals so that they stay perpendicular to transformed surfaces;
+    /// the inverse transpose of `mat`. Normals are not renormalized.
+    pub fn normal_matrix(&self) -> Option<Matrix3<T>> {
+        Some(self.mat.inv()?.tr())
+    }
+
+    /// Whether orientation is reversed, turning counterclockwise triangles clockwise.
+    pub fn is_mirroring(&self) -> bool {
+        self.mat.det() < T::ZERO
+    }
+
+    pub fn inv(&self) -> Option<Self> {
+        let mat = self.mat.inv()?;
+        Some(Self::new(mat, -(mat * self.shift)))
+    }
+}
+
+#[cfg(test)]
+use quickcheck::Arbitrary;
+
+#[cfg(test)]
+impl Arbitrary for Affine3<r64> {
+    fn arbitrary(g: &mut quickcheck::Gen) -> Self {
+        Self::new(r64_3x3::arbitrary(g), r64_3::arbitrary(g))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use quickcheck_macros::quickcheck;
+
+    #[quickcheck]
+    fn composition(a: r64_affine3, b: r64_affine3, c: r64_affine3, p: r64_3) -> bool {
+        (a * r64_affine3::ONE == a)
+            && (r64_affine3::ONE * a == a)
+            && ((a * b) * c == a * (b * c))
+            && ((a * b).apply_point(p) == a.apply_point(b.apply_point(p)))
+    }
+
+    #[quickcheck]
+    fn inversion(a: r64_affine3, p: r64_3) -> bool {
+        (r64_affine3::ONE.inv() == Some(r64_affine3::ONE))
+            && (a.inv().is_none() || (a.inv().unwrap() * a == r64_affine3::ONE))
+            && (a.inv().is_none() || a.inv().unwrap().apply_point(a.apply_point(p)) == p)
+    }
+
+    #[quickcheck]
+    fn normal_matrix(a: r64_affine3, n: r64_3, v: r64_3) -> bool {
+        match a.normal_matrix() {
+            Some(normal_mat) => r64_3::dot(normal_mat * n, a.apply_dir(v)) == r64_3::dot(n, v),
+            None => a.inv().is_none(),
+        }
+    }
+
+    #[quickcheck]
+    fn translation_and_scaling(shift: r64_3, factors: r64_3, p: r64_3) -> bool {
+        (r64_affine3::translation(shift).apply_point(p) == p + shift)
+            && (r64_affine3::translation(shift).apply_dir(p) == p)
+            && (r64_affine3::scaling(factors).apply_point(p).x() == p.x() * factors.x())
+            && (r64_affine3::scaling(factors).apply_point(p).z() == p.z() * factors.z())
+    }
+
+    #[test]
+    fn rotation() {
+        let quarter = f64_affine3::rotation(f64_3::EZ * 3.0, std::f64::consts::FRAC_PI_2);
+        assert!((quarter.apply_dir(f64_3::EX) - f64_3::EY).abs() < 1e-12);
+        assert!((quarter.apply_dir(f64_3::EY) + f64_3::EX).abs() < 1e-12);
+        assert!((quarter.apply_dir(f64_3::EZ) - f64_3::EZ).abs() < 1e-12);
+
+        let axis = f64_3::new(1.0, -2.0, 0.5);
+        let r = f64_affine3::rotation(axis, 0.7);
+        let v = f64_3::new(0.3, 4.0, -1.0);
+
+        assert!((r.apply_dir(v).abs() - v.abs()).abs() < 1e-12);
+        assert!((r.apply_dir(axis) - axis).abs() < 1e-12);
+        assert!((r.mat.det() - 1.0).abs() < 1e-12);
+        assert!(!r.is_mirroring());
+
+        // orthogonal, so normals transform like directions
+        let normal_mat = r.normal_matrix().unwrap();
+        assert!((normal_mat * v - r.apply_dir(v)).abs() < 1e-12);
+
+        let back = f64_affine3::rotation(axis, -0.7);
+        assert!(((back * r).apply_point(v) - v).abs() < 1e-12);
+    }
+}
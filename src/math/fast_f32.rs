@@ -47,6 +47,13 @@ impl Num for ff32 {
     }
 }
 
+impl Float for ff32 {
+    #[inline(always)]
+    fn acos(self) -> ff32 {
+        ff32(self.0.acos())
+    }
+}
+
 impl Zero for ff32 {
     const ZERO: Self = ff32(0.0);
 }
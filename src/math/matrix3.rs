@@ -175,7 +175,7 @@ impl<T: Num> Matrix3<T> {
     pub fn det(&self) -> T {
         let d00 = T::ZERO + (self.1 .1 * self.2 .2 - self.1 .2 * self.2 .1);
         let d01 = T::ZERO - (self.1 .0 * self.2 .2 - self.1 .2 * self.2 .0);
-        let d02 = T::ZERO + (self.0 .0 * self.1 .1 - self.0 .1 * self.1 .0);
+        let d02 = T::ZERO + (self.1 .0 * self.2 .1 - self.1 .1 * self.2 .0);
 
         let det = self.0 .0 * d00 + self.0 .1 * d01 + self.0 .2 * d02;
 
@@ -248,6 +248,14 @@ mod tests {
             && (m * r64_3::ZERO == r64_3::ZERO)
     }
 
+    #[quickcheck]
+    fn determinant(a: r64_3x3, b: r64_3x3) -> bool {
+        (r64_3x3::ONE.det() == r64::ONE)
+            && ((a * b).det() == a.det() * b.det())
+            && (a.tr().det() == a.det())
+            && ((a.det() == r64::ZERO) == a.inv().is_none())
+    }
+
     #[quickcheck]
     fn inversion(m: r64_3x3) -> bool {
         (r64_3x3::ONE.inv() == Some(r64_3x3::ONE))
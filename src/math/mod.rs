@@ -1,19 +1,25 @@
 #[macro_use]
 mod util_macros;
 
+mod affine3;
 mod fast_f32;
 #[macro_use]
 mod linear_space;
 mod matrix3;
 mod num;
+mod quaternion;
 mod random;
+mod simd;
 mod small_ratio;
 mod vector3;
 
+pub use affine3::*;
 pub use fast_f32::*;
 pub use linear_space::*;
 pub use matrix3::*;
 pub use num::*;
+pub use quaternion::*;
 pub use random::*;
+pub use simd::*;
 pub use small_ratio::*;
 pub use vector3::*;
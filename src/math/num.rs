@@ -30,6 +30,11 @@ pub trait Num:
     fn cos(self) -> Self;
 }
 
+/// Floating-point numbers, with functions that rationals have no use for.
+pub trait Float: Num {
+    fn acos(self) -> Self;
+}
+
 pub trait Zero {
     const ZERO: Self;
 }
@@ -77,6 +82,13 @@ macro_rules! impl_float {
                 $T::cos(self)
             }
         }
+
+        impl Float for $T {
+            #[inline(always)]
+            fn acos(self) -> Self {
+                $T::acos(self)
+            }
+        }
     };
 }
 
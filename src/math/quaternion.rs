@@ -0,0 +1,394 @@
+#![allow(non_camel_case_types)]
+
+use super::*;
+
+use std::fmt::Display;
+use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Scalar and vector parts. Unit quaternions represent rotations;
+/// `q` and `-q` represent the same one.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Quaternion<T: Num>(pub T, pub Vector3<T>);
+
+pub type f32_quat = Quaternion<f32>;
+pub type f64_quat = Quaternion<f64>;
+
+pub type ff32_quat = Quaternion<ff32>;
+
+pub type r64_quat = Quaternion<r64>;
+
+impl<T: Num> LinearSpace for Quaternion<T> {
+    type Scalar = T;
+}
+
+impl<T: Num> Display for Quaternion<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "({}, {})", self.0, self.1)
+    }
+}
+
+impl<T: Num> Neg for Quaternion<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn neg(self) -> Self {
+        Self(-self.0, -self.1)
+    }
+}
+
+impl<T: Num> Add<Self> for Quaternion<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+impl<T: Num> Sub<Self> for Quaternion<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+
+impl<T: Num> AddAssign<Self> for Quaternion<T> {
+    #[inline(always)]
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<T: Num> SubAssign<Self> for Quaternion<T> {
+    #[inline(always)]
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<T: Num> Mul<T> for Quaternion<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn mul(self, rhs: T) -> Self {
+        Self(self.0 * rhs, self.1 * rhs)
+    }
+}
+
+impl<T: Num> Div<T> for Quaternion<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn div(self, rhs: T) -> Self {
+        Self(self.0 / rhs, self.1 / rhs)
+    }
+}
+
+impl<T: Num> MulAssign<T> for Quaternion<T> {
+    #[inline(always)]
+    fn mul_assign(&mut self, rhs: T) {
+        *self = *self * rhs;
+    }
+}
+
+impl<T: Num> DivAssign<T> for Quaternion<T> {
+    #[inline(always)]
+    fn div_assign(&mut self, rhs: T) {
+        *self = *self / rhs;
+    }
+}
+
+impl<T: Num> Zero for Quaternion<T> {
+    const ZERO: Self = Self(T::ZERO, Vector3::ZERO);
+}
+
+impl<T: Num> One for Quaternion<T> {
+    const ONE: Self = Self(T::ONE, Vector3::ZERO);
+}
+
+/// Hamilton product; as rotations, `rhs` applies first.
+impl<T: Num> Mul<Self> for Quaternion<T> {
+    type Output = Self;
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        Self(
+            self.0 * rhs.0 - Vector3::dot(self.1, rhs.1),
+            rhs.1 * self.0 + self.1 * rhs.0 + Vector3::cross(self.1, rhs.1),
+        )
+    }
+}
+
+impl<T: Num> MulAssign<Self> for Quaternion<T> {
+    #[inline(always)]
+    fn mul_assign(&mut self, rhs: Self) {
+        *self = *self * rhs;
+    }
+}
+
+impl<T: Num> Quaternion<T> {
+    /// Counterclockwise by `angle` radians when looking against `axis`, which need not be unit.
+    pub fn from_axis_angle(axis: Vector3<T>, angle: T) -> Self {
+        let half = angle / (T::ONE + T::ONE);
+        Self(half.cos(), axis.norm() * half.sin())
+    }
+
+    /// Rotates around the fixed X axis first, then Y, then Z.
+    pub fn from_euler(x_angle: T, y_angle: T, z_angle: T) -> Self {
+        Self::from_axis_angle(Vector3::EZ, z_angle)
+            * Self::from_axis_angle(Vector3::EY, y_angle)
+            * Self::from_axis_angle(Vector3::EX, x_angle)
+    }
+
+    /// `m` must be a rotation matrix; picks the largest component to divide by,
+    /// which keeps the result accurate for any angle.
+    pub fn from_matrix3(m: &Matrix3<T>) -> Self {
+        let (m00, m01, m02) = (m.0 .0, m.0 .1, m.0 .2);
+        let (m10, m11, m12) = (m.1 .0, m.1 .1, m.1 .2);
+        let (m20, m21, m22) = (m.2 .0, m.2 .1, m.2 .2);
+
+        let two = T::ONE + T::ONE;
+        let four = two + two;
+
+        if m00 + m11 + m22 > T::ZERO {
+            let s = (T::ONE + m00 + m11 + m22).sqrt() * two;
+            Self(s / four, Vector3::new(m21 - m12, m02 - m20, m10 - m01) / s)
+        } else if m00 > m11 && m00 > m22 {
+            let s = (T::ONE + m00 - m11 - m22).sqrt() * two;
+            Self(
+                (m21 - m12) / s,
+                Vector3::new(s * s / four, m01 + m10, m02 + m20) / s,
+            )
+        } else if m11 > m22 {
+            let s = (T::ONE + m11 - m00 - m22).sqrt() * two;
+            Self(
+                (m02 - m20) / s,
+                Vector3::new(m01 + m10, s * s / four, m12 + m21) / s,
+            )
+        } else {
+            let s = (T::ONE + m22 - m00 - m11).sqrt() * two;
+            Self(
+                (m10 - m01) / s,
+                Vector3::new(m02 + m20, m12 + m21, s * s / four) / s,
+            )
+        }
+    }
+
+    #[inline(always)]
+    pub fn dot(a: Self, b: Self) -> T {
+        a.0 * b.0 + Vector3::dot(a.1, b.1)
+    }
+
+    #[inline(always)]
+    pub fn conj(&self) -> Self {
+        Self(self.0, -self.1)
+    }
+
+    #[inline(always)]
+    pub fn abs2(&self) -> T {
+        Self::dot(*self, *self)
+    }
+
+    #[inline(always)]
+    pub fn abs(&self) -> T {
+        self.abs2().sqrt()
+    }
+
+    #[inline(always)]
+    pub fn norm(self) -> Self {
+        self / self.abs()
+    }
+
+    pub fn inv(&self) -> Option<Self> {
+        let abs2 = self.abs2();
+
+        if abs2 == T::ZERO {
+            return None;
+        }
+
+        Some(self.conj() / abs2)
+    }
+
+    /// `q * v * q.inv()`; non-unit quaternions rotate the same as their normalized versions.
+    #[inline(always)]
+    pub fn rotate(&self, v: Vector3<T>) -> Vector3<T> {
+        let u = self.1;
+        let uv = Vector3::cross(u, v);
+        let uuv = Vector3::cross(u, uv);
+
+        let two = T::ONE + T::ONE;
+        v + (uv * self.0 + uuv) * (two / self.abs2())
+    }
+
+    pub fn to_matrix3(&self) -> Matrix3<T> {
+        Matrix3::from_cols(
+            self.rotate(Vector3::EX),
+            self.rotate(Vector3::EY),
+            self.rotate(Vector3::EZ),
+        )
+    }
+}
+
+impl<T: Float> Quaternion<T> {
+    /// Interpolates unit quaternions at a constant angular speed, along the shorter arc;
+    /// `t` goes from 0 at `a` to 1 at `b`.
+    pub fn slerp(a: Self, b: Self, t: T) -> Self {
+        let mut b = b;
+        let mut cos = Self::dot(a, b);
+
+        if cos < T::ZERO {
+            b = -b;
+            cos = -cos;
+        }
+
+        // too close for the angle to be accurate; also avoids dividing by zero
+        if cos > T::ONE - T::EPS {
+            return (a * (T::ONE - t) + b * t).norm();
+        }
+
+        let angle = cos.acos();
+        let sin = angle.sin();
+
+        a * (((T::ONE - t) * angle).sin() / sin) + b * ((t * angle).sin() / sin)
+    }
+}
+
+#[cfg(test)]
+use quickcheck::Arbitrary;
+
+#[cfg(test)]
+impl Arbitrary for Quaternion<r64> {
+    fn arbitrary(g: &mut quickcheck::Gen) -> Self {
+        Self(r64::arbitrary(g), r64_3::arbitrary(g))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use quickcheck_macros::quickcheck;
+
+    LinearSpace_tests!(Quaternion);
+
+    #[quickcheck]
+    fn multiplication(a: r64_quat, b: r64_quat, c: r64_quat) -> bool {
+        (a * r64_quat::ONE == a)
+            && (r64_quat::ONE * a == a)
+            && ((a * b) * c == a * (b * c))
+            && (a * (b + c) == a * b + a * c)
+    }
+
+    #[quickcheck]
+    fn conjugation(a: r64_quat, b: r64_quat) -> bool {
+        ((a * b).conj() == b.conj() * a.conj())
+            && (a * a.conj() == r64_quat::ONE * a.abs2())
+            && ((a * b).abs2() == a.abs2() * b.abs2())
+            && (a.inv().is_none() || a * a.inv().unwrap() == r64_quat::ONE)
+    }
+
+    #[quickcheck]
+    fn rotation(a: r64_quat, b: r64_quat, v: r64_3) -> bool {
+        if a.abs2() == r64::ZERO || b.abs2() == r64::ZERO {
+            return true;
+        }
+
+        let as_product = (a * Quaternion(r64::ZERO, v) * a.inv().unwrap()).1;
+
+        (a.rotate(v) == as_product)
+            && (a.rotate(v) == a.to_matrix3() * v)
+            && (a.rotate(v).abs2() == v.abs2())
+            && ((a * b).rotate(v) == a.rotate(b.rotate(v)))
+            && (a.to_matrix3().det() == r64::ONE)
+    }
+
+    fn assert_close(a: f64_quat, b: f64_quat) {
+        assert!((a - b).abs() < 1e-12, "{a} != {b}");
+    }
+
+    #[test]
+    fn from_axis_angle() {
+        let axis = f64_3::new(1.0, -2.0, 0.5);
+        let q = f64_quat::from_axis_angle(axis, 0.7);
+        let v = f64_3::new(0.3, 4.0, -1.0);
+
+        assert!((q.abs() - 1.0).abs() < 1e-12);
+
+        let expected = f64_affine3::rotation(axis, 0.7);
+        assert!((q.rotate(v) - expected.apply_dir(v)).abs() < 1e-12);
+        for e in [f64_3::EX, f64_3::EY, f64_3::EZ] {
+            assert!((q.to_matrix3() * e - expected.apply_dir(e)).abs() < 1e-12);
+        }
+
+        // a full turn flips the sign, but not the rotation
+        let full = f64_quat::from_axis_angle(axis, 2.0 * std::f64::consts::PI);
+        assert_close(full, -f64_quat::ONE);
+        assert!((full.rotate(v) - v).abs() < 1e-12);
+    }
+
+    #[test]
+    fn from_matrix3() {
+        let v = f64_3::new(0.3, 4.0, -1.0);
+
+        // near and far from the identity, around each axis, and past half a turn
+        for (axis, angle) in [
+            (f64_3::new(1.0, -2.0, 0.5), 0.7),
+            (f64_3::EX, 3.0),
+            (f64_3::EY, -3.0),
+            (f64_3::EZ, 3.1),
+            (f64_3::new(1.0, 1.0, 1.0), 5.0),
+            (f64_3::EX, 0.0),
+        ] {
+            let q = f64_quat::from_axis_angle(axis, angle);
+            let from_matrix = f64_quat::from_matrix3(&q.to_matrix3());
+
+            assert!((from_matrix.abs() - 1.0).abs() < 1e-12);
+            assert!(
+                f64_quat::dot(q, from_matrix).abs() > 1.0 - 1e-12,
+                "{q} {from_matrix}"
+            );
+            assert!((from_matrix.rotate(v) - q.rotate(v)).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn from_euler() {
+        let q = f64_quat::from_euler(0.3, -1.1, 2.0);
+        let v = f64_3::new(0.3, 4.0, -1.0);
+
+        let expected = f64_affine3::rotation(f64_3::EZ, 2.0)
+            * f64_affine3::rotation(f64_3::EY, -1.1)
+            * f64_affine3::rotation(f64_3::EX, 0.3);
+        assert!((q.rotate(v) - expected.apply_dir(v)).abs() < 1e-12);
+
+        // pitching straight up merges the X and Z axes, but the quaternion stays well-defined
+        let locked = f64_quat::from_euler(0.4, std::f64::consts::FRAC_PI_2, 0.0);
+        let same = f64_quat::from_euler(0.0, std::f64::consts::FRAC_PI_2, -0.4);
+        assert_close(locked, same);
+    }
+
+    #[test]
+    fn slerp() {
+        let a = f64_quat::from_axis_angle(f64_3::new(0.2, 1.0, 0.0), 0.3);
+        let b = f64_quat::from_axis_angle(f64_3::new(0.2, 1.0, 0.0), 1.9);
+
+        assert_close(f64_quat::slerp(a, b, 0.0), a);
+        assert_close(f64_quat::slerp(a, b, 1.0), b);
+        assert_close(
+            f64_quat::slerp(a, b, 0.25),
+            f64_quat::from_axis_angle(f64_3::new(0.2, 1.0, 0.0), 0.7),
+        );
+
+        // `-b` is the same rotation, so the path is too
+        assert_close(f64_quat::slerp(a, -b, 0.25), f64_quat::slerp(a, b, 0.25));
+
+        // the shorter way from 0.3 to 6.0 radians is backwards through zero
+        let c = f64_quat::from_axis_angle(f64_3::EZ, 6.0);
+        let d = f64_quat::from_axis_angle(f64_3::EZ, 0.3);
+        let mid = f64_quat::slerp(c, d, 0.5);
+        let v = f64_quat::from_axis_angle(f64_3::EZ, 3.15 - std::f64::consts::PI);
+        assert!((mid.rotate(f64_3::EX) - v.rotate(f64_3::EX)).abs() < 1e-12);
+
+        // nearly equal ends
+        let e = f64_quat::from_axis_angle(f64_3::EX, 1e-9);
+        let halfway = f64_quat::slerp(f64_quat::ONE, e, 0.5);
+        assert!((halfway.abs() - 1.0).abs() < 1e-12);
+        assert_close(halfway, f64_quat::from_axis_angle(f64_3::EX, 0.5e-9));
+    }
+}
@@ -0,0 +1,144 @@
+use super::*;
+
+use std::ops::{Add, Mul, Neg, Sub};
+use std::simd::prelude::*;
+
+/// Numbers stored as `f32`, so that several of them can be processed at once in SIMD lanes.
+pub trait SimdNum: Num {
+    fn to_f32(self) -> f32;
+    fn from_f32(x: f32) -> Self;
+}
+
+impl SimdNum for f32 {
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        self
+    }
+
+    #[inline(always)]
+    fn from_f32(x: f32) -> Self {
+        x
+    }
+}
+
+impl SimdNum for ff32 {
+    #[inline(always)]
+    fn to_f32(self) -> f32 {
+        self.0
+    }
+
+    #[inline(always)]
+    fn from_f32(x: f32) -> Self {
+        ff32(x)
+    }
+}
+
+/// `L` vectors, one per SIMD lane.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vector3Simd<const L: usize>(pub Simd<f32, L>, pub Simd<f32, L>, pub Simd<f32, L>);
+
+impl<const L: usize> Vector3Simd<L> {
+    /// The same vector in every lane.
+    #[inline(always)]
+    pub fn splat<N: SimdNum>(v: Vector3<N>) -> Self {
+        Self(
+            Simd::splat(v.0.to_f32()),
+            Simd::splat(v.1.to_f32()),
+            Simd::splat(v.2.to_f32()),
+        )
+    }
+
+    #[inline(always)]
+    pub fn from_lanes<N: SimdNum>(vs: [Vector3<N>; L]) -> Self {
+        Self(
+            Simd::from_array(vs.map(|v| v.0.to_f32())),
+            Simd::from_array(vs.map(|v| v.1.to_f32())),
+            Simd::from_array(vs.map(|v| v.2.to_f32())),
+        )
+    }
+
+    #[inline(always)]
+    pub fn lane<N: SimdNum>(&self, i: usize) -> Vector3<N> {
+        Vector3(
+            N::from_f32(self.0[i]),
+            N::from_f32(self.1[i]),
+            N::from_f32(self.2[i]),
+        )
+    }
+
+    #[inline(always)]
+    pub fn x(&self) -> Simd<f32, L> {
+        self.0
+    }
+
+    #[inline(always)]
+    pub fn y(&self) -> Simd<f32, L> {
+        self.1
+    }
+
+    #[inline(always)]
+    pub fn z(&self) -> Simd<f32, L> {
+        self.2
+    }
+
+    #[inline(always)]
+    pub fn dot(a: Self, b: Self) -> Simd<f32, L> {
+        a.0 * b.0 + a.1 * b.1 + a.2 * b.2
+    }
+
+    /// `m * v` in every lane.
+    #[inline(always)]
+    pub fn transform<N: SimdNum>(m: &Matrix3<N>, v: Self) -> Self {
+        Self(
+            Self::dot(Self::splat(m.0), v),
+            Self::dot(Self::splat(m.1), v),
+            Self::dot(Self::splat(m.2), v),
+        )
+    }
+
+    /// Takes lanes from `a` where `mask` is set, and from `b` elsewhere.
+    #[inline(always)]
+    pub fn select(mask: Mask<i32, L>, a: Self, b: Self) -> Self {
+        Self(
+            mask.select(a.0, b.0),
+            mask.select(a.1, b.1),
+            mask.select(a.2, b.2),
+        )
+    }
+}
+
+impl<const L: usize> Neg for Vector3Simd<L> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn neg(self) -> Self {
+        Self(-self.0, -self.1, -self.2)
+    }
+}
+
+impl<const L: usize> Add<Self> for Vector3Simd<L> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        Self(self.0 + rhs.0, self.1 + rhs.1, self.2 + rhs.2)
+    }
+}
+
+impl<const L: usize> Sub<Self> for Vector3Simd<L> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 - rhs.0, self.1 - rhs.1, self.2 - rhs.2)
+    }
+}
+
+impl<const L: usize> Mul<Simd<f32, L>> for Vector3Simd<L> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Simd<f32, L>) -> Self {
+        Self(self.0 * rhs, self.1 * rhs, self.2 * rhs)
+    }
+}
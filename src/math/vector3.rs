@@ -35,6 +35,30 @@ impl<T: Num> Vector3<T> {
     pub fn z(&self) -> T {
         self.2
     }
+
+    /// 0 is x, 1 is y, 2 is z
+    #[inline(always)]
+    pub fn axis(&self, axis: usize) -> T {
+        match axis {
+            0 => self.0,
+            1 => self.1,
+            2 => self.2,
+
+            _ => panic!("axis index out of range: {axis}"),
+        }
+    }
+
+    /// Copy with one coordinate replaced; 0 is x, 1 is y, 2 is z
+    #[inline(always)]
+    pub fn with_axis(self, axis: usize, value: T) -> Self {
+        match axis {
+            0 => Self(value, self.1, self.2),
+            1 => Self(self.0, value, self.2),
+            2 => Self(self.0, self.1, value),
+
+            _ => panic!("axis index out of range: {axis}"),
+        }
+    }
 }
 
 impl<T: Num> LinearSpace for Vector3<T> {
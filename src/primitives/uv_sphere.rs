@@ -55,6 +55,8 @@ impl<N: Num> Vertex<N> {
                     Vector3::new(vb.long, vb.lat, N::ZERO),
                     Vector3::new(vc.long, vc.lat, N::ZERO),
                 ),
+
+                material: 0,
             }),
         }
     }
@@ -0,0 +1,5 @@
+mod shading;
+mod tile_renderer;
+
+pub use shading::*;
+pub use tile_renderer::*;
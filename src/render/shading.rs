@@ -0,0 +1,236 @@
+use crate::cast::*;
+use crate::image::*;
+use crate::math::*;
+
+/// How a surface reflects and transmits light.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Material {
+    /// Lambertian, scattering light equally in all directions.
+    Diffuse { albedo: f32_rgb },
+
+    /// Perfect mirror.
+    Mirror { tint: f32_rgb },
+
+    /// Lambertian plus a Blinn-Phong highlight; higher `shininess` gives smaller highlights.
+    Glossy {
+        albedo: f32_rgb,
+        specular: f32_rgb,
+        shininess: f32,
+    },
+
+    /// Dielectric like glass or water, splitting rays into reflected and refracted ones
+    /// by the Fresnel equations. Meshes must be closed, with normals pointing outside.
+    Glass { ior: f32, tint: f32_rgb },
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::Diffuse {
+            albedo: f32_rgb(1.0, 1.0, 1.0),
+        }
+    }
+}
+
+/// Light coming from one direction, as from the sun.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DirectionalLight {
+    /// unit-length direction towards the light
+    pub dir1: ff32_3,
+
+    pub radiance: f32_rgb,
+}
+
+/// Evaluates materials at ray hits, following mirror and glass rays.
+#[derive(Debug, Clone)]
+pub struct Shader {
+    /// indexed by `RayIntersection::material`; indices past the end get `Material::default()`
+    pub materials: Vec<Material>,
+
+    pub lights: Vec<DirectionalLight>,
+
+    /// light reaching every point, in shadow or not
+    pub ambient: f32_rgb,
+
+    /// radiance of rays that hit nothing
+    pub background: f32_rgb,
+
+    /// triangle sides camera rays ignore; reflected and refracted rays hit both
+    pub culling: Culling,
+
+    pub max_d: ff32,
+
+    /// rays starting on a surface ignore hits closer than this, not to hit it again
+    pub bias: ff32,
+
+    /// how many times mirror and glass rays are followed; deeper ones are black
+    pub max_depth: usize,
+}
+
+impl Default for Shader {
+    fn default() -> Self {
+        Self {
+            materials: vec![Material::default()],
+            lights: Vec::new(),
+            ambient: f32_rgb::default(),
+            background: f32_rgb::default(),
+            culling: Culling::Back,
+            max_d: ff32(2000.0),
+            bias: ff32(0.01),
+            max_depth: 4,
+        }
+    }
+}
+
+/// Mirrors `dir` about the plane with normal `n1`.
+pub fn reflect<N: Num>(dir: Vector3<N>, n1: Vector3<N>) -> Vector3<N> {
+    dir - n1 * ((N::ONE + N::ONE) * Vector3::dot(dir, n1))
+}
+
+/// Bends a unit direction going through a boundary with normal `n1` facing it;
+/// `eta` is the ratio of refractive indices, the one on the side of `n1` to the other one.
+/// `None` on total internal reflection.
+pub fn refract<N: Num>(dir1: Vector3<N>, n1: Vector3<N>, eta: N) -> Option<Vector3<N>> {
+    let cos_i = -Vector3::dot(dir1, n1);
+    let sin2_t = eta * eta * (N::ONE - cos_i * cos_i);
+
+    if sin2_t > N::ONE {
+        return None;
+    }
+
+    let cos_t = (N::ONE - sin2_t).sqrt();
+    Some(dir1 * eta + n1 * (eta * cos_i - cos_t))
+}
+
+/// Fraction of unpolarized light reflected by a dielectric boundary, for the cosine
+/// of the angle of incidence and `eta` as in `refract`.
+pub fn fresnel_reflectance<N: Num>(cos_i: N, eta: N) -> N {
+    let cos_i = cos_i.abs();
+    let sin2_t = eta * eta * (N::ONE - cos_i * cos_i);
+
+    // total internal reflection
+    if sin2_t >= N::ONE {
+        return N::ONE;
+    }
+
+    let cos_t = (N::ONE - sin2_t).sqrt();
+    let r_s = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    let r_p = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+
+    (r_s * r_s + r_p * r_p) / (N::ONE + N::ONE)
+}
+
+impl Shader {
+    /// Linear radiance arriving along a camera ray.
+    pub fn shade<'a, C: Castable<'a, ff32>>(&self, castable: &'a C, ray: Ray<ff32>) -> f32_rgb {
+        self.trace(castable, ray, ff32(0.0), self.culling, 0)
+    }
+
+    fn trace<'a, C: Castable<'a, ff32>>(
+        &self,
+        castable: &'a C,
+        ray: Ray<ff32>,
+        min_d: ff32,
+        culling: Culling,
+        depth: usize,
+    ) -> f32_rgb {
+        let Some(isec) = castable.cast_ray_culled(ray, min_d, self.max_d, culling) else {
+            return self.background;
+        };
+
+        let p = ray.src + ray.dir1 * isec.d;
+        let n1_p = isec.interpolate_meta().n1_p;
+
+        // shade the side the ray came from
+        let n1 = if isec.is_front_face { n1_p } else { -n1_p };
+
+        let secondary = |dir1: ff32_3| {
+            if depth >= self.max_depth {
+                return f32_rgb::default();
+            }
+
+            let ray = Ray { src: p, dir1 };
+            self.trace(castable, ray, self.bias, Culling::None, depth + 1)
+        };
+
+        let material = self.materials.get(isec.material()).copied();
+
+        match material.unwrap_or_default() {
+            Material::Diffuse { albedo } => {
+                let (diffuse, _) = self.direct_light(castable, p, n1, None);
+                albedo * diffuse
+            }
+
+            Material::Glossy {
+                albedo,
+                specular,
+                shininess,
+            } => {
+                let (diffuse, highlight) =
+                    self.direct_light(castable, p, n1, Some((-ray.dir1, shininess)));
+                albedo * diffuse + specular * highlight
+            }
+
+            Material::Mirror { tint } => tint * secondary(reflect(ray.dir1, n1)),
+
+            Material::Glass { ior, tint } => {
+                let eta = if isec.is_front_face {
+                    ff32(1.0 / ior)
+                } else {
+                    ff32(ior)
+                };
+
+                let cos_i = -ff32_3::dot(ray.dir1, n1);
+                let reflectance = fresnel_reflectance(cos_i, eta).0;
+
+                let mut color = secondary(reflect(ray.dir1, n1)) * reflectance;
+
+                if let Some(refracted) = refract(ray.dir1, n1, eta) {
+                    color += secondary(refracted.norm()) * (1.0 - reflectance);
+                }
+
+                tint * color
+            }
+        }
+    }
+
+    /// Lambertian and, given the direction towards the viewer and shininess,
+    /// Blinn-Phong terms of the ambient and unoccluded light.
+    fn direct_light<'a, C: Castable<'a, ff32>>(
+        &self,
+        castable: &'a C,
+        p: ff32_3,
+        n1: ff32_3,
+        view: Option<(ff32_3, f32)>,
+    ) -> (f32_rgb, f32_rgb) {
+        let mut diffuse = self.ambient;
+        let mut highlight = f32_rgb::default();
+
+        for light in &self.lights {
+            let light_dot = ff32_3::dot(light.dir1, n1);
+
+            if light_dot <= ff32(0.0) {
+                continue;
+            }
+
+            let light_ray = Ray {
+                src: p,
+                dir1: light.dir1,
+            };
+
+            if castable.is_occluded(light_ray, self.bias, self.max_d) {
+                continue;
+            }
+
+            diffuse += light.radiance * light_dot.0;
+
+            if let Some((view1, shininess)) = view {
+                let half1 = (light.dir1 + view1).norm();
+                let half_dot = ff32_3::dot(half1, n1).0.max(0.0);
+
+                highlight += light.radiance * half_dot.powf(shininess);
+            }
+        }
+
+        (diffuse, highlight)
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+
+use crate::image::*;
+
+/// Renders an image in square tiles on a pool of scoped threads.
+///
+/// Every tile gets its own RNG, seeded from `seed` and the tile index,
+/// and its pixels are shaded in a fixed order; so the output does not depend
+/// on the number of threads or on which thread picks up which tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileRenderer {
+    /// side of a tile in pixels; tiles at the right and bottom edges may be smaller
+    pub tile_size: usize,
+
+    pub n_threads: usize,
+
+    pub seed: u64,
+}
+
+impl Default for TileRenderer {
+    fn default() -> Self {
+        Self {
+            tile_size: 32,
+            n_threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            seed: 0,
+        }
+    }
+}
+
+/// Rectangle of pixels with its top left corner at `x`, `y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    pub index: usize,
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// SplitMix64 finalizer; neighbouring tile indices give unrelated seeds.
+fn mix_seed(seed: u64, index: usize) -> u64 {
+    let mut z = seed.wrapping_add((index as u64 + 1).wrapping_mul(0x9e3779b97f4a7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+impl TileRenderer {
+    /// Tiles in row-major order.
+    pub fn tiles(&self, width: usize, height: usize) -> Vec<Tile> {
+        assert!(self.tile_size > 0);
+
+        let mut tiles = Vec::new();
+
+        for y in (0..height).step_by(self.tile_size) {
+            for x in (0..width).step_by(self.tile_size) {
+                tiles.push(Tile {
+                    index: tiles.len(),
+                    x,
+                    y,
+                    width: self.tile_size.min(width - x),
+                    height: self.tile_size.min(height - y),
+                });
+            }
+        }
+
+        tiles
+    }
+
+    pub fn tile_rng(&self, tile: &Tile) -> SmallRng {
+        SmallRng::seed_from_u64(mix_seed(self.seed, tile.index))
+    }
+
+    /// Calls `shade(pixel_x, pixel_y, rng)` once for every pixel,
+    /// with `rng` shared by the pixels of one tile.
+    pub fn render<P, F>(&self, width: usize, height: usize, shade: F) -> Image<P>
+    where
+        P: Copy + Default + Send,
+        F: Fn(usize, usize, &mut SmallRng) -> P + Sync,
+    {
+        let tiles = self.tiles(width, height);
+
+        let next_tile = AtomicUsize::new(0);
+        let rendered = Mutex::new(Vec::with_capacity(tiles.len()));
+
+        let render_tile = |tile: &Tile| {
+            let mut rng = self.tile_rng(tile);
+            let mut pixels = Vec::with_capacity(tile.width * tile.height);
+
+            for pixel_y in tile.y..tile.y + tile.height {
+                for pixel_x in tile.x..tile.x + tile.width {
+                    pixels.push(shade(pixel_x, pixel_y, &mut rng));
+                }
+            }
+
+            Image::from_pixels(tile.width, tile.height, pixels)
+        };
+
+        let n_threads = self.n_threads.clamp(1, tiles.len().max(1));
+
+        thread::scope(|scope| {
+            for _ in 0..n_threads {
+                scope.spawn(|| loop {
+                    let i = next_tile.fetch_add(1, Ordering::Relaxed);
+                    let Some(tile) = tiles.get(i) else { break };
+
+                    let tile_image = render_tile(tile);
+                    rendered.lock().unwrap().push((*tile, tile_image));
+                });
+            }
+        });
+
+        let mut image = Image::new(width, height, P::default());
+
+        for (tile, tile_image) in rendered.into_inner().unwrap() {
+            let source = tile_image.view(0, 0, tile.width, tile.height);
+            image
+                .view_mut(tile.x, tile.y, tile.width, tile.height)
+                .copy_from(source);
+        }
+
+        image
+    }
+}
@@ -0,0 +1,267 @@
+use deer2::cast::*;
+use deer2::formats::stl::*;
+use deer2::formats::FormatError;
+use deer2::math::*;
+
+use std::io::Cursor;
+
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+
+const UTAH_TEAPOT: &[u8] = include_bytes!("../data/stl/utah_teapot.stl");
+
+fn v(x: f32, y: f32, z: f32) -> ff32_3 {
+    ff32_3::new(ff32(x), ff32(y), ff32(z))
+}
+
+fn assert_matches_triangle_list<'a, C: Castable<'a, ff32>>(
+    castable: &'a C,
+    triangles: &'a TriangleList<ff32>,
+) {
+    let positions = [v(0.0, 0.0, 26.0), v(-20.0, 15.0, -10.0), v(3.0, -30.0, 1.0)];
+    let resolution = 16;
+
+    for position in positions {
+        let camera = Camera::perspective(position, ff32_3::ZERO, ff32_3::EY, ff32(0.8), ff32(1.0));
+
+        for pixel_y in 0..resolution {
+            for pixel_x in 0..resolution {
+                let ray = camera.pixel_ray(pixel_x, pixel_y, resolution, resolution);
+
+                for max_d in [ff32(2000.0), ff32(26.0)] {
+                    let expected = triangles.cast_ray(ray, ff32(0.0), max_d).map(|isec| isec.d);
+                    let actual = castable.cast_ray(ray, ff32(0.0), max_d).map(|isec| isec.d);
+
+                    assert_eq!(actual, expected, "{ray:?}");
+
+                    let is_occluded = castable.is_occluded(ray, ff32(0.0), max_d);
+                    assert_eq!(is_occluded, expected.is_some(), "{ray:?}");
+
+                    let all_d = |isecs: Vec<RayIntersection<ff32>>| -> Vec<ff32> {
+                        isecs.iter().map(|isec| isec.d).collect()
+                    };
+                    assert_eq!(
+                        all_d(castable.cast_ray_all(ray, ff32(0.0), max_d)),
+                        all_d(triangles.cast_ray_all(ray, ff32(0.0), max_d)),
+                        "{ray:?}"
+                    );
+                    assert_eq!(triangles.is_occluded(ray, ff32(0.0), max_d), is_occluded);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn utah_teapot_kd() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    let tree = BspTree::build_kd(&triangles.triangles);
+
+    assert_matches_triangle_list(&tree.with_triangles(&triangles.triangles), &triangles);
+}
+
+#[test]
+fn utah_teapot_randomized() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    let mut rng = SmallRng::seed_from_u64(117);
+    let tree = BspTree::build_tri_randomized(&triangles.triangles, &mut rng, 2);
+
+    assert_matches_triangle_list(&tree.with_triangles(&triangles.triangles), &triangles);
+}
+
+#[test]
+fn utah_teapot_kd_sah() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    let tree = BspTree::build_kd_sah(&triangles.triangles, &KdSahOptions::default());
+
+    assert_matches_triangle_list(&tree.with_triangles(&triangles.triangles), &triangles);
+}
+
+#[test]
+fn kd_sah_stats() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+    let n_tris = triangles.triangles.len();
+
+    let options = KdSahOptions {
+        leaf_size: 8,
+        max_depth: Some(20),
+    };
+    let stats = BspTree::build_kd_sah(&triangles.triangles, &options).stats();
+
+    assert!(stats.depth > 1 && stats.depth <= 21);
+    assert!(stats.n_leaves > n_tris / 8 && stats.n_leaves < stats.n_nodes);
+
+    // everything is in leaves, some triangles in more than one
+    assert_eq!(stats.n_leaf_tris, stats.n_tri_refs);
+    assert!(stats.n_tri_refs >= n_tris);
+    assert!(stats.mean_leaf_tris() <= stats.max_leaf_tris as f64);
+
+    let options = KdSahOptions {
+        max_depth: Some(0),
+        ..options
+    };
+    let stats = BspTree::build_kd_sah(&triangles.triangles, &options).stats();
+
+    assert_eq!((stats.depth, stats.n_nodes, stats.n_leaves), (1, 1, 1));
+    assert_eq!(stats.max_leaf_tris, n_tris);
+
+    let stats = BspTree::<ff32>::build_kd_sah(&[], &options).stats();
+    assert_eq!(stats, BspTreeStats::default());
+}
+
+#[test]
+fn kd_stats() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    // every triangle is in exactly one node
+    let stats = BspTree::build_kd(&triangles.triangles).stats();
+    assert_eq!(stats.n_tri_refs, triangles.triangles.len());
+}
+
+/// Trees no longer borrow the triangles, so they can be kept next to them.
+struct Mesh {
+    triangles: TriangleList<ff32>,
+    tree: BspTree<ff32>,
+}
+
+#[test]
+fn save_load() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+    let tree = BspTree::build_kd_sah(&triangles.triangles, &KdSahOptions::default());
+    let key = BuildKey::new("kd-sah", &triangles.triangles);
+
+    let mut bytes = Vec::new();
+    tree.write_to(&mut bytes, &key).unwrap();
+
+    let (loaded, loaded_key) = BspTree::read_from(&mut Cursor::new(&bytes)).unwrap();
+    assert_eq!(loaded_key, key);
+
+    let mesh = Mesh {
+        tree: loaded,
+        triangles,
+    };
+
+    assert_eq!(mesh.tree.n_triangles(), mesh.triangles.triangles.len());
+    assert_eq!(mesh.tree.stats(), tree.stats());
+    assert_matches_triangle_list(
+        &mesh.tree.with_triangles(&mesh.triangles.triangles),
+        &mesh.triangles,
+    );
+
+    let mut resaved = Vec::new();
+    mesh.tree.write_to(&mut resaved, &key).unwrap();
+    assert_eq!(resaved, bytes);
+
+    let empty = BspTree::<ff32>::build_kd(&[]);
+    let mut empty_bytes = Vec::new();
+    empty
+        .write_to(&mut empty_bytes, &BuildKey::default())
+        .unwrap();
+
+    let (empty, _) = BspTree::<ff32>::read_from(&mut Cursor::new(&empty_bytes)).unwrap();
+    assert_eq!(empty.stats(), BspTreeStats::default());
+}
+
+#[test]
+fn load_invalid() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+    let tree = BspTree::build_kd(&triangles.triangles);
+
+    let mut bytes = Vec::new();
+    tree.write_to(&mut bytes, &BuildKey::default()).unwrap();
+
+    let read = |bytes: &[u8]| BspTree::<ff32>::read_from(&mut Cursor::new(bytes)).err();
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = b'x';
+    assert!(matches!(
+        read(&bad_magic),
+        Some(FormatError::InvalidHeader(_))
+    ));
+
+    let mut bad_version = bytes.clone();
+    bad_version[8] = 3;
+    assert!(matches!(
+        read(&bad_version),
+        Some(FormatError::Unsupported(_))
+    ));
+
+    assert!(matches!(
+        read(&bytes[..bytes.len() - 1]),
+        Some(FormatError::Truncated)
+    ));
+
+    // the last triangle index
+    let mut bad_index = bytes.clone();
+    let n = bad_index.len();
+    bad_index[n - 4..].copy_from_slice(&(triangles.triangles.len() as u32).to_le_bytes());
+    assert!(matches!(
+        read(&bad_index),
+        Some(FormatError::InvalidData(_))
+    ));
+
+    // the negative child of the root points back to it
+    let mut bad_child = bytes.clone();
+    bad_child[48..52].copy_from_slice(&0u32.to_le_bytes());
+    assert!(matches!(
+        read(&bad_child),
+        Some(FormatError::InvalidData(_))
+    ));
+}
+
+/// File of a chain of nodes, each the negative child of the previous one.
+fn chain_file(n_nodes: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    BspTree::<ff32>::build_kd(&[])
+        .write_to(&mut bytes, &BuildKey::default())
+        .unwrap();
+
+    // the node count, after the magic, version, key and triangle count
+    bytes[32..36].copy_from_slice(&n_nodes.to_le_bytes());
+
+    for i in 0..n_nodes {
+        let neg = if i + 1 < n_nodes { i + 1 } else { u32::MAX };
+
+        for field in [0, 0, neg, u32::MAX] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+
+        bytes.extend_from_slice(&[0; 48]);
+    }
+
+    bytes
+}
+
+#[test]
+fn load_deep() {
+    let read = |bytes: &[u8]| BspTree::<ff32>::read_from(&mut Cursor::new(bytes));
+
+    let (tree, _) = read(&chain_file(1024)).unwrap();
+    assert_eq!(tree.stats().depth, 1024);
+
+    // deep enough to overflow the stack in traversals
+    assert!(matches!(
+        read(&chain_file(1_000_000)),
+        Err(FormatError::InvalidData(_))
+    ));
+}
+
+#[test]
+#[should_panic]
+fn other_triangles() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+    let tree = BspTree::build_kd(&triangles.triangles);
+
+    tree.with_triangles(&triangles.triangles[1..]);
+}
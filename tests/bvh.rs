@@ -0,0 +1,199 @@
+use deer2::cast::*;
+use deer2::formats::stl::*;
+use deer2::formats::FormatError;
+use deer2::math::*;
+
+use std::io::Cursor;
+
+const UTAH_TEAPOT: &[u8] = include_bytes!("../data/stl/utah_teapot.stl");
+
+fn v(x: f32, y: f32, z: f32) -> ff32_3 {
+    ff32_3::new(ff32(x), ff32(y), ff32(z))
+}
+
+fn ray(src: ff32_3, dir: ff32_3) -> Ray<ff32> {
+    Ray {
+        src,
+        dir1: dir.norm(),
+    }
+}
+
+#[test]
+fn slab_test() {
+    let aabb = Aabb::from_point(v(-1.0, -1.0, -1.0)).extend(v(1.0, 1.0, 1.0));
+    assert_eq!(aabb.surface_area(), ff32(24.0));
+
+    let hit = |ray: Ray<ff32>, max_d: f32| {
+        aabb.cast_slab_ray(&SlabRay::from(ray), ff32(0.0), ff32(max_d))
+            .map(|(near_d, far_d)| (near_d.0, far_d.0))
+    };
+
+    let ray_z = ray(v(0.0, 0.0, 5.0), v(0.0, 0.0, -1.0));
+    assert_eq!(hit(ray_z, 100.0), Some((4.0, 6.0)));
+    assert_eq!(hit(ray_z, 5.0), Some((4.0, 5.0)));
+    assert_eq!(hit(ray_z, 3.0), None);
+
+    // parallel to two slabs, outside one of them
+    let ray_miss = ray(v(2.0, 0.0, 5.0), v(0.0, 0.0, -1.0));
+    assert_eq!(hit(ray_miss, 100.0), None);
+
+    // starting inside
+    let ray_inside = ray(v(0.0, 0.0, 0.0), v(1.0, 0.0, 0.0));
+    assert_eq!(hit(ray_inside, 100.0), Some((0.0, 1.0)));
+
+    let ray_away = ray(v(0.0, 0.0, 5.0), v(0.0, 0.0, 1.0));
+    assert_eq!(hit(ray_away, 100.0), None);
+}
+
+#[test]
+fn empty() {
+    let bvh = Bvh::<ff32>::build(&[]);
+    let bvh = bvh.with_triangles(&[]);
+
+    assert!(bvh.bounds().is_none());
+    assert!(bvh
+        .cast_ray(ray(ff32_3::ZERO, ff32_3::EZ), ff32(0.0), ff32(100.0))
+        .is_none());
+}
+
+fn assert_matches_triangle_list<'a>(bvh: &'a BvhView<'a, ff32>, triangles: &'a TriangleList<ff32>) {
+    let bounds = bvh.bounds().unwrap();
+    for tri in &triangles.triangles {
+        assert_eq!(Aabb::union(bounds, Aabb::from_triangle(tri)), bounds);
+    }
+
+    let positions = [v(0.0, 0.0, 26.0), v(-20.0, 15.0, -10.0), v(3.0, -30.0, 1.0)];
+    let resolution = 16;
+
+    for position in positions {
+        let camera =
+            Camera::perspective(position, bounds.center(), ff32_3::EY, ff32(0.8), ff32(1.0));
+
+        for pixel_y in 0..resolution {
+            for pixel_x in 0..resolution {
+                let ray = camera.pixel_ray(pixel_x, pixel_y, resolution, resolution);
+
+                for max_d in [ff32(2000.0), ff32(26.0)] {
+                    let expected = triangles.cast_ray(ray, ff32(0.0), max_d).map(|isec| isec.d);
+                    let actual = bvh.cast_ray(ray, ff32(0.0), max_d).map(|isec| isec.d);
+
+                    assert_eq!(actual, expected, "{ray:?}");
+
+                    let is_occluded = bvh.is_occluded(ray, ff32(0.0), max_d);
+                    assert_eq!(is_occluded, expected.is_some(), "{ray:?}");
+
+                    let all_d = |isecs: Vec<RayIntersection<ff32>>| -> Vec<ff32> {
+                        isecs.iter().map(|isec| isec.d).collect()
+                    };
+                    assert_eq!(
+                        all_d(bvh.cast_ray_all(ray, ff32(0.0), max_d)),
+                        all_d(triangles.cast_ray_all(ray, ff32(0.0), max_d)),
+                        "{ray:?}"
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn utah_teapot_matches_triangle_list() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    let bvh = Bvh::build(&triangles.triangles);
+    assert_matches_triangle_list(&bvh.with_triangles(&triangles.triangles), &triangles);
+}
+
+#[test]
+fn save_load() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+    let bvh = Bvh::build(&triangles.triangles);
+    let key = BuildKey::new("bvh", &triangles.triangles);
+
+    let mut bytes = Vec::new();
+    bvh.write_to(&mut bytes, &key).unwrap();
+
+    let (loaded, loaded_key) = Bvh::<ff32>::read_from(&mut Cursor::new(&bytes)).unwrap();
+    assert_eq!(loaded_key, key);
+    assert_eq!(loaded.n_triangles(), triangles.triangles.len());
+    assert_matches_triangle_list(&loaded.with_triangles(&triangles.triangles), &triangles);
+
+    let mut resaved = Vec::new();
+    loaded.write_to(&mut resaved, &key).unwrap();
+    assert_eq!(resaved, bytes);
+
+    let empty = Bvh::<ff32>::build(&[]);
+    let mut empty_bytes = Vec::new();
+    empty.write_to(&mut empty_bytes, &key).unwrap();
+
+    let (empty, _) = Bvh::<ff32>::read_from(&mut Cursor::new(&empty_bytes)).unwrap();
+    assert!(empty.bounds().is_none());
+}
+
+#[test]
+fn load_invalid() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+    let bvh = Bvh::build(&triangles.triangles);
+
+    let mut bytes = Vec::new();
+    bvh.write_to(&mut bytes, &BuildKey::default()).unwrap();
+
+    let read = |bytes: &[u8]| Bvh::<ff32>::read_from(&mut Cursor::new(bytes)).err();
+
+    let mut bad_magic = bytes.clone();
+    bad_magic[0] = b'x';
+    assert!(matches!(
+        read(&bad_magic),
+        Some(FormatError::InvalidHeader(_))
+    ));
+
+    assert!(matches!(
+        read(&bytes[..bytes.len() - 1]),
+        Some(FormatError::Truncated)
+    ));
+
+    // the last triangle index
+    let mut bad_index = bytes.clone();
+    let n = bad_index.len();
+    bad_index[n - 4..].copy_from_slice(&(triangles.triangles.len() as u32).to_le_bytes());
+    assert!(matches!(
+        read(&bad_index),
+        Some(FormatError::InvalidData(_))
+    ));
+
+    // the second child of the root is its first one too
+    let mut bad_child = bytes.clone();
+    bad_child[40..44].copy_from_slice(&1u32.to_le_bytes());
+    assert!(matches!(
+        read(&bad_child),
+        Some(FormatError::InvalidData(_))
+    ));
+}
+
+#[test]
+fn build_key() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    let key = BuildKey::new("bvh", &triangles.triangles);
+    assert_eq!(key, BuildKey::new("bvh", &triangles.triangles));
+
+    assert_ne!(
+        key.builder,
+        BuildKey::new("kd", &triangles.triangles).builder
+    );
+    assert_ne!(
+        key.triangles,
+        BuildKey::new("bvh", &triangles.triangles[1..]).triangles
+    );
+
+    let mut moved = model.to_triangle_list();
+    moved.transform(&Affine3::translation(v(0.0, 0.0, 1.0)));
+    assert_ne!(
+        key.triangles,
+        BuildKey::new("bvh", &moved.triangles).triangles
+    );
+}
@@ -0,0 +1,100 @@
+use deer2::cast::*;
+use deer2::math::*;
+
+fn v(x: f64, y: f64, z: f64) -> f64_3 {
+    f64_3::new(x, y, z)
+}
+
+fn assert_close(a: f64_3, b: f64_3) {
+    assert!((a - b).abs() < 1e-9, "{a:?} != {b:?}");
+}
+
+#[test]
+fn perspective() {
+    let fov_y = 90f64.to_radians();
+    let camera = Camera::perspective(v(0.0, 0.0, 10.0), f64_3::ZERO, f64_3::EY, fov_y, 2.0);
+
+    let center = camera.ray(0.5, 0.5);
+    assert_close(center.src, v(0.0, 0.0, 10.0));
+    assert_close(center.dir1, v(0.0, 0.0, -1.0));
+
+    // 90 degrees vertically, twice as wide horizontally
+    let top_left = camera.ray(0.0, 0.0);
+    assert_close(top_left.dir1, v(-2.0, 1.0, -1.0).norm());
+
+    let bottom_right = camera.ray(1.0, 1.0);
+    assert_close(bottom_right.dir1, v(2.0, -1.0, -1.0).norm());
+}
+
+#[test]
+fn orthographic() {
+    let camera = Camera::orthographic(v(0.0, 0.0, 10.0), f64_3::ZERO, f64_3::EY, 4.0, 1.0);
+
+    let top_left = camera.ray(0.0, 0.0);
+    assert_close(top_left.src, v(-2.0, 2.0, 10.0));
+    assert_close(top_left.dir1, v(0.0, 0.0, -1.0));
+
+    let center = camera.pixel_ray(2, 2, 5, 5);
+    assert_close(center.src, v(0.0, 0.0, 10.0));
+}
+
+#[test]
+fn look_at_any_direction() {
+    let camera = Camera::perspective(v(5.0, 5.0, 0.0), v(5.0, 0.0, 0.0), f64_3::EZ, 1.0, 1.0);
+
+    assert_close(camera.forward1, v(0.0, -1.0, 0.0));
+    assert_close(camera.up1, v(0.0, 0.0, 1.0));
+    assert_close(camera.right1, v(-1.0, 0.0, 0.0));
+
+    // up is orthogonalized if not perpendicular to the view direction
+    let camera = Camera::perspective(v(1.0, 1.0, 1.0), f64_3::ZERO, f64_3::EY, 1.0, 1.0);
+    assert!(f64_3::dot(camera.up1, camera.forward1).abs() < 1e-9);
+    assert!(camera.up1.y() > 0.0);
+
+    // up along the view direction falls back to +Y, or +Z when looking along Y
+    let camera = Camera::perspective(v(0.0, 0.0, 10.0), f64_3::ZERO, f64_3::EZ, 1.0, 1.0);
+    assert_close(camera.up1, f64_3::EY);
+    assert_close(camera.right1, f64_3::EX);
+
+    let camera = Camera::perspective(v(0.0, 10.0, 0.0), f64_3::ZERO, f64_3::EY, 1.0, 1.0);
+    assert_close(camera.up1, f64_3::EZ);
+    assert_close(camera.right1, -f64_3::EX);
+}
+
+#[test]
+fn pixel_rays() {
+    let camera = Camera::perspective(v(0.0, 0.0, 1.0), f64_3::ZERO, f64_3::EY, 1.0, 2.0);
+
+    let ray = camera.pixel_ray(0, 0, 4, 2);
+    assert_close(ray.dir1, camera.image_ray(0.5, 0.5, 4, 2).dir1);
+    assert_close(ray.dir1, camera.ray(0.125, 0.25).dir1);
+}
+
+#[test]
+fn orientation() {
+    let projection = Projection::Perspective { fov_y: 1.0 };
+
+    for (position, target, up) in [
+        (v(0.0, 0.0, 10.0), f64_3::ZERO, f64_3::EY),
+        (v(5.0, 5.0, 0.0), v(5.0, 0.0, 0.0), f64_3::EZ),
+        (v(1.0, -2.0, 3.0), v(-4.0, 0.5, 0.0), v(0.3, 1.0, 0.2)),
+        (v(0.0, 0.0, -10.0), f64_3::ZERO, f64_3::EY),
+    ] {
+        let camera = Camera::look_at(position, target, up, projection, 1.5);
+        let oriented = Camera::oriented(position, camera.orientation(), projection, 1.5);
+
+        assert_close(oriented.forward1, camera.forward1);
+        assert_close(oriented.right1, camera.right1);
+        assert_close(oriented.up1, camera.up1);
+    }
+
+    // halfway along a turn around the target, looking at it all the way
+    let from = Camera::look_at(v(0.0, 0.0, 10.0), f64_3::ZERO, f64_3::EY, projection, 1.0);
+    let to = Camera::look_at(v(10.0, 0.0, 0.0), f64_3::ZERO, f64_3::EY, projection, 1.0);
+
+    let halfway = f64_quat::slerp(from.orientation(), to.orientation(), 0.5);
+    let camera = Camera::oriented(v(0.0, 0.0, 0.0), halfway, projection, 1.0);
+
+    assert_close(camera.forward1, v(-1.0, 0.0, -1.0).norm());
+    assert_close(camera.up1, f64_3::EY);
+}
@@ -0,0 +1,84 @@
+use deer2::cast::*;
+use deer2::math::*;
+use deer2::primitives::*;
+
+fn distances<'a, C: Castable<'a, f64>>(castable: &'a C, min_d: f64, max_d: f64) -> Vec<f64> {
+    // slightly off the axis, away from the edges between triangles
+    let ray = Ray {
+        src: f64_3::new(0.0, 0.0, 10.0),
+        dir1: f64_3::new(0.013, 0.021, -1.0).norm(),
+    };
+
+    castable
+        .cast_ray_all(ray, min_d, max_d)
+        .iter()
+        .map(|isec| isec.d)
+        .collect()
+}
+
+fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+
+    for (a, b) in actual.iter().zip(expected) {
+        assert!((a - b).abs() < 0.1, "{actual:?} != {expected:?}");
+    }
+}
+
+fn concentric_spheres() -> TriangleList<f64> {
+    let mut triangles = make_uv_sphere(f64_3::ZERO, 5.0, 32, 64);
+    triangles
+        .triangles
+        .extend(make_uv_sphere(f64_3::ZERO, 2.0, 32, 64).triangles);
+
+    triangles
+}
+
+#[test]
+fn triangle_list() {
+    let triangles = concentric_spheres();
+
+    // entering and leaving both spheres, back faces included
+    assert_close(&distances(&triangles, 0.0, 100.0), &[5.0, 8.0, 12.0, 15.0]);
+
+    assert_close(&distances(&triangles, 6.0, 13.0), &[8.0, 12.0]);
+    assert_close(&distances(&triangles, 0.0, 4.0), &[]);
+}
+
+#[test]
+fn acceleration_structures() {
+    let triangles = concentric_spheres();
+    let expected = distances(&triangles, 0.0, 100.0);
+    assert_eq!(expected.len(), 4);
+
+    assert_eq!(
+        distances(
+            &BspTree::build_kd(&triangles.triangles).with_triangles(&triangles.triangles),
+            0.0,
+            100.0,
+        ),
+        expected
+    );
+
+    // references triangles crossing split planes more than once
+    let kd_sah = BspTree::build_kd_sah(&triangles.triangles, &KdSahOptions::default());
+    assert_eq!(
+        distances(&kd_sah.with_triangles(&triangles.triangles), 0.0, 100.0),
+        expected
+    );
+
+    assert_eq!(
+        distances(
+            &Bvh::build(&triangles.triangles).with_triangles(&triangles.triangles),
+            0.0,
+            100.0
+        ),
+        expected
+    );
+
+    let clipped = distances(
+        &Bvh::build(&triangles.triangles).with_triangles(&triangles.triangles),
+        6.0,
+        13.0,
+    );
+    assert_eq!(clipped, expected[1..3]);
+}
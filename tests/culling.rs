@@ -0,0 +1,203 @@
+use deer2::cast::*;
+use deer2::math::*;
+use deer2::primitives::*;
+
+fn make_triangle(a: f64_3, b: f64_3, c: f64_3) -> Triangle<f64> {
+    let n1 = f64_3::cross(b - a, c - a).norm();
+
+    Triangle {
+        a,
+        m_abc: Matrix3::from_cols(b - a, c - a, n1).inv().unwrap(),
+        meta: Box::new(TriangleMeta {
+            a,
+            b,
+            c,
+            abc_nc: Matrix3::from_cols(n1, n1, n1),
+            abc_uv: Matrix3::ONE,
+            material: 0,
+        }),
+    }
+}
+
+/// Distance and side of the closest hit.
+type Hit = Option<(f64, bool)>;
+
+fn ray(src: f64_3, dir: f64_3) -> Ray<f64> {
+    Ray {
+        src,
+        dir1: dir.norm(),
+    }
+}
+
+#[test]
+fn single_triangle() {
+    // normal points to +z
+    let tri = make_triangle(
+        f64_3::new(-1.0, -1.0, 0.0),
+        f64_3::new(2.0, -1.0, 0.0),
+        f64_3::new(-1.0, 2.0, 0.0),
+    );
+
+    let cast = |ray, culling| {
+        tri.cast_ray_culled(ray, 0.0, 100.0, culling)
+            .map(|isec| (isec.d, isec.is_front_face))
+    };
+
+    let from_front = ray(f64_3::new(0.0, 0.0, 5.0), -f64_3::EZ);
+    assert_eq!(cast(from_front, Culling::Back), Some((5.0, true)));
+    assert_eq!(cast(from_front, Culling::Front), None);
+    assert_eq!(cast(from_front, Culling::None), Some((5.0, true)));
+    assert_eq!(tri.cast_ray(from_front, 0.0, 100.0).unwrap().d, 5.0);
+
+    let from_back = ray(f64_3::new(0.0, 0.0, -5.0), f64_3::EZ);
+    assert_eq!(cast(from_back, Culling::Back), None);
+    assert_eq!(cast(from_back, Culling::Front), Some((5.0, false)));
+    assert_eq!(cast(from_back, Culling::None), Some((5.0, false)));
+    assert!(tri.cast_ray(from_back, 0.0, 100.0).is_none());
+
+    let away = ray(f64_3::new(0.0, 0.0, 5.0), f64_3::EZ);
+    let along = ray(f64_3::new(0.0, 0.0, 5.0), f64_3::EX);
+    let on_plane = ray(f64_3::new(0.0, 0.0, 0.0), -f64_3::EZ);
+
+    for ray in [away, along, on_plane] {
+        assert_eq!(cast(ray, Culling::None), None);
+    }
+
+    // parallel to the plane, on either side, even with an unbounded interval
+    let along_front = ray(f64_3::new(0.0, 0.0, 5.0), f64_3::EX);
+    let along_back = ray(f64_3::new(0.0, 0.0, -5.0), f64_3::EX);
+
+    for ray in [along_front, along_back] {
+        for culling in [Culling::Back, Culling::Front, Culling::None] {
+            assert!(tri
+                .cast_ray_culled(ray, 0.0, f64::INFINITY, culling)
+                .is_none());
+        }
+    }
+}
+
+#[test]
+fn concentric_spheres() {
+    let mut triangles = make_uv_sphere(f64_3::ZERO, 5.0, 32, 64);
+    triangles
+        .triangles
+        .extend(make_uv_sphere(f64_3::ZERO, 2.0, 32, 64).triangles);
+
+    let ray = ray(f64_3::new(0.0, 0.0, 10.0), f64_3::new(0.013, 0.021, -1.0));
+
+    let kd_tree = BspTree::build_kd(&triangles.triangles);
+    let kd = kd_tree.with_triangles(&triangles.triangles);
+    let kd_sah_tree = BspTree::build_kd_sah(&triangles.triangles, &KdSahOptions::default());
+    let kd_sah = kd_sah_tree.with_triangles(&triangles.triangles);
+    let bvh_tree = Bvh::build(&triangles.triangles);
+    let bvh = bvh_tree.with_triangles(&triangles.triangles);
+
+    let castables: [&dyn Fn(Culling) -> Hit; 4] = [
+        &|culling| {
+            triangles
+                .cast_ray_culled(ray, 0.0, 100.0, culling)
+                .map(|isec| (isec.d, isec.is_front_face))
+        },
+        &|culling| {
+            kd.cast_ray_culled(ray, 0.0, 100.0, culling)
+                .map(|isec| (isec.d, isec.is_front_face))
+        },
+        &|culling| {
+            kd_sah
+                .cast_ray_culled(ray, 0.0, 100.0, culling)
+                .map(|isec| (isec.d, isec.is_front_face))
+        },
+        &|culling| {
+            bvh.cast_ray_culled(ray, 0.0, 100.0, culling)
+                .map(|isec| (isec.d, isec.is_front_face))
+        },
+    ];
+
+    for cast in castables {
+        let (entry_d, is_entry_front) = cast(Culling::None).unwrap();
+        assert!((entry_d - 5.0).abs() < 0.1);
+
+        // the first hit on the other side is leaving the inner sphere
+        let (culled, not_culled) = if is_entry_front {
+            (Culling::Front, Culling::Back)
+        } else {
+            (Culling::Back, Culling::Front)
+        };
+
+        assert_eq!(cast(not_culled), Some((entry_d, is_entry_front)));
+
+        let (exit_d, is_exit_front) = cast(culled).unwrap();
+        assert!((exit_d - 12.0).abs() < 0.1);
+        assert_eq!(is_exit_front, !is_entry_front);
+    }
+}
+
+#[test]
+fn min_distance() {
+    let mut triangles = make_uv_sphere(f64_3::ZERO, 5.0, 32, 64);
+    triangles
+        .triangles
+        .extend(make_uv_sphere(f64_3::ZERO, 2.0, 32, 64).triangles);
+
+    // crosses the spheres at about 5, 8, 12 and 15
+    let ray = ray(f64_3::new(0.0, 0.0, 10.0), f64_3::new(0.013, 0.021, -1.0));
+
+    let kd_tree = BspTree::build_kd(&triangles.triangles);
+    let kd = kd_tree.with_triangles(&triangles.triangles);
+    let kd_sah_tree = BspTree::build_kd_sah(&triangles.triangles, &KdSahOptions::default());
+    let kd_sah = kd_sah_tree.with_triangles(&triangles.triangles);
+    let bvh_tree = Bvh::build(&triangles.triangles);
+    let bvh = bvh_tree.with_triangles(&triangles.triangles);
+
+    let castables: [&dyn Fn(f64) -> (Hit, bool); 4] = [
+        &|min_d| {
+            (
+                triangles
+                    .cast_ray_culled(ray, min_d, 100.0, Culling::None)
+                    .map(|isec| (isec.d, isec.is_front_face)),
+                triangles.is_occluded(ray, min_d, 100.0),
+            )
+        },
+        &|min_d| {
+            (
+                kd.cast_ray_culled(ray, min_d, 100.0, Culling::None)
+                    .map(|isec| (isec.d, isec.is_front_face)),
+                kd.is_occluded(ray, min_d, 100.0),
+            )
+        },
+        &|min_d| {
+            (
+                kd_sah
+                    .cast_ray_culled(ray, min_d, 100.0, Culling::None)
+                    .map(|isec| (isec.d, isec.is_front_face)),
+                kd_sah.is_occluded(ray, min_d, 100.0),
+            )
+        },
+        &|min_d| {
+            (
+                bvh.cast_ray_culled(ray, min_d, 100.0, Culling::None)
+                    .map(|isec| (isec.d, isec.is_front_face)),
+                bvh.is_occluded(ray, min_d, 100.0),
+            )
+        },
+    ];
+
+    for cast in castables {
+        let (entry, _) = cast(0.0);
+        let (entry_d, is_entry_front) = entry.unwrap();
+
+        // a hit exactly at `min_d` is kept
+        assert_eq!(cast(entry_d).0, Some((entry_d, is_entry_front)));
+
+        let (inner, is_occluded) = cast(6.0);
+        assert!((inner.unwrap().0 - 8.0).abs() < 0.1);
+        assert!(is_occluded);
+
+        // both remaining hits are leaving a sphere
+        let (exit, is_occluded) = cast(8.5);
+        assert!((exit.unwrap().0 - 12.0).abs() < 0.1);
+        assert_eq!(is_occluded, !is_entry_front);
+
+        assert_eq!(cast(15.5), (None, false));
+    }
+}
@@ -0,0 +1,153 @@
+use deer2::image::*;
+
+fn gradient() -> Image<u8_gray> {
+    Image::from_pixels(4, 3, (0..12).map(u8_gray))
+}
+
+#[test]
+fn rows_and_pixels() {
+    let image = gradient();
+
+    let rows: Vec<&[u8_gray]> = image.rows().collect();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[1], &[u8_gray(4), u8_gray(5), u8_gray(6), u8_gray(7)]);
+
+    for (x, y, pixel) in image.enumerate_pixels() {
+        assert_eq!(pixel, u8_gray((y * 4 + x) as u8));
+        assert_eq!(image.get(x, y), pixel);
+    }
+}
+
+#[test]
+fn views() {
+    let mut image = gradient();
+
+    let view = image.view(1, 1, 2, 2);
+    assert_eq!((view.width(), view.height()), (2, 2));
+    assert_eq!(view.get(1, 0), u8_gray(6));
+    assert_eq!(
+        view.to_image().into_pixels(),
+        vec![u8_gray(5), u8_gray(6), u8_gray(9), u8_gray(10)]
+    );
+
+    let tile = Image::new(2, 2, u8_gray(100));
+    image.view_mut(2, 0, 2, 2).copy_from(tile.view(0, 0, 2, 2));
+
+    assert_eq!(image.get(1, 0), u8_gray(1));
+    assert_eq!(image.get(2, 0), u8_gray(100));
+    assert_eq!(image.get(3, 1), u8_gray(100));
+    assert_eq!(image.get(3, 2), u8_gray(11));
+
+    for row in image.view_mut(0, 2, 4, 1).rows_mut() {
+        row.fill(u8_gray(0));
+    }
+    assert!(image
+        .rows()
+        .last()
+        .unwrap()
+        .iter()
+        .all(|&p| p == u8_gray(0)));
+}
+
+#[test]
+fn conversions() {
+    let rgb = Image::new(1, 1, u8_rgb(200, 100, 50));
+
+    assert_eq!(
+        rgb.convert::<u8_rgba>().get(0, 0),
+        u8_rgba(200, 100, 50, 255)
+    );
+    assert_eq!(rgb.convert::<u8_gray>().get(0, 0), u8_gray(124));
+    assert_eq!(u8_gray::from(u8_rgb(77, 77, 77)), u8_gray(77));
+
+    let float = rgb.convert::<f32_rgb>();
+    assert_eq!(float.convert::<u8_rgb>(), rgb);
+    assert_eq!(u8_rgb::from(f32_rgb(-1.0, 0.5, 2.0)), u8_rgb(0, 128, 255));
+}
+
+#[test]
+fn tone_map_operators() {
+    for operator in [
+        ToneMapOperator::Clamp,
+        ToneMapOperator::Reinhard,
+        ToneMapOperator::Aces,
+    ] {
+        assert_eq!(operator.apply(0.0), 0.0);
+        assert_eq!(operator.apply(-1.0), 0.0);
+        assert!(operator.apply(1e6) <= 1.0);
+
+        // monotonic, so brighter input never gets darker
+        let mut prev = 0.0;
+        for i in 1..100 {
+            let y = operator.apply(i as f32 * 0.1);
+            assert!(y >= prev);
+            prev = y;
+        }
+    }
+
+    assert_eq!(ToneMapOperator::Clamp.apply(2.0), 1.0);
+    assert_eq!(ToneMapOperator::Reinhard.apply(1.0), 0.5);
+    assert!(ToneMapOperator::Aces.apply(1e6) > 0.99);
+}
+
+#[test]
+fn tone_mapping() {
+    let linear = ToneMapping {
+        is_srgb: false,
+        ..ToneMapping::default()
+    };
+    assert_eq!(linear.map(f32_rgb(0.5, 0.0, 2.0)), u8_rgb(128, 0, 255));
+
+    let brighter = ToneMapping {
+        exposure: 1.0,
+        ..linear
+    };
+    assert_eq!(brighter.map(f32_rgb(0.25, 0.0, 0.0)), u8_rgb(128, 0, 0));
+
+    // mid gray is much brighter once gamma encoded
+    let srgb = ToneMapping::default();
+    assert_eq!(srgb.map(f32_rgb(0.5, 0.5, 0.5)), u8_rgb(188, 188, 188));
+
+    for i in 0..=255 {
+        let x = i as f32 / 255.0;
+        assert!((srgb_decode(srgb_encode(x)) - x).abs() < 1e-5);
+    }
+}
+
+#[test]
+fn framebuffer() {
+    let mut framebuffer = Framebuffer::new(2, 1);
+    framebuffer.add_sample(0, 0, f32_rgb(1.0, 0.0, 4.0));
+    framebuffer.add_sample(0, 0, f32_rgb(0.0, 0.0, 2.0));
+    framebuffer.add_weighted_sample(1, 0, f32_rgb(8.0, 8.0, 8.0), 0.5);
+
+    let image = framebuffer.resolve();
+
+    assert_eq!(image.get(0, 0), f32_rgb(0.5, 0.0, 3.0));
+    assert_eq!(image.get(1, 0), f32_rgb(8.0, 8.0, 8.0));
+    assert_eq!(
+        Framebuffer::new(1, 1).resolve().get(0, 0),
+        f32_rgb::default()
+    );
+}
+
+#[test]
+fn framebuffer_images() {
+    let mut framebuffer = Framebuffer::new(2, 1);
+    framebuffer.add_image(&Image::from_pixels(
+        2,
+        1,
+        [f32_rgb(1.0, 0.0, 4.0), f32_rgb(2.0, 2.0, 2.0)],
+    ));
+    framebuffer.add_image(&Image::from_pixels(
+        2,
+        1,
+        [f32_rgb(0.0, 0.0, 2.0), f32_rgb(2.0, 2.0, 2.0)],
+    ));
+    framebuffer.add_sample(1, 0, f32_rgb(5.0, 5.0, 5.0));
+
+    let image = framebuffer.resolve();
+
+    assert_eq!(image.get(0, 0), f32_rgb(0.5, 0.0, 3.0));
+    assert_eq!(image.get(1, 0), f32_rgb(3.0, 3.0, 3.0));
+}
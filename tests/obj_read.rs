@@ -0,0 +1,106 @@
+use deer2::cast::*;
+use deer2::formats::obj::*;
+use deer2::formats::FormatError;
+use deer2::math::*;
+
+use std::io::Cursor;
+
+const QUAD: &str = "
+# a unit quad in the XY plane, facing +Z
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+
+vn 0 0 1
+vn 0 0 2
+
+o quad
+usemtl default
+s 1
+f 1/1/1 2/2/2 3/3/1 -1/-1/-2
+";
+
+#[test]
+fn quad() {
+    let model = ObjModel::read_from(&mut Cursor::new(QUAD)).unwrap();
+
+    assert_eq!(model.positions.len(), 4);
+    assert_eq!(model.uvs.len(), 4);
+    assert_eq!(model.normals.len(), 2);
+    assert_eq!(model.faces.len(), 1);
+
+    assert_eq!(
+        model.faces[0].vertices[3],
+        ObjVertex {
+            p: 3,
+            uv: Some(3),
+            n: Some(0),
+        }
+    );
+
+    let triangles = model.to_triangle_list();
+    assert_eq!(triangles.triangles.len(), 2);
+
+    let ray = Ray {
+        src: ff32_3::new(ff32(0.25), ff32(0.75), ff32(1.0)),
+        dir1: -ff32_3::EZ,
+    };
+
+    let isec = triangles.cast_ray(ray, ff32(0.0), ff32(10.0)).unwrap();
+    let meta = isec.interpolate_meta();
+
+    assert!((isec.d - ff32(1.0)).abs() < ff32::EPS);
+    assert!((meta.n1_p - ff32_3::EZ).abs() < ff32::EPS);
+    assert!((meta.p_uv.x() - ff32(0.25)).abs() < ff32::EPS);
+    assert!((meta.p_uv.y() - ff32(0.75)).abs() < ff32::EPS);
+}
+
+#[test]
+fn smooth_normals() {
+    let source = "
+        v -1 0 0
+        v 1 0 0
+        v 0 1 0
+        vn -1 0 1
+        vn 1 0 1
+        vn 0 0 1
+        f 1//1 2//2 3//3
+    ";
+
+    let model = ObjModel::read_from(&mut Cursor::new(source)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    let ray = Ray {
+        src: ff32_3::new(ff32(-0.5), ff32(0.25), ff32(1.0)),
+        dir1: -ff32_3::EZ,
+    };
+
+    let meta = triangles
+        .cast_ray(ray, ff32(0.0), ff32(10.0))
+        .unwrap()
+        .interpolate_meta();
+
+    assert!(meta.n1_p.x() < ff32(0.0));
+    assert!((meta.n1_p.abs() - ff32(1.0)).abs() < ff32::EPS);
+}
+
+#[test]
+fn invalid_index() {
+    let source = "v 0 0 0\nv 1 0 0\nf 1 2 3\n";
+    assert!(matches!(
+        ObjModel::read_from(&mut Cursor::new(source)),
+        Err(FormatError::InvalidData(_))
+    ));
+
+    let source = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2\n";
+    assert!(matches!(
+        ObjModel::read_from(&mut Cursor::new(source)),
+        Err(FormatError::InvalidData(_))
+    ));
+}
@@ -0,0 +1,237 @@
+use deer2::cast::*;
+use deer2::formats::ply::*;
+use deer2::formats::stl::*;
+use deer2::formats::FormatError;
+use deer2::math::*;
+
+use std::io::Cursor;
+
+const UTAH_TEAPOT: &[u8] = include_bytes!("../data/stl/utah_teapot.stl");
+
+const QUAD: &str = "ply
+format ascii 1.0
+comment a unit quad in the XY plane, facing +Z
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property uchar red
+property uchar green
+property uchar blue
+property float s
+property float t
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0 0 0 1 255 0 0 0 0
+1 0 0 0 0 1 0 255 0 1 0
+1 1 0 0 0 1 0 0 255 1 1
+0 1 0 0 0 1 255 255 255 0 1
+4 0 1 2 3
+";
+
+#[test]
+fn ascii_quad() {
+    let model = PlyModel::read_from(&mut Cursor::new(QUAD)).unwrap();
+
+    assert_eq!(model.format, PlyFormat::Ascii);
+    assert_eq!(model.comments, ["a unit quad in the XY plane, facing +Z"]);
+
+    let mesh = PlyMesh::from_model(&model).unwrap();
+
+    assert_eq!(mesh.positions.len(), 4);
+    assert_eq!(mesh.faces, [vec![0, 1, 2, 3]]);
+    assert_eq!(mesh.colors.as_ref().unwrap()[1], ff32_3::EY);
+    assert_eq!(
+        mesh.uvs.as_ref().unwrap()[2],
+        ff32_3::new(ff32(1.0), ff32(1.0), ff32(0.0))
+    );
+
+    let triangles = mesh.to_triangle_list();
+    assert_eq!(triangles.triangles.len(), 2);
+
+    let ray = Ray {
+        src: ff32_3::new(ff32(0.25), ff32(0.75), ff32(1.0)),
+        dir1: -ff32_3::EZ,
+    };
+
+    let meta = triangles
+        .cast_ray(ray, ff32(0.0), ff32(10.0))
+        .unwrap()
+        .interpolate_meta();
+
+    assert!((meta.n1_p - ff32_3::EZ).abs() < ff32::EPS);
+    assert!((meta.p_uv.x() - ff32(0.25)).abs() < ff32::EPS);
+    assert!((meta.p_uv.y() - ff32(0.75)).abs() < ff32::EPS);
+}
+
+#[test]
+fn round_trip_all_formats() {
+    let source = PlyModel::read_from(&mut Cursor::new(QUAD)).unwrap();
+
+    for format in [
+        PlyFormat::Ascii,
+        PlyFormat::BinaryLittleEndian,
+        PlyFormat::BinaryBigEndian,
+    ] {
+        let model = PlyModel {
+            format,
+            ..source.clone()
+        };
+
+        let mut bytes = Vec::<u8>::new();
+        model.write_to(&mut bytes).unwrap();
+
+        let result = PlyModel::read_from(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(result, model);
+    }
+}
+
+#[test]
+fn utah_teapot() {
+    let stl = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+
+    let mesh = PlyMesh {
+        positions: stl.triangles.iter().flat_map(|t| [t.a, t.b, t.c]).collect(),
+        faces: (0..stl.triangles.len())
+            .map(|i| vec![3 * i, 3 * i + 1, 3 * i + 2])
+            .collect(),
+        ..Default::default()
+    };
+
+    let mut bytes = Vec::<u8>::new();
+    mesh.to_model(PlyFormat::BinaryBigEndian)
+        .write_to(&mut bytes)
+        .unwrap();
+
+    let model = PlyModel::read_from(&mut Cursor::new(&bytes)).unwrap();
+    let result = PlyMesh::from_model(&model).unwrap();
+
+    assert_eq!(result.positions, mesh.positions);
+    assert_eq!(result.faces, mesh.faces);
+    assert!(result.normals.is_none());
+
+    assert_eq!(
+        model.to_triangle_list().unwrap().triangles.len(),
+        stl.to_triangle_list().triangles.len()
+    );
+}
+
+#[test]
+fn truncated() {
+    let source = &QUAD[..QUAD.len() - 4];
+    assert!(matches!(
+        PlyModel::read_from(&mut Cursor::new(source)),
+        Err(FormatError::Truncated)
+    ));
+
+    let source = QUAD.replace("4 0 1 2 3", "3 0 1 7");
+    let model = PlyModel::read_from(&mut Cursor::new(source)).unwrap();
+    assert!(matches!(
+        PlyMesh::from_model(&model),
+        Err(FormatError::InvalidData(_))
+    ));
+}
+
+#[test]
+fn large_face() {
+    let n = 300;
+
+    let mesh = PlyMesh {
+        positions: (0..n)
+            .map(|i| {
+                let angle = i as f32 / n as f32 * std::f32::consts::TAU;
+                ff32_3::new(ff32(angle.cos()), ff32(angle.sin()), ff32(0.0))
+            })
+            .collect(),
+        faces: vec![(0..n).collect()],
+        ..Default::default()
+    };
+
+    let model = mesh.to_model(PlyFormat::BinaryLittleEndian);
+
+    let mut bytes = Vec::<u8>::new();
+    model.write_to(&mut bytes).unwrap();
+
+    let result = PlyModel::read_from(&mut Cursor::new(&bytes)).unwrap();
+    assert_eq!(PlyMesh::from_model(&result).unwrap().faces, mesh.faces);
+
+    // a uchar length would wrap around to 44
+    let mut model = model;
+    model.elements[1].def.properties[0].ty = PlyPropertyType::List {
+        count: PlyScalarType::UChar,
+        item: PlyScalarType::Int,
+    };
+    assert!(matches!(
+        model.write_to(&mut Vec::<u8>::new()),
+        Err(FormatError::OversizeDimension(_))
+    ));
+}
+
+#[test]
+fn out_of_range_value() {
+    let mut model = PlyModel::read_from(&mut Cursor::new(QUAD)).unwrap();
+    model.elements[0].rows[0][6] = PlyValue::Scalar(256.0);
+
+    for format in [PlyFormat::Ascii, PlyFormat::BinaryBigEndian] {
+        let model = PlyModel {
+            format,
+            ..model.clone()
+        };
+
+        assert!(matches!(
+            model.write_to(&mut Vec::<u8>::new()),
+            Err(FormatError::InvalidData(_))
+        ));
+    }
+}
+
+#[test]
+fn mismatched_row() {
+    let mut model = PlyModel::read_from(&mut Cursor::new(QUAD)).unwrap();
+    model.elements[0].rows[1].pop();
+
+    assert!(model.elements[0].scalars("t").is_none());
+    assert!(model.elements[0].scalars("x").is_some());
+
+    assert!(matches!(
+        model.write_to(&mut Vec::<u8>::new()),
+        Err(FormatError::InvalidData(_))
+    ));
+
+    model.elements[0].rows[1].push(PlyValue::Scalar(0.0));
+    model.elements[0].rows[1].push(PlyValue::Scalar(0.0));
+
+    assert!(matches!(
+        model.write_to(&mut Vec::<u8>::new()),
+        Err(FormatError::InvalidData(_))
+    ));
+}
+
+#[test]
+fn missing_component() {
+    let check = |from: &str, to: &str| {
+        let source = QUAD.replace(from, to);
+        let model = PlyModel::read_from(&mut Cursor::new(source)).unwrap();
+
+        assert!(matches!(
+            PlyMesh::from_model(&model),
+            Err(FormatError::InvalidHeader(_))
+        ));
+    };
+
+    check("property float z\n", "property float w\n");
+    check("property float nz\n", "property float w\n");
+    check("property float t\n", "property float w\n");
+
+    // without any normal components, the mesh simply has no normals
+    let source = QUAD
+        .replace("property float nx\n", "property float w1\n")
+        .replace("property float ny\n", "property float w2\n")
+        .replace("property float nz\n", "property float w3\n");
+    let model = PlyModel::read_from(&mut Cursor::new(source)).unwrap();
+    assert!(PlyMesh::from_model(&model).unwrap().normals.is_none());
+}
@@ -0,0 +1,103 @@
+use deer2::formats::png::*;
+use deer2::formats::tga::*;
+use deer2::formats::FormatError;
+use deer2::image::*;
+
+use std::io::Cursor;
+
+const LENA: &[u8] = include_bytes!("../data/tga/lena.tga");
+const FILTERS_RGB: &[u8] = include_bytes!("../data/png/filters_rgb.png");
+const PALETTE_2BIT: &[u8] = include_bytes!("../data/png/palette_2bit.png");
+const GRAY_16BIT: &[u8] = include_bytes!("../data/png/gray_16bit.png");
+
+fn round_trip<P: PngPixel + From<u8_rgba>>(image: &Image<P>) -> Image<P> {
+    let mut bytes = Vec::<u8>::new();
+    image.write_png_to(&mut bytes).unwrap();
+
+    assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+
+    Image::read_png_from(&mut Cursor::new(&bytes)).unwrap()
+}
+
+#[test]
+fn lena() {
+    let image = TgaBitmap::read_from(&mut Cursor::new(LENA))
+        .unwrap()
+        .into_image();
+    assert_eq!(round_trip(&image), image);
+
+    let gray = image.convert::<u8_gray>();
+    assert_eq!(round_trip(&gray), gray);
+}
+
+#[test]
+fn alpha() {
+    let mut image = Image::new(3, 2, u8_rgba(10, 20, 30, 255));
+    *image.get_mut(1, 0) = u8_rgba(10, 20, 30, 0);
+    *image.get_mut(2, 1) = u8_rgba(40, 50, 60, 128);
+
+    assert_eq!(round_trip(&image), image);
+}
+
+#[test]
+fn all_filter_types() {
+    let image: Image<u8_rgb> = Image::read_png_from(&mut Cursor::new(FILTERS_RGB)).unwrap();
+
+    for (x, y, color) in image.enumerate_pixels() {
+        let expected = u8_rgb(x as u8 * 60, y as u8 * 60, (x * y * 17) as u8);
+        assert_eq!(color, expected);
+    }
+}
+
+#[test]
+fn palette_2bit() {
+    let image: Image<u8_rgba> = Image::read_png_from(&mut Cursor::new(PALETTE_2BIT)).unwrap();
+
+    assert_eq!((image.width(), image.height()), (4, 2));
+
+    assert_eq!(image.get(0, 0), u8_rgba(255, 0, 0, 0));
+    assert_eq!(image.get(1, 0), u8_rgba(0, 255, 0, 128));
+    assert_eq!(image.get(2, 0), u8_rgba(0, 0, 255, 255));
+    assert_eq!(image.get(3, 0), u8_rgba(255, 255, 255, 255));
+    assert_eq!(image.get(1, 1), u8_rgba(0, 0, 255, 255));
+}
+
+#[test]
+fn gray_16bit() {
+    let image: Image<u8_gray> = Image::read_png_from(&mut Cursor::new(GRAY_16BIT)).unwrap();
+
+    assert_eq!(image.pixels(), &[u8_gray(0), u8_gray(128), u8_gray(255)]);
+}
+
+#[test]
+fn truncated() {
+    let source = &FILTERS_RGB[..FILTERS_RGB.len() - 20];
+    let result = Image::<u8_rgba>::read_png_from(&mut Cursor::new(source));
+    assert!(matches!(result, Err(FormatError::Truncated)));
+}
+
+#[test]
+fn wrong_crc() {
+    let mut source = FILTERS_RGB.to_vec();
+    source[20] ^= 1; // inside IHDR
+
+    let result = Image::<u8_rgba>::read_png_from(&mut Cursor::new(&source));
+    assert!(matches!(result, Err(FormatError::InvalidData(_))));
+}
+
+#[test]
+fn not_png() {
+    let result = Image::<u8_rgba>::read_png_from(&mut Cursor::new(LENA));
+    assert!(matches!(result, Err(FormatError::InvalidHeader(_))));
+}
+
+#[test]
+fn invalid_dimensions() {
+    let empty = Image::<u8_rgb>::from_pixels(0, 3, vec![]);
+    let result = empty.write_png_to(&mut Vec::<u8>::new());
+    assert!(matches!(result, Err(FormatError::InvalidData(_))));
+
+    let oversize = Image::<u8_rgb>::from_pixels(1 << 31, 0, vec![]);
+    let result = oversize.write_png_to(&mut Vec::<u8>::new());
+    assert!(matches!(result, Err(FormatError::OversizeDimension(_))));
+}
@@ -0,0 +1,120 @@
+use deer2::formats::pnm::*;
+use deer2::formats::tga::*;
+use deer2::formats::FormatError;
+use deer2::image::*;
+
+use std::io::Cursor;
+
+const LENA: &[u8] = include_bytes!("../data/tga/lena.tga");
+
+fn round_trip<P: PnmPixel + From<u8_rgb>>(image: &Image<P>, is_ascii: bool) -> Image<P> {
+    let mut bytes = Vec::<u8>::new();
+    image.write_pnm_to(&mut bytes, is_ascii).unwrap();
+
+    Image::read_pnm_from(&mut Cursor::new(&bytes)).unwrap()
+}
+
+#[test]
+fn lena_ppm() {
+    let image = TgaBitmap::read_from(&mut Cursor::new(LENA))
+        .unwrap()
+        .into_image();
+
+    for is_ascii in [false, true] {
+        assert_eq!(round_trip(&image, is_ascii), image);
+    }
+}
+
+#[test]
+fn pgm() {
+    let mut image = Image::new(3, 2, u8_gray(0));
+    *image.get_mut(1, 0) = u8_gray(7);
+    *image.get_mut(2, 1) = u8_gray(255);
+
+    let mut bytes = Vec::<u8>::new();
+    image.write_pnm_to(&mut bytes, false).unwrap();
+    assert_eq!(&bytes[..11], b"P5\n3 2\n255\n");
+
+    for is_ascii in [false, true] {
+        assert_eq!(round_trip(&image, is_ascii), image);
+    }
+}
+
+#[test]
+fn ascii_with_comments() {
+    let source = "P3\n# made by hand\n2 1 # size\n15\n15 0 0   0 15 # green\n 0\n";
+    let image: Image<u8_rgb> = Image::read_pnm_from(&mut Cursor::new(source)).unwrap();
+
+    assert_eq!(image.pixels(), &[u8_rgb(255, 0, 0), u8_rgb(0, 255, 0)]);
+}
+
+#[test]
+fn ascii_line_length() {
+    let image = Image::new(40, 3, u8_rgb(255, 255, 255));
+
+    let mut bytes = Vec::<u8>::new();
+    image.write_pnm_to(&mut bytes, true).unwrap();
+
+    let text = String::from_utf8(bytes).unwrap();
+    assert!(text.lines().all(|line| line.len() <= 70));
+}
+
+#[test]
+fn pgm_16bit() {
+    let mut source = b"P5 2 1 65535\n".to_vec();
+    source.extend_from_slice(&[0xFF, 0xFF, 0x80, 0x00]);
+
+    let image: Image<u8_gray> = Image::read_pnm_from(&mut Cursor::new(&source)).unwrap();
+
+    assert_eq!(image.pixels(), &[u8_gray(255), u8_gray(128)]);
+}
+
+#[test]
+fn pfm() {
+    let mut image = Image::new(2, 3, f32_rgb(0.0, 0.0, 0.0));
+    *image.get_mut(0, 0) = f32_rgb(1.5, -2.0, 1e10);
+    *image.get_mut(1, 2) = f32_rgb(0.25, 0.5, 0.75);
+
+    let mut bytes = Vec::<u8>::new();
+    image.write_pfm_to(&mut bytes).unwrap();
+
+    assert!(bytes.starts_with(b"PF\n2 3\n-1.0\n"));
+
+    let result = Image::read_pfm_from(&mut Cursor::new(&bytes)).unwrap();
+    assert_eq!(result, image);
+}
+
+#[test]
+fn pfm_big_endian_gray() {
+    let mut source = b"Pf\n1 2\n1.0\n".to_vec();
+    source.extend_from_slice(&1.0f32.to_be_bytes()); // bottom row
+    source.extend_from_slice(&2.0f32.to_be_bytes()); // top row
+
+    let image = Image::read_pfm_from(&mut Cursor::new(&source)).unwrap();
+
+    assert_eq!(image.get(0, 0), f32_rgb(2.0, 2.0, 2.0));
+    assert_eq!(image.get(0, 1), f32_rgb(1.0, 1.0, 1.0));
+}
+
+#[test]
+fn truncated() {
+    let source = b"P6 2 2 255\n\x00\x00\x00";
+    let result = Image::<u8_rgb>::read_pnm_from(&mut Cursor::new(source));
+    assert!(matches!(result, Err(FormatError::Truncated)));
+
+    let source = b"PF\n100000 100000\n-1.0\n\x00\x00";
+    let result = Image::read_pfm_from(&mut Cursor::new(source));
+    assert!(matches!(result, Err(FormatError::Truncated)));
+}
+
+#[test]
+fn invalid_header() {
+    let result = Image::<u8_rgb>::read_pnm_from(&mut Cursor::new("P7 1 1 255\n"));
+    assert!(matches!(result, Err(FormatError::InvalidHeader(_))));
+
+    let result = Image::<u8_rgb>::read_pnm_from(&mut Cursor::new("P2 1 1 0\n0"));
+    assert!(matches!(result, Err(FormatError::InvalidHeader(_))));
+
+    let result = Image::<u8_rgb>::read_pnm_from(&mut Cursor::new("P2 1 1 15\n16"));
+    assert!(matches!(result, Err(FormatError::InvalidData(_))));
+}
@@ -0,0 +1,226 @@
+use deer2::cast::*;
+use deer2::formats::stl::*;
+use deer2::math::*;
+use deer2::primitives::*;
+
+use std::io::Cursor;
+
+use rand::rngs::SmallRng;
+use rand::SeedableRng;
+
+const UTAH_TEAPOT: &[u8] = include_bytes!("../data/stl/utah_teapot.stl");
+
+fn v(x: f32, y: f32, z: f32) -> ff32_3 {
+    ff32_3::new(ff32(x), ff32(y), ff32(z))
+}
+
+/// Rays of a few cameras, interleaved so that some packets mix unrelated rays.
+fn camera_rays(resolution: usize) -> Vec<Ray<ff32>> {
+    let positions = [v(0.0, 0.0, 26.0), v(-20.0, 15.0, -10.0), v(3.0, -30.0, 1.0)];
+    let cameras = positions.map(|position| {
+        Camera::perspective(position, ff32_3::ZERO, ff32_3::EY, ff32(0.8), ff32(1.0))
+    });
+
+    let mut rays = Vec::new();
+
+    for pixel_y in 0..resolution {
+        for pixel_x in 0..resolution {
+            let i_camera = if pixel_y < resolution / 2 {
+                0
+            } else {
+                (pixel_x / 3) % cameras.len()
+            };
+
+            rays.push(cameras[i_camera].pixel_ray(pixel_x, pixel_y, resolution, resolution));
+        }
+    }
+
+    rays
+}
+
+fn assert_packets_match<'a, C: Castable<'a, ff32>, const L: usize>(castable: &'a C) {
+    assert_rays_match::<_, L>(castable, &camera_rays(16));
+}
+
+fn assert_rays_match<'a, C: Castable<'a, ff32>, const L: usize>(
+    castable: &'a C,
+    rays: &[Ray<ff32>],
+) {
+    for max_d in [ff32(2000.0), ff32(26.0)] {
+        for packet in rays.chunks_exact(L) {
+            let packet: &[Ray<ff32>; L] = packet.try_into().unwrap();
+            let isecs = castable.cast_packet(packet, ff32(0.0), max_d);
+
+            for (ray, isec) in packet.iter().zip(isecs) {
+                let expected = castable.cast_ray(*ray, ff32(0.0), max_d);
+
+                assert_eq!(isec.is_some(), expected.is_some(), "{ray:?}");
+
+                if let (Some(isec), Some(expected)) = (isec, expected) {
+                    assert!((isec.d - expected.d).abs() < ff32(1e-3), "{ray:?}");
+                    assert!(isec.is_front_face);
+
+                    let p = ray.src + ray.dir1 * isec.d;
+                    let p_abc = isec.tri.m_abc * (p - isec.tri.a);
+                    assert!((p_abc - isec.p_abc).abs() < ff32(1e-3), "{ray:?}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn utah_teapot_triangle_list() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    assert_packets_match::<_, 4>(&triangles);
+    assert_packets_match::<_, 8>(&triangles);
+}
+
+#[test]
+fn utah_teapot_bsp_trees() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    let kd = BspTree::build_kd(&triangles.triangles);
+    assert_packets_match::<_, 4>(&kd.with_triangles(&triangles.triangles));
+    assert_packets_match::<_, 8>(&kd.with_triangles(&triangles.triangles));
+
+    let kd_sah = BspTree::build_kd_sah(&triangles.triangles, &KdSahOptions::default());
+    assert_packets_match::<_, 4>(&kd_sah.with_triangles(&triangles.triangles));
+    assert_packets_match::<_, 8>(&kd_sah.with_triangles(&triangles.triangles));
+
+    let mut rng = SmallRng::seed_from_u64(117);
+    let randomized = BspTree::build_tri_randomized(&triangles.triangles, &mut rng, 2);
+    assert_packets_match::<_, 4>(&randomized.with_triangles(&triangles.triangles));
+    assert_packets_match::<_, 8>(&randomized.with_triangles(&triangles.triangles));
+}
+
+/// Cubes on a grid, whose faces lie in the split planes of the kd trees.
+fn cube_grid() -> Vec<Triangle<ff32>> {
+    let quad = |center: ff32_3, u: ff32_3, v: ff32_3| {
+        let n1 = ff32_3::cross(u, v).norm();
+
+        let make_triangle = |a, b, c| {
+            Triangle::from_meta(TriangleMeta {
+                a,
+                b,
+                c,
+                abc_nc: Matrix3::from_cols(n1, n1, n1),
+                abc_uv: Matrix3::ONE,
+                material: 0,
+            })
+            .unwrap()
+        };
+
+        [
+            make_triangle(center - u - v, center + u - v, center + u + v),
+            make_triangle(center - u - v, center + u + v, center - u + v),
+        ]
+    };
+
+    let axes = [ff32_3::EX, ff32_3::EY, ff32_3::EZ].map(|axis| axis * ff32(1.5));
+    let mut triangles = Vec::new();
+
+    for i in 0..27 {
+        let center = v(
+            (i % 3) as f32 - 1.0,
+            (i / 3 % 3) as f32 - 1.0,
+            (i / 9) as f32 - 1.0,
+        );
+        let center = center * ff32(4.0);
+
+        for k in 0..3 {
+            let (n, u, v) = (axes[k], axes[(k + 1) % 3], axes[(k + 2) % 3]);
+            triangles.extend(quad(center + n, u, v));
+            triangles.extend(quad(center - n, v, u));
+        }
+    }
+
+    triangles
+}
+
+/// Rays from a few points to the corners and the edge midpoints of the cubes of `cube_grid`.
+fn cube_grid_rays() -> Vec<Ray<ff32>> {
+    let sources = [
+        v(0.3, 0.7, 26.0),
+        v(-20.0, 15.0, -10.0),
+        v(17.0, -30.0, 1.0),
+    ];
+    let coords = [-5.5, -4.0, -2.5, -1.5, 0.0, 1.5, 2.5, 4.0, 5.5];
+
+    let mut rays = Vec::new();
+
+    for (i, &x) in coords.iter().enumerate() {
+        for &y in coords.iter() {
+            for &z in coords.iter() {
+                let src = sources[i % sources.len()];
+                let dir1 = (v(x, y, z) - src).norm();
+                rays.push(Ray { src, dir1 });
+            }
+        }
+    }
+
+    rays
+}
+
+#[test]
+fn triangles_in_split_planes() {
+    let triangles = cube_grid();
+    let rays = cube_grid_rays();
+
+    let kd = BspTree::build_kd(&triangles);
+    assert_rays_match::<_, 4>(&kd.with_triangles(&triangles), &rays);
+    assert_rays_match::<_, 8>(&kd.with_triangles(&triangles), &rays);
+
+    let kd_sah = BspTree::build_kd_sah(&triangles, &KdSahOptions::default());
+    assert_rays_match::<_, 4>(&kd_sah.with_triangles(&triangles), &rays);
+    assert_rays_match::<_, 8>(&kd_sah.with_triangles(&triangles), &rays);
+}
+
+#[test]
+fn utah_teapot_bvh() {
+    let model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    let triangles = model.to_triangle_list();
+
+    // casts the rays one by one
+    let bvh = Bvh::build(&triangles.triangles);
+    let bvh = bvh.with_triangles(&triangles.triangles);
+    assert_packets_match::<_, 4>(&bvh);
+}
+
+#[test]
+fn min_distance() {
+    // coarser than in the `f64` tests, for the triangle matrices to stay invertible in `f32`
+    let mut triangles = make_uv_sphere(f32_3::ZERO, 5.0, 16, 32);
+    triangles
+        .triangles
+        .extend(make_uv_sphere(f32_3::ZERO, 2.0, 16, 32).triangles);
+
+    let tree = BspTree::build_kd_sah(&triangles.triangles, &KdSahOptions::default());
+    let tree = tree.with_triangles(&triangles.triangles);
+
+    // cross the spheres at about 5, 8, 12 and 15
+    let rays: [Ray<f32>; 4] = std::array::from_fn(|i| Ray {
+        src: f32_3::new(0.0, 0.0, 10.0),
+        dir1: f32_3::new(0.013 * i as f32, 0.021, -1.0).norm(),
+    });
+
+    for min_d in [0.0, 6.0, 12.5, 15.5] {
+        for isecs in [
+            triangles.cast_packet(&rays, min_d, 100.0),
+            tree.cast_packet(&rays, min_d, 100.0),
+        ] {
+            for (ray, isec) in rays.iter().zip(isecs) {
+                let expected = triangles.cast_ray(*ray, min_d, 100.0);
+
+                assert_eq!(isec.is_some(), expected.is_some(), "{min_d} {ray:?}");
+
+                if let (Some(isec), Some(expected)) = (isec, expected) {
+                    assert!((isec.d - expected.d).abs() < 1e-3, "{min_d} {ray:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use deer2::cast::*;
+use deer2::math::*;
+use deer2::primitives::*;
+
+fn make_triangle(a: f64_3, b: f64_3, c: f64_3) -> Triangle<f64> {
+    let n1 = f64_3::cross(b - a, c - a).norm();
+
+    Triangle {
+        a,
+        m_abc: Matrix3::from_cols(b - a, c - a, n1).inv().unwrap(),
+        meta: Box::new(TriangleMeta {
+            a,
+            b,
+            c,
+            abc_nc: Matrix3::from_cols(n1, n1, n1),
+            abc_uv: Matrix3::ONE,
+            material: 0,
+        }),
+    }
+}
+
+fn ray(src: f64_3, dir: f64_3) -> Ray<f64> {
+    Ray {
+        src,
+        dir1: dir.norm(),
+    }
+}
+
+/// Spheres placed by scaling and moving a shared unit one.
+const SPHERES: [(f64, [f64; 3]); 4] = [
+    (2.0, [3.0, 0.0, 0.0]),
+    (1.0, [-4.0, 1.0, 0.0]),
+    (1.5, [0.0, 0.5, -6.0]),
+    (0.5, [0.5, -2.0, 3.0]),
+];
+
+fn spheres_scene() -> Scene<f64> {
+    let mut scene = Scene::new();
+    let sphere = scene.add_mesh(make_uv_sphere(f64_3::ZERO, 1.0, 16, 32));
+
+    // left out of the object BVH
+    let empty = scene.add_mesh(TriangleList { triangles: vec![] });
+    scene.add_object(empty, Affine3::ONE);
+
+    for (radius, [x, y, z]) in SPHERES {
+        let transform = Affine3::translation(f64_3::new(x, y, z))
+            * Affine3::scaling(f64_3::new(radius, radius, radius));
+        scene.add_object(sphere, transform);
+    }
+
+    scene
+}
+
+/// Sphere triangles moved into world coordinates one by one;
+/// the slivers at the poles can become degenerate and are skipped.
+fn spheres_flattened() -> TriangleList<f64> {
+    let sphere = make_uv_sphere(f64_3::ZERO, 1.0, 16, 32);
+    let mut triangles = TriangleList { triangles: vec![] };
+
+    for (radius, [x, y, z]) in SPHERES {
+        let place = |p: f64_3| p * radius + f64_3::new(x, y, z);
+
+        for tri in &sphere.triangles {
+            let (a, b, c) = (place(tri.meta.a), place(tri.meta.b), place(tri.meta.c));
+            let n1 = f64_3::cross(b - a, c - a).norm();
+
+            let Some(m_abc) = Matrix3::from_cols(b - a, c - a, n1).inv() else {
+                continue;
+            };
+
+            triangles.triangles.push(Triangle {
+                a,
+                m_abc,
+                meta: Box::new(TriangleMeta {
+                    a,
+                    b,
+                    c,
+                    // uniform scaling keeps the directions
+                    abc_nc: tri.meta.abc_nc,
+                    abc_uv: tri.meta.abc_uv,
+                    material: tri.meta.material,
+                }),
+            });
+        }
+    }
+
+    triangles
+}
+
+#[test]
+fn instances_match_flattened() {
+    let scene = spheres_scene();
+    let scene_bvh = SceneBvh::build(&scene).unwrap();
+    let flattened = spheres_flattened();
+
+    let bounds = scene_bvh.bounds().unwrap();
+    assert!((bounds.min_coords - f64_3::new(-5.0, -2.5, -7.5)).abs() < 1e-9);
+    assert!((bounds.max_coords - f64_3::new(5.0, 2.0, 3.5)).abs() < 1e-9);
+
+    let cameras = [f64_3::new(0.3, 0.2, 20.0), f64_3::new(-15.0, 4.0, -3.0)];
+
+    for src in cameras {
+        for i in 0..24 {
+            for j in 0..24 {
+                // off the grid, to stay clear of vertices shared by many triangles
+                let target = f64_3::new(i as f64 * 0.97 - 11.3, j as f64 * 0.53 - 6.1, -1.0);
+                let ray = ray(src, target - src);
+
+                for culling in [Culling::Back, Culling::Front, Culling::None] {
+                    let isec = scene_bvh.cast_ray_culled(ray, 0.0, 100.0, culling);
+                    let expected = flattened.cast_ray_culled(ray, 0.0, 100.0, culling);
+
+                    assert_eq!(isec.is_some(), expected.is_some(), "{ray:?}");
+
+                    if let (Some(isec), Some(expected)) = (isec, expected) {
+                        assert!((isec.d - expected.d).abs() < 1e-9, "{ray:?}");
+                        assert_eq!(isec.is_front_face, expected.is_front_face);
+                        assert!(isec.world_to_mesh.is_some());
+
+                        let n1_p = isec.interpolate_meta().n1_p;
+                        let expected_n1_p = expected.interpolate_meta().n1_p;
+                        assert!((n1_p - expected_n1_p).abs() < 1e-9, "{ray:?}");
+                    }
+                }
+
+                assert_eq!(
+                    scene_bvh.is_occluded(ray, 0.0, 100.0),
+                    flattened.is_occluded(ray, 0.0, 100.0)
+                );
+
+                let all: Vec<_> = scene_bvh.cast_ray_all(ray, 0.0, 100.0);
+                let expected_all = flattened.cast_ray_all(ray, 0.0, 100.0);
+                assert_eq!(all.len(), expected_all.len(), "{ray:?}");
+
+                for (isec, expected) in all.iter().zip(&expected_all) {
+                    assert!((isec.d - expected.d).abs() < 1e-9, "{ray:?}");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn non_uniform_scaling() {
+    // in the x + y = 0 plane, the normal points to -x - y
+    let mut mesh = TriangleList { triangles: vec![] };
+    mesh.triangles.push(make_triangle(
+        f64_3::new(-1.0, 1.0, -1.0),
+        f64_3::new(1.0, -1.0, -1.0),
+        f64_3::new(0.0, 0.0, 2.0),
+    ));
+
+    let mut scene = Scene::new();
+    let mesh = scene.add_mesh(mesh);
+    scene.add_object(mesh, Affine3::scaling(f64_3::new(2.0, 1.0, 1.0)));
+    scene.add_object(
+        mesh,
+        Affine3::translation(f64_3::new(0.0, 0.0, 10.0))
+            * Affine3::scaling(f64_3::new(-1.0, 1.0, 1.0)),
+    );
+
+    let scene_bvh = SceneBvh::build(&scene).unwrap();
+
+    // into the x / 2 + y = 0 plane
+    let isec = scene_bvh
+        .cast_ray(ray(f64_3::new(-10.0, 0.0, 0.0), f64_3::EX), 0.0, 100.0)
+        .unwrap();
+    assert!((isec.d - 10.0).abs() < 1e-9);
+    assert!(isec.is_front_face);
+
+    let n1_p = isec.interpolate_meta().n1_p;
+    assert!((n1_p - f64_3::new(-1.0, -2.0, 0.0).norm()).abs() < 1e-9);
+
+    // the mirrored copy, in the x = y plane; its front side faces +x
+    let ray_10 = ray(f64_3::new(10.0, 0.0, 10.0), -f64_3::EX);
+    let isec = scene_bvh.cast_ray(ray_10, 0.0, 100.0).unwrap();
+    assert!((isec.d - 10.0).abs() < 1e-9);
+
+    let n1_p = isec.interpolate_meta().n1_p;
+    assert!((n1_p - f64_3::new(1.0, -1.0, 0.0).norm()).abs() < 1e-9);
+
+    assert!(scene_bvh
+        .cast_ray(ray(f64_3::new(-10.0, 0.0, 10.0), f64_3::EX), 0.0, 100.0)
+        .is_none());
+}
+
+#[test]
+fn empty() {
+    let scene = Scene::<f64>::new();
+    let scene_bvh = SceneBvh::build(&scene).unwrap();
+
+    let ray = ray(f64_3::ZERO, f64_3::EZ);
+
+    assert!(scene_bvh.bounds().is_none());
+    assert!(scene_bvh.cast_ray(ray, 0.0, 100.0).is_none());
+    assert!(!scene_bvh.is_occluded(ray, 0.0, 100.0));
+    assert!(scene_bvh.cast_ray_all(ray, 0.0, 100.0).is_empty());
+}
+
+#[test]
+fn degenerate_transform() {
+    let mut scene = Scene::new();
+    let sphere = scene.add_mesh(make_uv_sphere(f64_3::ZERO, 1.0, 4, 8));
+    scene.add_object(sphere, Affine3::scaling(f64_3::new(1.0, 0.0, 1.0)));
+
+    assert!(SceneBvh::build(&scene).is_none());
+}
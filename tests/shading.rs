@@ -0,0 +1,290 @@
+use deer2::cast::*;
+use deer2::image::*;
+use deer2::math::*;
+use deer2::render::*;
+
+fn v(x: f32, y: f32, z: f32) -> ff32_3 {
+    ff32_3::new(ff32(x), ff32(y), ff32(z))
+}
+
+/// Square around `center` with the normal along `u × v`.
+fn quad(center: ff32_3, u: ff32_3, v: ff32_3, material: usize) -> [Triangle<ff32>; 2] {
+    let n1 = ff32_3::cross(u, v).norm();
+
+    let make_triangle = |a, b, c| {
+        Triangle::from_meta(TriangleMeta {
+            a,
+            b,
+            c,
+            abc_nc: Matrix3::from_cols(n1, n1, n1),
+            abc_uv: Matrix3::ONE,
+            material,
+        })
+        .unwrap()
+    };
+
+    [
+        make_triangle(center - u - v, center + u - v, center + u + v),
+        make_triangle(center - u - v, center + u + v, center - u + v),
+    ]
+}
+
+fn ray(src: ff32_3, dir: ff32_3) -> Ray<ff32> {
+    Ray {
+        src,
+        dir1: dir.norm(),
+    }
+}
+
+fn assert_close(color: f32_rgb, expected: f32_rgb, eps: f32) {
+    let diff = [
+        color.0 - expected.0,
+        color.1 - expected.1,
+        color.2 - expected.2,
+    ];
+
+    assert!(
+        diff.iter().all(|d| d.abs() < eps),
+        "{color:?} != {expected:?}"
+    );
+}
+
+/// Facing +y, in the y = 0 plane.
+fn floor(material: usize) -> [Triangle<ff32>; 2] {
+    quad(
+        ff32_3::ZERO,
+        ff32_3::EZ * ff32(10.0),
+        ff32_3::EX * ff32(10.0),
+        material,
+    )
+}
+
+#[test]
+fn diffuse() {
+    let albedo = f32_rgb(1.0, 0.5, 0.25);
+
+    let mut triangles = TriangleList { triangles: vec![] };
+    triangles.triangles.extend(floor(0));
+    // facing the floor, to shadow (0.3, 0, -0.2) from light along (1, 1, 0)
+    triangles
+        .triangles
+        .extend(quad(v(2.0, 2.0, 0.0), ff32_3::EX, ff32_3::EZ, 0));
+
+    let shader = Shader {
+        materials: vec![Material::Diffuse { albedo }],
+        lights: vec![DirectionalLight {
+            dir1: v(1.0, 1.0, 0.0).norm(),
+            radiance: f32_rgb(0.8, 0.8, 0.8),
+        }],
+        ambient: f32_rgb(0.2, 0.2, 0.2),
+        ..Shader::default()
+    };
+
+    let lit = shader.shade(&triangles, ray(v(-2.3, 5.0, -0.2), -ff32_3::EY));
+    let lambert = 0.2 + 0.8 * std::f32::consts::FRAC_1_SQRT_2;
+    assert_close(lit, albedo * lambert, 1e-5);
+
+    let shadowed = shader.shade(&triangles, ray(v(0.3, 5.0, -0.2), -ff32_3::EY));
+    assert_close(shadowed, albedo * 0.2, 1e-5);
+
+    // from below, the back side is culled, and nothing else is there
+    let background = f32_rgb(0.1, 0.2, 0.3);
+    let shader = Shader {
+        background,
+        ..shader
+    };
+    let missed = shader.shade(&triangles, ray(v(-0.3, -5.0, -0.2), ff32_3::EY));
+    assert_eq!(missed, background);
+}
+
+#[test]
+fn missing_material() {
+    let triangles = TriangleList {
+        triangles: floor(3).into(),
+    };
+
+    let shader = Shader {
+        materials: vec![Material::Diffuse {
+            albedo: f32_rgb(1.0, 0.0, 0.0),
+        }],
+        ambient: f32_rgb(0.5, 0.5, 0.5),
+        ..Shader::default()
+    };
+
+    // the default white diffuse material
+    let color = shader.shade(&triangles, ray(v(0.3, 5.0, -0.2), -ff32_3::EY));
+    assert_eq!(color, f32_rgb(0.5, 0.5, 0.5));
+}
+
+#[test]
+fn mirror() {
+    let albedo = f32_rgb(0.3, 0.6, 0.9);
+    let tint = f32_rgb(0.9, 0.8, 0.7);
+
+    let mut triangles = TriangleList { triangles: vec![] };
+    triangles.triangles.extend(floor(1));
+    // facing +x, in the x = -5 plane
+    triangles.triangles.extend(quad(
+        v(-5.0, 0.0, 0.0),
+        ff32_3::EY * ff32(10.0),
+        ff32_3::EZ * ff32(10.0),
+        0,
+    ));
+
+    let shader = Shader {
+        materials: vec![Material::Diffuse { albedo }, Material::Mirror { tint }],
+        ambient: f32_rgb(1.0, 1.0, 1.0),
+        ..Shader::default()
+    };
+
+    // reflected off (0.3, 0, -0.2) onto the wall
+    let camera_ray = ray(v(5.3, 5.0, -0.2), v(-1.0, -1.0, 0.0));
+    assert_close(shader.shade(&triangles, camera_ray), tint * albedo, 1e-5);
+
+    let shader = Shader {
+        max_depth: 0,
+        ..shader
+    };
+    assert_eq!(shader.shade(&triangles, camera_ray), f32_rgb::default());
+}
+
+#[test]
+fn glossy() {
+    let albedo = f32_rgb(0.5, 0.25, 0.125);
+    let specular = f32_rgb(0.5, 0.5, 0.5);
+    let shininess = 8.0;
+
+    let triangles = TriangleList {
+        triangles: floor(0).into(),
+    };
+
+    let shader = Shader {
+        materials: vec![Material::Glossy {
+            albedo,
+            specular,
+            shininess,
+        }],
+        lights: vec![DirectionalLight {
+            dir1: ff32_3::EY,
+            radiance: f32_rgb(1.0, 1.0, 1.0),
+        }],
+        ..Shader::default()
+    };
+
+    // the highlight is brightest with the view along the reflected light
+    let above = shader.shade(&triangles, ray(v(0.3, 5.0, -0.2), -ff32_3::EY));
+    assert_close(above, albedo + specular, 1e-5);
+
+    // the half vector is 22.5 degrees off the normal
+    let oblique = shader.shade(&triangles, ray(v(5.3, 5.0, -0.2), v(-1.0, -1.0, 0.0)));
+    let highlight = std::f32::consts::FRAC_PI_8.cos().powf(shininess);
+    assert_close(oblique, albedo + specular * highlight, 1e-5);
+}
+
+#[test]
+fn glass() {
+    let mut triangles = TriangleList { triangles: vec![] };
+    // a slab between z = -1 and z = 0, with normals pointing outside
+    triangles.triangles.extend(quad(
+        ff32_3::ZERO,
+        ff32_3::EX * ff32(2.0),
+        ff32_3::EY * ff32(2.0),
+        1,
+    ));
+    triangles.triangles.extend(quad(
+        v(0.0, 0.0, -1.0),
+        ff32_3::EY * ff32(2.0),
+        ff32_3::EX * ff32(2.0),
+        1,
+    ));
+    // behind it
+    triangles.triangles.extend(quad(
+        v(0.0, 0.0, -10.0),
+        ff32_3::EX * ff32(20.0),
+        ff32_3::EY * ff32(20.0),
+        0,
+    ));
+
+    let shader = Shader {
+        materials: vec![
+            Material::default(),
+            Material::Glass {
+                ior: 1.5,
+                tint: f32_rgb(1.0, 1.0, 1.0),
+            },
+        ],
+        ambient: f32_rgb(1.0, 1.0, 1.0),
+        ..Shader::default()
+    };
+
+    // 4% is reflected at each side, plus a little from light bouncing inside
+    let color = shader.shade(&triangles, ray(v(0.3, -0.2, 10.0), -ff32_3::EZ));
+    assert_close(color, f32_rgb(0.9216, 0.9216, 0.9216), 0.005);
+
+    // the glass darkens the backdrop when no rays go through
+    let shader = Shader {
+        max_depth: 0,
+        ..shader
+    };
+    let color = shader.shade(&triangles, ray(v(0.3, -0.2, 10.0), -ff32_3::EZ));
+    assert_eq!(color, f32_rgb::default());
+}
+
+#[test]
+fn fresnel() {
+    // glass at normal incidence, from either side
+    assert!((fresnel_reflectance(1.0, 1.0 / 1.5) - 0.04).abs() < 1e-12);
+    assert!((fresnel_reflectance(1.0, 1.5) - 0.04).abs() < 1e-12);
+
+    // grazing
+    assert!((fresnel_reflectance(1e-9, 1.0 / 1.5) - 1.0).abs() < 1e-6);
+
+    // past the critical angle of about 41.8 degrees, going out
+    assert_eq!(fresnel_reflectance(0.5, 1.5), 1.0);
+
+    let n1 = f64_3::EZ;
+    let dir1 = f64_3::new(0.0, 3.0_f64.sqrt() / 2.0, -0.5);
+    assert!(refract(-dir1, -n1, 1.5).is_none());
+
+    // Snell's law going in
+    let refracted = refract(dir1, n1, 1.0 / 1.5).unwrap();
+    assert!((refracted.abs() - 1.0).abs() < 1e-12);
+    assert!((refracted.y() - dir1.y() / 1.5).abs() < 1e-12);
+    assert!(refracted.z() < 0.0);
+
+    let reflected = reflect(dir1, n1);
+    assert!((reflected - f64_3::new(0.0, dir1.y(), 0.5)).abs() < 1e-12);
+}
+
+#[test]
+fn object_materials() {
+    let red = f32_rgb(1.0, 0.0, 0.0);
+    let green = f32_rgb(0.0, 1.0, 0.0);
+
+    let mut scene = Scene::new();
+    let mesh = scene.add_mesh(TriangleList {
+        triangles: floor(0).into(),
+    });
+
+    scene.add_object(mesh, Affine3::ONE);
+    scene
+        .add_object(mesh, Affine3::translation(v(30.0, 0.0, 0.0)))
+        .material = Some(1);
+
+    let scene_bvh = SceneBvh::build(&scene).unwrap();
+
+    let shader = Shader {
+        materials: vec![
+            Material::Diffuse { albedo: red },
+            Material::Diffuse { albedo: green },
+        ],
+        ambient: f32_rgb(1.0, 1.0, 1.0),
+        ..Shader::default()
+    };
+
+    let own = shader.shade(&scene_bvh, ray(v(0.3, 5.0, -0.2), -ff32_3::EY));
+    assert_eq!(own, red);
+
+    let overridden = shader.shade(&scene_bvh, ray(v(30.3, 5.0, -0.2), -ff32_3::EY));
+    assert_eq!(overridden, green);
+}
@@ -1,4 +1,5 @@
 use deer2::formats::stl::*;
+use deer2::formats::FormatError;
 
 use std::io::Cursor;
 
@@ -17,3 +18,132 @@ fn utah_teapot() {
 
     assert_eq!(source, result);
 }
+
+#[test]
+fn utah_teapot_ascii() {
+    let binary_model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+
+    let mut ascii = Vec::<u8>::new();
+    binary_model.write_ascii_to(&mut ascii).unwrap();
+
+    assert_eq!(StlFormat::detect(&ascii), StlFormat::Ascii);
+
+    let ascii_model = StlModel::read_from(&mut Cursor::new(&ascii)).unwrap();
+
+    assert_eq!(ascii_model.header, "Exported from Blender-2.74 (sub 5)");
+    assert_eq!(ascii_model.triangles.len(), binary_model.triangles.len());
+
+    for (a, b) in ascii_model
+        .triangles
+        .iter()
+        .zip(binary_model.triangles.iter())
+    {
+        assert_eq!((a.n, a.a, a.b, a.c), (b.n, b.a, b.b, b.c));
+    }
+}
+
+#[test]
+fn ascii_multiple_solids() {
+    let source = "
+        solid first
+          facet normal 0 0 1
+            outer loop
+              vertex 0 0 0
+              vertex 1 0 0
+              vertex 0 1 0
+            endloop
+          endfacet
+        endsolid first
+        solid
+          facet normal 0 0 -1.0e0
+            outer loop
+              vertex 0 0 0
+              vertex 0 1 0
+              vertex 1 0 0
+            endloop
+          endfacet
+        endsolid
+    ";
+
+    let model = StlModel::read_from(&mut Cursor::new(source)).unwrap();
+
+    assert_eq!(model.header, "first");
+    assert_eq!(model.triangles.len(), 2);
+    assert_eq!(model.triangles[1].n.2 .0, -1.0);
+    assert_eq!(model.triangles[1].c.0 .0, 1.0);
+}
+
+#[test]
+fn ascii_degenerate_facet() {
+    let source = "
+        solid cad
+          facet normal 0 0 1
+            outer loop
+              vertex 0 0 0
+              vertex 1 0 0
+              vertex 0 1 0
+            endloop
+          endfacet
+          facet normal 0 0 0
+            outer loop
+              vertex 0 0 0
+              vertex 1 1 0
+              vertex 2 2 0
+            endloop
+          endfacet
+          facet normal 0 0 1
+            outer loop
+              vertex 1 0 0
+              vertex 1 0 0
+              vertex 0 1 0
+            endloop
+          endfacet
+        endsolid cad
+    ";
+
+    let model = StlModel::read_from(&mut Cursor::new(source)).unwrap();
+    assert_eq!(model.triangles.len(), 3);
+
+    let triangles = model.to_triangle_list();
+    assert_eq!(triangles.triangles.len(), 1);
+    assert_eq!(triangles.triangles[0].meta.b, model.triangles[0].b);
+}
+
+#[test]
+fn ascii_truncated() {
+    let source = "solid broken\n  facet normal 0 0 1\n    outer loop\n      vertex 0 0";
+
+    assert!(StlModel::read_from(&mut Cursor::new(source)).is_err());
+}
+
+#[test]
+fn binary_with_solid_header() {
+    let mut model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    model.header = "solid teapot".to_string();
+
+    let mut binary = Vec::<u8>::new();
+    model.write_to(&mut binary).unwrap();
+
+    assert_eq!(StlFormat::detect(&binary), StlFormat::Binary);
+
+    let result = StlModel::read_from(&mut Cursor::new(&binary)).unwrap();
+    assert_eq!(result.triangles.len(), model.triangles.len());
+}
+
+#[test]
+fn binary_truncated() {
+    let mut source = vec![0u8; 84];
+    source[80..84].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    let result = StlModel::read_binary_from(&mut Cursor::new(&source));
+    assert!(matches!(result, Err(FormatError::Truncated)));
+}
+
+#[test]
+fn oversize_header() {
+    let mut model = StlModel::read_from(&mut Cursor::new(UTAH_TEAPOT)).unwrap();
+    model.header = "x".repeat(81);
+
+    let result = model.write_to(&mut Vec::<u8>::new());
+    assert!(matches!(result, Err(FormatError::OversizeDimension(_))));
+}
@@ -1,4 +1,6 @@
 use deer2::formats::tga::*;
+use deer2::formats::FormatError;
+use deer2::image::*;
 
 use std::io::Cursor;
 
@@ -17,3 +19,238 @@ fn lena() {
 
     assert_eq!(source, result);
 }
+
+fn round_trip(bitmap: &TgaBitmap) -> TgaBitmap {
+    let mut bytes = Vec::<u8>::new();
+    bitmap.write_to(&mut bytes).unwrap();
+
+    TgaBitmap::read_from(&mut Cursor::new(&bytes)).unwrap()
+}
+
+fn assert_same_pixels(a: &TgaBitmap, b: &TgaBitmap) {
+    assert_eq!((a.width(), a.height()), (b.width(), b.height()));
+
+    for y in 0..a.height() {
+        for x in 0..a.width() {
+            assert_eq!(a.get(x, y), b.get(x, y), "pixel ({x}, {y})");
+        }
+    }
+}
+
+#[test]
+fn lena_rle() {
+    let bitmap = TgaBitmap::read_from(&mut Cursor::new(LENA)).unwrap();
+
+    let mut rle = TgaBitmap::read_from(&mut Cursor::new(LENA)).unwrap();
+    rle.set_encoding(ImageType::RleTrueColor, 24, 0).unwrap();
+
+    let result = round_trip(&rle);
+
+    assert_eq!(result.image_type(), ImageType::RleTrueColor);
+    assert_same_pixels(&bitmap, &result);
+}
+
+#[test]
+fn bottom_to_top_right_to_left() {
+    let bitmap = TgaBitmap::read_from(&mut Cursor::new(LENA)).unwrap();
+
+    let mut flipped = TgaBitmap::read_from(&mut Cursor::new(LENA)).unwrap();
+    flipped.set_orientation(false, true);
+
+    let mut bytes = Vec::<u8>::new();
+    flipped.write_to(&mut bytes).unwrap();
+
+    // first pixel in the file is the bottom-right one, stored as BGR
+    let last = bitmap.get(bitmap.width() - 1, bitmap.height() - 1);
+    assert_eq!(bytes[18..21], [last.2, last.1, last.0]);
+
+    let result = TgaBitmap::read_from(&mut Cursor::new(&bytes)).unwrap();
+
+    assert!(!result.is_top_to_bottom());
+    assert!(result.is_right_to_left());
+    assert_same_pixels(&bitmap, &result);
+}
+
+#[test]
+fn alpha_32_bit() {
+    let mut bitmap = TgaBitmap::with_dimensions(3, 2, u8_rgb(10, 20, 30)).unwrap();
+    bitmap.set_encoding(ImageType::RleTrueColor, 32, 8).unwrap();
+    *bitmap.get_alpha_mut(1, 1) = 77;
+
+    let result = round_trip(&bitmap);
+
+    assert!(result.has_alpha());
+    assert_eq!(result.get(1, 1), u8_rgb(10, 20, 30));
+    assert_eq!(result.get_alpha(1, 1), 77);
+    assert_eq!(result.get_alpha(0, 0), 255);
+}
+
+#[test]
+fn true_color_16_bit() {
+    let mut bitmap = TgaBitmap::with_dimensions(2, 2, u8_rgb(255, 0, 255)).unwrap();
+    *bitmap.get_mut(1, 0) = u8_rgb(0, 255, 0);
+    *bitmap.get_alpha_mut(0, 1) = 0;
+    bitmap.set_encoding(ImageType::TrueColor, 16, 1).unwrap();
+
+    let result = round_trip(&bitmap);
+
+    assert_same_pixels(&bitmap, &result);
+    assert_eq!(result.get_alpha(0, 1), 0);
+    assert_eq!(result.get_alpha(1, 1), 255);
+}
+
+#[test]
+fn grayscale() {
+    let mut bitmap = TgaBitmap::with_dimensions(4, 4, u8_rgb(1, 2, 3)).unwrap();
+    *bitmap.get_mut(2, 3) = u8_rgb(200, 100, 50);
+    bitmap.set_encoding(ImageType::RleGrayscale, 8, 0).unwrap();
+
+    let result = round_trip(&bitmap);
+
+    assert_eq!(result.get(0, 0), u8_rgb(2, 2, 2));
+    assert_eq!(result.get(2, 3), u8_rgb(100, 100, 100));
+}
+
+#[test]
+fn color_mapped() {
+    let palette = [u8_rgb(0, 0, 0), u8_rgb(255, 0, 0), u8_rgb(0, 0, 255)];
+
+    let mut bitmap = TgaBitmap::with_dimensions(5, 3, palette[1]).unwrap();
+    *bitmap.get_mut(4, 2) = palette[2];
+    *bitmap.get_mut(0, 1) = palette[0];
+
+    assert!(bitmap.set_encoding(ImageType::ColorMapped, 8, 0).is_err());
+
+    bitmap.set_color_map(&palette).unwrap();
+
+    for image_type in [ImageType::ColorMapped, ImageType::RleColorMapped] {
+        bitmap.set_encoding(image_type, 8, 0).unwrap();
+        assert_same_pixels(&bitmap, &round_trip(&bitmap));
+    }
+
+    *bitmap.get_mut(0, 0) = u8_rgb(1, 2, 3);
+
+    let mut bytes = Vec::<u8>::new();
+    assert!(bitmap.write_to(&mut bytes).is_err());
+}
+
+#[test]
+fn bottom_to_top_file() {
+    #[rustfmt::skip]
+    let source: &[u8] = &[
+        0, 0, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 2, 0, 8, 0,
+        1, 2, // bottom row
+        3, 4, // top row
+    ];
+
+    let bitmap = TgaBitmap::read_from(&mut Cursor::new(source)).unwrap();
+
+    assert_eq!(bitmap.get(0, 0), u8_rgb(3, 3, 3));
+    assert_eq!(bitmap.get(1, 1), u8_rgb(2, 2, 2));
+
+    let mut result = Vec::<u8>::new();
+    bitmap.write_to(&mut result).unwrap();
+
+    assert_eq!(source, result);
+}
+
+#[test]
+fn extension_area() {
+    let mut bitmap = TgaBitmap::with_dimensions(2, 2, u8_rgb(1, 2, 3)).unwrap();
+
+    bitmap.set_extension(Some(TgaExtension {
+        author_name: "deer2".to_string(),
+        software_version: (100, b' '),
+        gamma: (22, 10),
+        attributes_type: 3,
+        ..Default::default()
+    }));
+
+    let mut bytes = Vec::<u8>::new();
+    bitmap.write_to(&mut bytes).unwrap();
+
+    assert!(bytes.ends_with(b"TRUEVISION-XFILE.\0"));
+
+    let result = TgaBitmap::read_from(&mut Cursor::new(&bytes)).unwrap();
+    let extension = result.extension().unwrap();
+
+    assert_eq!(extension.author_name, "deer2");
+    assert_eq!(extension.gamma, (22, 10));
+    assert_eq!(extension.attributes_type, 3);
+
+    let mut result_bytes = Vec::<u8>::new();
+    result.write_to(&mut result_bytes).unwrap();
+
+    assert_eq!(bytes, result_bytes);
+}
+
+#[test]
+fn truncated() {
+    let source = &LENA[..LENA.len() - 1];
+    let result = TgaBitmap::read_from(&mut Cursor::new(source));
+    assert!(matches!(result, Err(FormatError::Truncated)));
+}
+
+#[test]
+fn invalid_header() {
+    let mut source = LENA.to_vec();
+    source[2] = 42; // image type
+    let result = TgaBitmap::read_from(&mut Cursor::new(&source));
+    assert!(matches!(result, Err(FormatError::InvalidHeader(_))));
+
+    let mut source = LENA.to_vec();
+    source[1] = 7; // color map type
+    let result = TgaBitmap::read_from(&mut Cursor::new(&source));
+    assert!(matches!(result, Err(FormatError::InvalidHeader(_))));
+}
+
+#[test]
+fn oversize_dimensions() {
+    let mut source = LENA[..18].to_vec();
+    source[12..16].copy_from_slice(&[0xFF, 0xFF, 0xFF, 0xFF]);
+
+    let result = TgaBitmap::read_from(&mut Cursor::new(&source));
+    assert!(matches!(result, Err(FormatError::Truncated)));
+}
+
+#[test]
+fn unused_color_map() {
+    // true color, with a color map of one 8-bit entry
+    let source = [
+        0, 1, 2, 0, 0, 1, 0, 8, 0, 0, 0, 0, 1, 0, 1, 0, 24, 0x20, // header
+        7,    // color map
+        1, 2, 3, // pixel
+    ];
+
+    let result = TgaBitmap::read_from(&mut Cursor::new(&source));
+    assert!(matches!(result, Err(FormatError::Unsupported(_))));
+
+    // the same with a 24-bit entry, which is skipped
+    let source = [
+        0, 1, 2, 0, 0, 1, 0, 24, 0, 0, 0, 0, 1, 0, 1, 0, 24, 0x20, // header
+        7, 8, 9, // color map
+        1, 2, 3, // pixel
+    ];
+
+    let result = TgaBitmap::read_from(&mut Cursor::new(&source)).unwrap();
+    assert_eq!(result.get(0, 0), u8_rgb(3, 2, 1));
+}
+
+#[test]
+fn oversize_image() {
+    let image = Image::new(65536, 1, u8_rgb(0, 0, 0));
+    let result = TgaBitmap::try_from(image);
+    assert!(matches!(result, Err(FormatError::OversizeDimension(_))));
+}
+
+#[test]
+fn rgba_image() {
+    let mut image = Image::new(2, 2, u8_rgba(1, 2, 3, 255));
+    *image.get_mut(1, 1) = u8_rgba(4, 5, 6, 7);
+
+    let bitmap = TgaBitmap::try_from(&image).unwrap();
+    assert!(bitmap.has_alpha());
+
+    let result = round_trip(&bitmap);
+    assert_eq!(result.to_rgba_image(), image);
+}
@@ -0,0 +1,68 @@
+use deer2::image::*;
+use deer2::render::*;
+
+use rand::Rng;
+
+fn renderer(tile_size: usize, n_threads: usize) -> TileRenderer {
+    TileRenderer {
+        tile_size,
+        n_threads,
+        seed: 117,
+    }
+}
+
+#[test]
+fn tiles_cover_image() {
+    let tiles = renderer(16, 1).tiles(40, 20);
+    assert_eq!(tiles.len(), 3 * 2);
+
+    let mut coverage = Image::new(40, 20, 0);
+    for tile in &tiles {
+        for pixel_y in tile.y..tile.y + tile.height {
+            for pixel_x in tile.x..tile.x + tile.width {
+                *coverage.get_mut(pixel_x, pixel_y) += 1;
+            }
+        }
+    }
+
+    assert!(coverage.pixels().iter().all(|&n| n == 1));
+
+    let last = tiles.last().unwrap();
+    assert_eq!((last.x, last.y, last.width, last.height), (32, 16, 8, 4));
+}
+
+#[test]
+fn pixel_coordinates() {
+    let image = renderer(7, 3).render(30, 17, |pixel_x, pixel_y, _| (pixel_x, pixel_y));
+
+    for (pixel_x, pixel_y, pixel) in image.enumerate_pixels() {
+        assert_eq!(pixel, (pixel_x, pixel_y));
+    }
+}
+
+#[test]
+fn deterministic_across_thread_counts() {
+    let shade = |_, _, rng: &mut rand::rngs::SmallRng| rng.gen::<u32>();
+
+    let expected = renderer(8, 1).render(61, 35, shade);
+
+    for n_threads in [2, 3, 8, 64] {
+        assert_eq!(renderer(8, n_threads).render(61, 35, shade), expected);
+    }
+
+    // tiles do not share random sequences
+    let first_row: Vec<u32> = expected.rows().next().unwrap()[..16].to_vec();
+    assert_ne!(first_row[..8], first_row[8..]);
+
+    let reseeded = TileRenderer {
+        seed: 118,
+        ..renderer(8, 4)
+    };
+    assert_ne!(reseeded.render(61, 35, shade), expected);
+}
+
+#[test]
+fn empty_image() {
+    let image = renderer(8, 4).render(0, 0, |_, _, _| 0u8);
+    assert_eq!((image.width(), image.height()), (0, 0));
+}
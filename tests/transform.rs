@@ -0,0 +1,164 @@
+use deer2::cast::*;
+use deer2::math::*;
+use deer2::primitives::*;
+
+fn ray(src: f64_3, dir: f64_3) -> Ray<f64> {
+    Ray {
+        src,
+        dir1: dir.norm(),
+    }
+}
+
+/// Off the grid, to stay clear of vertices shared by many triangles.
+fn rays_towards_origin(src: f64_3) -> Vec<Ray<f64>> {
+    let mut rays = Vec::new();
+
+    for i in 0..20 {
+        for j in 0..20 {
+            let target = f64_3::new(i as f64 * 0.31 - 3.1, j as f64 * 0.29 - 2.9, 0.1);
+            rays.push(ray(src, target - src));
+        }
+    }
+
+    rays
+}
+
+#[test]
+fn matches_scene_object() {
+    let transform = f64_affine3::translation(f64_3::new(1.0, -2.0, 0.5))
+        * f64_affine3::rotation(f64_3::new(1.0, 1.0, 0.0), 0.6)
+        * f64_affine3::scaling(f64_3::new(1.0, 2.0, 0.5));
+
+    let sphere = make_uv_sphere(f64_3::ZERO, 1.0, 16, 32);
+    let n_triangles = sphere.triangles.len();
+
+    let mut scene = Scene::new();
+    let mesh = scene.add_mesh(sphere);
+    scene.add_object(mesh, transform);
+    let scene_bvh = SceneBvh::build(&scene).unwrap();
+
+    let mut transformed = make_uv_sphere(f64_3::ZERO, 1.0, 16, 32);
+    transformed.transform(&transform);
+    // only slivers at the poles can collapse
+    assert!(transformed.triangles.len() >= n_triangles - 2 * 32);
+
+    let mut n_hits = 0;
+
+    for src in [f64_3::new(0.5, 0.3, 10.0), f64_3::new(-8.0, 1.0, -4.0)] {
+        for ray in rays_towards_origin(src) {
+            let isec = transformed.cast_ray_culled(ray, 0.0, 100.0, Culling::None);
+            let expected = scene_bvh.cast_ray_culled(ray, 0.0, 100.0, Culling::None);
+
+            assert_eq!(isec.is_some(), expected.is_some(), "{ray:?}");
+
+            if let (Some(isec), Some(expected)) = (isec, expected) {
+                n_hits += 1;
+
+                assert!((isec.d - expected.d).abs() < 1e-9, "{ray:?}");
+                assert_eq!(isec.is_front_face, expected.is_front_face);
+
+                // vertex normals are rescaled before interpolation, not after
+                let n1_p = isec.interpolate_meta().n1_p;
+                let expected_n1_p = expected.interpolate_meta().n1_p;
+                assert!((n1_p - expected_n1_p).abs() < 0.05, "{ray:?}");
+            }
+        }
+    }
+
+    assert!(n_hits > 100);
+}
+
+#[test]
+fn normals_stay_perpendicular() {
+    let transform =
+        f64_affine3::rotation(f64_3::EZ, 0.3) * f64_affine3::scaling(f64_3::new(3.0, 1.0, 1.0));
+
+    let mut triangles = make_uv_sphere(f64_3::ZERO, 1.0, 8, 16);
+    triangles.transform(&transform);
+
+    for tri in &triangles.triangles {
+        let meta = &tri.meta;
+        let n1 = tri.m_abc.2;
+
+        // slivers at the poles are too thin for the checks below
+        if f64_3::cross(meta.b - meta.a, meta.c - meta.a).abs() < 1e-6 {
+            continue;
+        }
+
+        // the third row of `m_abc` is the unit normal
+        assert!((n1.abs() - 1.0).abs() < 1e-9);
+        assert!(f64_3::dot(n1, meta.b - meta.a).abs() < 1e-9);
+        assert!(f64_3::dot(n1, meta.c - meta.a).abs() < 1e-9);
+
+        // sphere vertex normals have unit length, and keep it
+        let abc_n = meta.abc_nc.tr();
+        for n in [abc_n.0, abc_n.1, abc_n.2] {
+            assert!((n.abs() - 1.0).abs() < 1e-9);
+        }
+
+        // flat faces have vertex normals close to the face normal
+        let face_n = (abc_n.0 + abc_n.1 + abc_n.2).norm();
+        assert!(f64_3::dot(face_n, n1).abs() > 0.8);
+    }
+}
+
+#[test]
+fn mirroring_keeps_sides() {
+    let sphere = make_uv_sphere(f64_3::ZERO, 1.0, 16, 32);
+
+    let mut mirrored = make_uv_sphere(f64_3::ZERO, 1.0, 16, 32);
+    mirrored.transform(&f64_affine3::scaling(f64_3::new(-1.0, 1.0, 1.0)));
+
+    let mirror = |v: f64_3| f64_3::new(-v.x(), v.y(), v.z());
+
+    for ray in rays_towards_origin(f64_3::new(2.5, 0.3, 10.0)) {
+        let mirrored_ray = Ray {
+            src: mirror(ray.src),
+            dir1: mirror(ray.dir1),
+        };
+
+        for culling in [Culling::Back, Culling::Front] {
+            let isec = mirrored.cast_ray_culled(mirrored_ray, 0.0, 100.0, culling);
+            let expected = sphere.cast_ray_culled(ray, 0.0, 100.0, culling);
+
+            assert_eq!(isec.is_some(), expected.is_some(), "{ray:?}");
+
+            if let (Some(isec), Some(expected)) = (isec, expected) {
+                assert!((isec.d - expected.d).abs() < 1e-9, "{ray:?}");
+
+                let n1_p = isec.interpolate_meta().n1_p;
+                let expected_n1_p = expected.interpolate_meta().n1_p;
+                assert!((n1_p - mirror(expected_n1_p)).abs() < 1e-9, "{ray:?}");
+            }
+        }
+    }
+}
+
+#[test]
+fn degenerate_triangles() {
+    let meta = |c: f64_3| TriangleMeta {
+        a: f64_3::ZERO,
+        b: f64_3::EX,
+        c,
+        abc_nc: Matrix3::ONE,
+        abc_uv: Matrix3::ONE,
+        material: 0,
+    };
+
+    assert!(Triangle::from_meta(meta(f64_3::EY)).is_some());
+    assert!(Triangle::from_meta(meta(f64_3::EX * 2.0)).is_none());
+    assert!(Triangle::from_meta(meta(f64_3::ZERO)).is_none());
+
+    // rotations keep every triangle
+    let mut triangles = make_uv_sphere(f64_3::ZERO, 1.0, 4, 8);
+    let before = triangles.triangles.len();
+    triangles.transform(&f64_affine3::rotation(f64_3::EY, 0.2));
+    assert_eq!(triangles.triangles.len(), before);
+}
+
+#[test]
+#[should_panic(expected = "degenerate transform")]
+fn degenerate_transform() {
+    let mut triangles = make_uv_sphere(f64_3::ZERO, 1.0, 4, 8);
+    triangles.transform(&f64_affine3::scaling(f64_3::new(1.0, 1.0, 0.0)));
+}